@@ -12,35 +12,481 @@ extern "C" {
 // Opaque pointer to Rust WhisperHandle
 typedef struct WhisperHandle WhisperHandle;
 
+// Opaque ring buffer of recent audio, see whisper_buffer_create()
+typedef struct WhisperBuffer WhisperBuffer;
+
+// Opaque live transcription session, see whisper_stream_create()
+typedef struct WhisperStream WhisperStream;
+
+// Error categories reported in error_code
+typedef enum {
+    WHISPER_ERROR_NONE = 0,
+    WHISPER_ERROR_INVALID_PARAMS = 1,
+    WHISPER_ERROR_MODEL_NOT_FOUND = 2,
+    WHISPER_ERROR_LOAD_FAILED = 3,
+    WHISPER_ERROR_INFERENCE_FAILED = 4,
+    WHISPER_ERROR_CONVERSION_FAILED = 5,
+    WHISPER_ERROR_CANCELLED = 6,  // Stopped by whisper_abort()
+    WHISPER_ERROR_FILE_NOT_FOUND = 7,
+    WHISPER_ERROR_INVALID_MODEL = 8,  // Not a ggml/gguf model file
+    WHISPER_ERROR_INTERNAL = 9,  // Panic caught inside the library; error holds the details
+    WHISPER_ERROR_TIMED_OUT = 10,  // Exceeded the budget given to whisper_transcribe_with_timeout()
+    WHISPER_ERROR_INVALID_AUDIO = 11,  // Samples outside [-1, 1]; see whisper_set_audio_check()
+    WHISPER_ERROR_AUDIO_TOO_LONG = 12,  // Over whisper_set_max_audio_secs(); use whisper_transcribe_long()
+    WHISPER_ERROR_CHECKSUM_MISMATCH = 13,  // Model file's SHA-256 differs, see whisper_init_verified()
+} WhisperError;
+
 // FFI-safe result structure matching Rust definition
-// Note: caller is responsible for freeing 'text' and 'error' using whisper_free_string()
+// Note: caller is responsible for freeing 'text', 'error', 'detected_lang' and 'warning' using whisper_free_string()
 typedef struct {
     bool success;
     char* text;   // Transcribed text (NULL if success=false)
     char* error;  // Error message (NULL if success=true)
+    int32_t error_code;          // WhisperError (WHISPER_ERROR_NONE on success)
+    uint64_t inference_ms;       // Wall time of the call incl. lazy model load (0 on error)
+    uint64_t audio_duration_ms;  // Input audio length at 16kHz (0 on error)
+    uint32_t segment_count;      // Segments in the text, 0 when no speech was found
+    bool has_text;               // False when a successful call produced no text
+    char* detected_lang;         // Language decoded as, e.g. "en" (NULL if unknown)
+    uint64_t non_finite_samples; // NaN/Inf input samples transcribed as silence
+    char* warning;               // Set when over 1% of samples were non-finite (NULL otherwise)
+    uint32_t fallback_count;     // Temperature fallbacks needed; several suggest unreliable text
+    float final_temperature;     // Highest temperature any 30s window was decoded at
+    bool no_speech;              // Success, but no segments or only mostly silent ones ("didn't catch that");
+                                 // false on error
 } WhisperResult;
 
+// Timed transcription segment
+typedef struct {
+    char* text;
+    int64_t start_ms;
+    int64_t end_ms;
+    float avg_logprob;     // Mean log probability of the text tokens
+    float no_speech_prob;  // Estimated share of silence in the segment (0-1)
+    bool starts_new_turn;  // Probable speaker change, see whisper_set_speaker_gap()
+    uint32_t byte_start;   // UTF-8 byte range of the segment in the array's text; a text
+    uint32_t byte_end;     // beyond 4 GiB fails the call with WHISPER_ERROR_CONVERSION_FAILED
+    char* language;        // Language of the run or window that decoded it (NULL if unknown);
+                           // whisper detects once per run, so a single call gives every segment
+                           // the same one, while whisper_transcribe_long() detects per chunk
+} WhisperSegment;
+
+// Word with timing relative to the start of the audio buffer
+typedef struct {
+    char* text;
+    int64_t start_ms;
+    int64_t end_ms;
+    float probability;
+    int64_t t_dtw;   // DTW-aligned start in ms, -1 unless DTW timestamps are enabled
+} WhisperWord;
+
+// Raw decoded token
+typedef struct {
+    int32_t id;
+    char* text;      // Raw token bytes, may be a partial UTF-8 sequence
+    float p;
+    int64_t t_dtw;   // DTW-aligned time in ms, -1 unless DTW timestamps are enabled
+    bool is_special; // Timestamp, SOT, EOT and other control tokens
+} WhisperTokenInfo;
+
+// What a WhisperArray's ptr points at
+#define WHISPER_ARRAY_NONE 0      // No elements, as returned after an internal error
+#define WHISPER_ARRAY_RESULTS 1   // WhisperResult
+#define WHISPER_ARRAY_SEGMENTS 2  // WhisperSegment
+#define WHISPER_ARRAY_WORDS 3     // WhisperWord
+#define WHISPER_ARRAY_TOKENS 4    // WhisperTokenInfo
+
+// Array returned by every array call; release with whisper_free_array(), which frees the
+// elements, their strings and the array's own strings
+typedef struct {
+    bool success;        // For batches, false only if the whole batch failed
+    int32_t kind;        // WHISPER_ARRAY_*, set even on failure
+    void* ptr;           // len elements of kind, e.g. (WhisperSegment*)array.ptr; NULL on failure
+    size_t len;
+    char* error;
+    int32_t error_code;  // WhisperError
+    char* detected_lang; // Segments only: language decoded as (NULL if unknown)
+    char* text;          // Segments only: joined and formatted text, as whisper_transcribe() returns it
+} WhisperArray;
+
+// Manager-wide snapshot from whisper_get_stats()
+typedef struct {
+    uint32_t loaded_models;  // Models busy transcribing count as loaded
+    uint64_t total_memory;   // Estimated bytes of loaded models and in-flight loads
+    float avg_cpu;
+    uint64_t memory_limit;
+} WhisperStats;
+
+// Options for a single whisper_transcribe_params() call; all fields are used
+typedef struct {
+    const char* language;  // ISO 639-1 code or "auto"; NULL = English, or detection when translating
+    float temperature;     // Clamped to [0, 1]; NaN rejected
+    int32_t sampling_mode; // WHISPER_SAMPLING_GREEDY or WHISPER_SAMPLING_BEAM_SEARCH
+    int32_t sampling_param; // best_of or beam size, >= 1
+    bool translate;
+    int32_t n_threads;     // 0 = default
+} WhisperParams;
+
 // Initialize whisper context with model path
 WhisperHandle* whisper_init(const char* model_path);
 
+// Register under a caller-chosen id such as "fast" instead of one derived from the path.
+// Returns NULL if the id is empty, contains '#', or is already registered.
+WhisperHandle* whisper_init_named(const char* model_path, const char* id);
+
+// Register like whisper_init() only if the file's SHA-256 equals `expected_sha256` (64 hex digits).
+// Returns a WhisperError: NONE with the handle in *handle_out, CHECKSUM_MISMATCH for a truncated
+// or corrupt file, FILE_NOT_FOUND / LOAD_FAILED if unreadable. Hashes the whole file.
+int32_t whisper_init_verified(const char* model_path, const char* expected_sha256, WhisperHandle** handle_out);
+
+// Check a model file's header without registering it; returns a WhisperError
+// (WHISPER_ERROR_FILE_NOT_FOUND, WHISPER_ERROR_LOAD_FAILED or WHISPER_ERROR_INVALID_MODEL)
+int32_t whisper_validate_model(const char* model_path);
+
+// Level of a buffer for meters; 0 when empty. RMS below 0.01 is what the VAD treats as silence
+float whisper_audio_rms(const float* samples, size_t len);
+float whisper_audio_peak(const float* samples, size_t len);
+
 // Transcribe audio data (f32 array, length)
 WhisperResult whisper_transcribe(WhisperHandle* handle, const float* audio_data, size_t audio_len);
 
+// Transcribe with options for this call only, leaving the handle's settings unchanged.
+// NULL params uses the stored settings.
+WhisperResult whisper_transcribe_params(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                        const WhisperParams* params);
+
+// Transcribe, stopping with WHISPER_ERROR_TIMED_OUT once inference runs past timeout_ms.
+// Model loading doesn't count towards the budget; a zero timeout is invalid.
+WhisperResult whisper_transcribe_with_timeout(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                              uint32_t timeout_ms);
+
+// Ring buffer holding the last capacity_samples of 16kHz mono audio, kept Rust-side for
+// continuous capture. NULL for 0 or more than ten minutes; release with whisper_buffer_free().
+WhisperBuffer* whisper_buffer_create(size_t capacity_samples);
+
+// Append samples, overwriting the oldest once full; safe while the buffer is being transcribed
+bool whisper_buffer_push(WhisperBuffer* buffer, const float* samples, size_t len);
+
+// Transcribe the buffer's current contents; an empty buffer is WHISPER_ERROR_INVALID_PARAMS
+WhisperResult whisper_transcribe_buffer(WhisperHandle* handle, WhisperBuffer* buffer);
+
+void whisper_buffer_free(WhisperBuffer* buffer);
+
+// Transcribe mono audio at any sample rate (resampled to 16kHz internally)
+WhisperResult whisper_transcribe_resampled(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                           uint32_t src_rate);
+
+// Transcribe a 16-bit int or 32-bit float PCM WAV file at any rate and channel count.
+// Unreadable or unsupported files fail with WHISPER_ERROR_INVALID_PARAMS.
+WhisperResult whisper_transcribe_wav(WhisperHandle* handle, const char* path);
+
+// Transcribe interleaved 16kHz audio (len counts all channels); downmixed to mono
+WhisperResult whisper_transcribe_multichannel(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                              uint16_t channels);
+
+// Transcribe a window of 16kHz audio without copying it; duration_ms = 0 runs to the end.
+// Fails with WHISPER_ERROR_INVALID_PARAMS if the window exceeds the buffer.
+WhisperResult whisper_transcribe_range(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                       uint32_t offset_ms, uint32_t duration_ms);
+
+// Cancel the model's running transcription from any thread; no-op when idle
+bool whisper_abort(WhisperHandle* handle);
+
+// Detect the spoken language (ISO 639-1 code, free with whisper_free_string) without
+// transcribing; confidence may be NULL. Returns NULL on failure.
+char* whisper_detect_language(WhisperHandle* handle, const float* audio_data, size_t audio_len, float* confidence);
+
+// Model architecture as JSON (free with whisper_free_string), loading the model if needed:
+// {"type": string, "is_multilingual": bool, "n_vocab": int, "n_audio_ctx": int,
+//  "n_text_ctx": int, "n_mels": int}. Returns NULL if the model can't be loaded.
+char* whisper_get_model_info(WhisperHandle* handle);
+
+// Settings the next transcription uses, as JSON (free with whisper_free_string). Defaults
+// depend on model size and language support; setters override them:
+// {"n_threads": int, "sampling": "greedy"|"beam_search", "sampling_param": int,
+//  "temperature": number, "language": string, "translate": bool}. NULL for an unknown handle.
+char* whisper_get_effective_params(WhisperHandle* handle);
+
+// Load the model and time `seconds` of synthetic audio; returns inference time / audio time
+// (below 1 is faster than real time), or 0 on failure. Not counted in the CPU usage stats.
+float whisper_benchmark(WhisperHandle* handle, uint32_t seconds);
+
+// Idle unload timeout in seconds (0 = never auto-unload)
+bool whisper_set_idle_timeout(WhisperHandle* handle, uint64_t seconds);
+void whisper_set_idle_timeout_global(uint64_t seconds);
+
+// Retry once with beam search when mean token probability < threshold (<= 0 disables)
+bool whisper_set_confidence_retry(WhisperHandle* handle, float threshold, int32_t beam_size);
+
+// Decoding strategy; param is best_of (greedy) or beam size (beam search), >= 1
+#define WHISPER_SAMPLING_GREEDY 0
+#define WHISPER_SAMPLING_BEAM_SEARCH 1
+bool whisper_set_sampling(WhisperHandle* handle, int32_t mode, int32_t param);
+
+// Keep the most probable of n greedy candidates wherever decoding runs above temperature 0
+// (n >= 1, capped at WHISPER_MAX_BEST_OF); selects greedy decoding in place of any beam search
+#define WHISPER_MAX_BEST_OF 8
+bool whisper_set_best_of(WhisperHandle* handle, int32_t n);
+
+// Strip [...] and (...) non-speech annotations such as [BLANK_AUDIO] from text (off by default)
+bool whisper_set_suppress_non_speech(WhisperHandle* handle, bool enabled);
+
+// Split segments at word boundaries past `chars` characters, e.g. for subtitles
+// (0 = whisper's default lengths). Enables token timestamps, which whisper needs to split.
+bool whisper_set_max_segment_len(WhisperHandle* handle, uint32_t chars);
+
+// Cut a phrase repeated more than 3 times at the end of a segment to one occurrence (off by default)
+bool whisper_set_collapse_repeats(WhisperHandle* handle, bool enabled);
+
+// Decoder-level suppression, applied while decoding rather than to the finished text.
+// Blank starts are suppressed by default; pass false to let silence decode to nothing.
+bool whisper_set_suppress_blank(WhisperHandle* handle, bool enabled);
+// Skip non-speech tokens such as music symbols (off by default); combines with the above
+bool whisper_set_suppress_nst(WhisperHandle* handle, bool enabled);
+
+// Boost quiet input to a consistent level before inference, peak-limited (off by default)
+bool whisper_set_auto_gain(WhisperHandle* handle, bool enabled);
+
+// Seed each transcription with the trailing tokens of the previous one, after any
+// prompt (off by default; disabling clears the stored context)
+bool whisper_set_context_carryover(WhisperHandle* handle, bool enabled);
+
+// Sampling temperature and fallback step, both clamped to [0, 1] (NaN rejected).
+// Sampling stays reproducible: whisper.cpp seeds every decoding state with a fixed
+// value (it has no seed parameter), so identical audio and settings give identical text.
+bool whisper_set_temperature(WhisperHandle* handle, float temperature);
+bool whisper_set_temperature_fallback(WhisperHandle* handle, float increment);
+
+// Translate to English (source language is auto-detected); multilingual models only
+bool whisper_set_translate(WhisperHandle* handle, bool enabled);
+
+// Initial prompt biasing vocabulary; persists until cleared with NULL or ""
+bool whisper_set_prompt(WhisperHandle* handle, const char* text);
+
+// Drop segments with mean token logprob below threshold (NaN disables)
+bool whisper_set_min_confidence(WhisperHandle* handle, float threshold);
+
+// Prepend `ms` of silence (0-30000, default 0) so the first word isn't clipped;
+// timestamps stay relative to the audio passed in
+bool whisper_set_lead_silence_ms(WhisperHandle* handle, uint32_t ms);
+
+// Post-processing of the joined result text (off by default); segment texts are unchanged.
+// Flags combine; unknown flags are rejected. Leading-capital stripping keeps "I" and acronyms.
+#define WHISPER_TEXT_LOWERCASE 1
+#define WHISPER_TEXT_STRIP_TRAILING_PUNCT 2
+#define WHISPER_TEXT_STRIP_LEADING_CAPITAL 4
+bool whisper_set_text_format(WhisperHandle* handle, uint32_t flags);
+
+// Which ends of the result text lose whisper's whitespace; TRAILING keeps the leading space for
+// inserting at the cursor. Segment texts and empty results are unaffected.
+#define WHISPER_TRIM_NONE 0
+#define WHISPER_TRIM_BOTH 1      // Default
+#define WHISPER_TRIM_LEADING 2
+#define WHISPER_TRIM_TRAILING 3
+bool whisper_set_trim(WhisperHandle* handle, int32_t policy);
+
+// Flag segments starting more than `gap_ms` after the previous one ended as
+// probable speaker changes (starts_new_turn); 0 disables (default)
+bool whisper_set_speaker_gap(WhisperHandle* handle, uint32_t gap_ms);
+
+// Never emit the given token ids, e.g. digits or punctuation; replaces any earlier
+// list and len 0 clears it. Ids past the vocabulary are ignored; negative ids are rejected.
+bool whisper_set_suppress_tokens(WhisperHandle* handle, const int32_t* ids, size_t len);
+
+// Fail single calls given more than `secs` of audio with WHISPER_ERROR_AUDIO_TOO_LONG,
+// before any allocation; 0 restores the default of 600. whisper_transcribe_long() checks each window
+// and whisper_transcribe_range() the range it decodes.
+bool whisper_set_max_audio_secs(WhisperHandle* handle, uint32_t secs);
+
+// Reject any call whose audio length is over `samples` with WHISPER_ERROR_INVALID_PARAMS,
+// before the buffer is read, to catch miscomputed lengths; 0 restores the default of
+// four hours at 16kHz. Applies to every handle.
+bool whisper_set_max_audio_len(size_t samples);
+
+// Fail with WHISPER_ERROR_CONVERSION_FAILED, naming the segment, when a segment's text
+// can't be read; by default such a segment keeps its timing with U+FFFD as its text
+bool whisper_set_strict_segments(WhisperHandle* handle, bool strict);
+
+// Kind of core inference runs on: caps the thread count and, on Apple platforms,
+// sets the QoS class for the duration of the call
+#define WHISPER_CORES_AUTO 0         // Default: performance for single calls, efficiency for batch/long
+#define WHISPER_CORES_PERFORMANCE 1
+#define WHISPER_CORES_EFFICIENCY 2
+bool whisper_set_core_preference(WhisperHandle* handle, int32_t preference);
+
+// Opt-in warning when audio looks band-limited below 16kHz (best-effort)
+bool whisper_set_sample_rate_check(WhisperHandle* handle, bool enabled);
+
+// Reaction when over 5% of samples fall outside [-1, 1] (e.g. int16 passed as float)
+#define WHISPER_AUDIO_CHECK_OFF 0
+#define WHISPER_AUDIO_CHECK_WARN 1    // Default: log a warning and transcribe anyway
+#define WHISPER_AUDIO_CHECK_REJECT 2  // Fail with WHISPER_ERROR_INVALID_AUDIO
+bool whisper_set_audio_check(WhisperHandle* handle, int32_t mode);
+
+// Trim leading/trailing silence (energy VAD, 100ms pre-roll) before inference
+bool whisper_set_vad(WhisperHandle* handle, bool enabled);
+
+// Transcribe into timed segments with confidence estimates (WHISPER_ARRAY_SEGMENTS)
+WhisperArray whisper_transcribe_segments(WhisperHandle* handle, const float* audio_data, size_t audio_len);
+
+// Transcribe long audio as chunk_secs windows overlapping by overlap_secs (< chunk_secs).
+// Times are relative to the whole buffer; words repeated across an overlap appear once.
+WhisperArray whisper_transcribe_long(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                     uint32_t chunk_secs, uint32_t overlap_secs);
+
+// Transcribe into words with token-level timestamps (WHISPER_ARRAY_WORDS)
+WhisperArray whisper_transcribe_words(WhisperHandle* handle, const float* audio_data, size_t audio_len);
+
+// Transcribe into the raw token stream of every segment, special tokens flagged (WHISPER_ARRAY_TOKENS)
+WhisperArray whisper_transcribe_tokens(WhisperHandle* handle, const float* audio_data, size_t audio_len);
+
+// Free any WhisperArray, whatever its kind; unknown kinds are logged and their elements left alone
+void whisper_free_array(WhisperArray array);
+
+// DTW token alignment filling t_dtw in tokens and words; each preset is the alignment heads of one
+// released model (.en = English-only). AUTO picks by file name and fails for a plain "large" or an
+// unknown name. A loaded model reloads on its next transcription; DTW needs ~128MB extra memory.
+#define WHISPER_DTW_OFF 0             // Default
+#define WHISPER_DTW_AUTO 1
+#define WHISPER_DTW_TINY_EN 2
+#define WHISPER_DTW_TINY 3
+#define WHISPER_DTW_BASE_EN 4
+#define WHISPER_DTW_BASE 5
+#define WHISPER_DTW_SMALL_EN 6
+#define WHISPER_DTW_SMALL 7
+#define WHISPER_DTW_MEDIUM_EN 8
+#define WHISPER_DTW_MEDIUM 9
+#define WHISPER_DTW_LARGE_V1 10
+#define WHISPER_DTW_LARGE_V2 11
+#define WHISPER_DTW_LARGE_V3 12
+#define WHISPER_DTW_LARGE_V3_TURBO 13
+bool whisper_set_dtw(WhisperHandle* handle, int32_t preset);
+
+// Write each segment to fd as a JSON line:
+// {"text": string, "start_ms": int, "end_ms": int, "confidence": float}
+WhisperResult whisper_transcribe_jsonl_to_fd(WhisperHandle* handle, const float* audio_data, size_t audio_len, int32_t fd);
+
+// Transcribe into one JSON document (free with whisper_free_string), schema version 1:
+// {"version": 1, "text": string, "language": string|null,
+//  "segments": [{"start": int, "end": int, "text": string}], "inference_ms": int}
+// or {"version": 1, "error": {"code": int, "message": string}} on failure
+char* whisper_transcribe_json(WhisperHandle* handle, const float* audio_data, size_t audio_len);
+
+// Transcribe several buffers under one model lock; failures are per entry (WHISPER_ARRAY_RESULTS)
+WhisperArray whisper_transcribe_batch(WhisperHandle* handle, const float* const* buffers,
+                                      const size_t* lengths, size_t count);
+
+// Streaming transcription: callback receives each segment as it is decoded.
+// The text pointer is only valid during the callback; copy it immediately.
+typedef void (*WhisperSegmentCallback)(const char* text, void* user_data);
+WhisperResult whisper_transcribe_streaming(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                           WhisperSegmentCallback callback, void* user_data);
+
+// Live session: each feed re-transcribes all audio so far, and words are passed to the callback
+// once they read the same in `passes` consecutive feeds (0 = 2). Later words stay provisional.
+// NULL for a null handle or callback; release with whisper_stream_free().
+WhisperStream* whisper_stream_create(WhisperHandle* handle, uint32_t passes, WhisperSegmentCallback callback,
+                                     void* user_data);
+
+// Append audio and re-transcribe; the result holds the full current text (committed + provisional).
+// Fails with WHISPER_ERROR_INVALID_PARAMS past ten minutes of audio.
+WhisperResult whisper_stream_feed(WhisperStream* stream, const float* samples, size_t len);
+
+// Final pass over all audio fed so far, then end the session: the result holds the committed
+// plus final text, remaining words go to the callback, and the audio is released. Feeding or
+// finishing again fails with WHISPER_ERROR_INVALID_PARAMS. Still free with whisper_stream_free().
+WhisperResult whisper_stream_finish(WhisperStream* stream);
+void whisper_stream_free(WhisperStream* stream);
+
+// Transcription with progress reported in percent (0-100) on the calling thread
+typedef void (*WhisperProgressCallback)(int32_t progress, void* user_data);
+WhisperResult whisper_transcribe_with_progress(WhisperHandle* handle, const float* audio_data, size_t audio_len,
+                                               WhisperProgressCallback progress_cb, void* user_data);
+
+// Log routing for this library and whisper.cpp (stderr when unset; NULL restores it).
+// May be called from any thread; msg is only valid during the callback.
+#define WHISPER_LOG_LEVEL_DEBUG 0
+#define WHISPER_LOG_LEVEL_INFO 1
+#define WHISPER_LOG_LEVEL_WARN 2
+#define WHISPER_LOG_LEVEL_ERROR 3
+typedef void (*WhisperLogCallback)(int32_t level, const char* msg, void* user_data);
+void whisper_set_log_callback(WhisperLogCallback callback, void* user_data);
+
+// Load the model eagerly; false if loading failed, see whisper_get_last_error()
+bool whisper_preload(WhisperHandle* handle);
+
+// Load the model on a low-priority thread, only if it fits without evicting another model.
+// Returns immediately; false for a null handle or unknown priority.
+#define WHISPER_PRIORITY_UTILITY 0
+#define WHISPER_PRIORITY_BACKGROUND 1  // Only runs when the system is otherwise idle
+bool whisper_warm_in_background(WhisperHandle* handle, int32_t priority);
+bool whisper_is_loaded(WhisperHandle* handle);
+bool whisper_is_gpu_enabled(WhisperHandle* handle);  // false if unloaded or fell back to CPU
+
+// Pool up to n (1-8) loaded contexts so transcriptions on one model run in parallel.
+// Each context counts against the memory limit. Returns the pool size, 0 on failure.
+uint32_t whisper_set_concurrency(WhisperHandle* handle, uint32_t n);
+
+// Drop the model's context and load a fresh one, e.g. after repeated inference failures.
+// Transcriptions wait for the reload. False if the model can't be loaded again.
+bool whisper_reload(WhisperHandle* handle);
+
+// Remove the model registration and release its context (handle still needs whisper_free)
+bool whisper_unregister(WhisperHandle* handle);
+
 // Free whisper context
 void whisper_free(WhisperHandle* handle);
 
 // Free result strings
 void whisper_free_string(char* ptr);
 
+// Why the last whisper_preload(), whisper_reload() or whisper_cleanup_memory() on this
+// thread returned false; NULL if it succeeded. Kept per thread, like errno; free with whisper_free_string()
+char* whisper_get_last_error(void);
+
 // Memory management functions
-uint64_t whisper_get_memory_usage(void);
+uint64_t whisper_get_memory_usage(void);  // Sum of model estimates, as used by the memory limit
+uint64_t whisper_get_process_rss(void);   // Measured resident bytes of the whole process (0 if unsupported)
 bool whisper_cleanup_memory(void);
+bool whisper_set_memory_limit(uint64_t bytes);  // Peak budget in bytes; false below 39MB
+
+// Background thread unloading idle models every interval_secs (> 0); restarting replaces it.
+// Stop joins the thread and is a no-op when it isn't running.
+bool whisper_start_idle_sweeper(uint64_t interval_secs);
+void whisper_stop_idle_sweeper(void);
+
+// Bracket a recording so the sweeper doesn't unload models between push-to-talk presses;
+// idle timeouts count from the later of the last transcription and whisper_mark_idle()
+void whisper_mark_active(void);
+void whisper_mark_idle(void);
+
+// whisper.cpp's build capabilities, e.g. "NEON = 1 | ..." (free with whisper_free_string)
+char* whisper_system_info(void);
+
+uint64_t whisper_get_estimated_memory(WhisperHandle* handle);
+uint64_t whisper_get_actual_memory(WhisperHandle* handle);
+uint64_t whisper_get_model_limit(WhisperHandle* handle);  // size-class cap in bytes
 
 // Performance monitoring functions  
 float whisper_get_avg_cpu_usage(void);
+void whisper_reset_cpu_stats(void);  // Clear CPU history, e.g. between recording sessions
+WhisperStats whisper_get_stats(void);  // Memory and CPU figures in one consistent snapshot
 bool whisper_check_downgrade_needed(WhisperHandle* handle);
 char* whisper_get_suggested_model(WhisperHandle* handle);
 
+// Suggest `to` (by its registered path) instead of `from` under CPU pressure;
+// NULL restores the default large -> medium -> small -> tiny chain
+bool whisper_set_downgrade_target(WhisperHandle* from, WhisperHandle* to);
+bool whisper_set_downgrade_threshold(float percent);  // 0-100, default 80
+
+// Recent transcriptions, kept so a result can be replayed without inference (at most 32).
+// A long transcription is one entry, and a batch one entry per buffer.
+// Both strings are freed with whisper_free_string; the last result is NULL when there is none.
+char* whisper_get_last_result(void);
+// JSON array, newest first: [{"model_id": string, "text": string, "timestamp_ms": int}]
+char* whisper_get_history_json(uint32_t n);
+void whisper_clear_history(void);
+
 #ifdef __cplusplus
 }
 #endif
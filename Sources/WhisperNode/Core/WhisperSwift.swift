@@ -13,6 +13,8 @@ public struct WhisperPerformanceMetrics {
     public let averageCpuUsage: Float   // Average CPU usage percentage
     public let isDowngradeNeeded: Bool  // Whether model downgrade is recommended
     public let suggestedModel: String?  // Suggested smaller model if applicable
+    public var estimatedModelMemory: UInt64 = 0 // Pre-load estimate for this model in bytes
+    public var actualModelMemory: UInt64 = 0    // Measured footprint for this model in bytes
 }
 
 /// Swift wrapper for Rust whisper FFI with enhanced memory management
@@ -116,6 +118,12 @@ public class WhisperSwift {
             if result.error != nil {
                 whisper_free_string(result.error)
             }
+            if result.detected_lang != nil {
+                whisper_free_string(result.detected_lang)
+            }
+            if result.warning != nil {
+                whisper_free_string(result.warning)
+            }
         }
         
         guard result.success else {
@@ -126,6 +134,10 @@ public class WhisperSwift {
             return nil
         }
         
+        if let warningPtr = result.warning {
+            print("Whisper transcription warning: \(String(cString: warningPtr))")
+        }
+        
         guard let textPtr = result.text else {
             return nil
         }
@@ -213,7 +225,9 @@ public class WhisperSwift {
             memoryUsage: memoryUsage,
             averageCpuUsage: avgCpuUsage,
             isDowngradeNeeded: isDowngradeNeeded,
-            suggestedModel: suggestedModel
+            suggestedModel: suggestedModel,
+            estimatedModelMemory: whisper_get_estimated_memory(handle),
+            actualModelMemory: whisper_get_actual_memory(handle)
         )
         #else
         return WhisperPerformanceMetrics(
@@ -1,25 +1,71 @@
 use std::env;
 
+/// Thread count every model defaults to instead of the size table in lib.rs
+const DEFAULT_THREADS_ENV: &str = "WHISPER_NODE_DEFAULT_THREADS";
+
+/// Most threads `WHISPER_NODE_DEFAULT_THREADS` may ask for
+const MAX_DEFAULT_THREADS: u32 = 256;
+
+/// The packager's default thread count, None when unset or empty
+/// 
+/// Anything but a count from 1 to `MAX_DEFAULT_THREADS` fails the build,
+/// rather than shipping a binary that silently ignores it.
+fn default_threads() -> Option<u32> {
+    let value = env::var(DEFAULT_THREADS_ENV).ok()?;
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    match value.parse::<u32>() {
+        Ok(threads) if (1..=MAX_DEFAULT_THREADS).contains(&threads) => Some(threads),
+        _ => panic!("{} must be a thread count from 1 to {}, got {:?}", DEFAULT_THREADS_ENV, MAX_DEFAULT_THREADS, value),
+    }
+}
+
+/// Whether the GPU backend is built, matching `GPU_ENABLED` in lib.rs
+fn metal_enabled() -> bool {
+    env::var_os("CARGO_FEATURE_METAL").is_some() && env::var_os("CARGO_FEATURE_CPU_ONLY").is_none()
+}
+
+/// Write the C header for every exported function and #[repr(C)] type
+#[cfg(feature = "header")]
+fn generate_header() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR environment variable not set");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+        .expect("Failed to read cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("Failed to generate the C header")
+        .write_to_file(format!("{}/include/whisper_node.h", crate_dir));
+}
+
 fn main() {
     let target = env::var("TARGET").expect("TARGET environment variable not set");
-    
+
     if target.contains("aarch64-apple-darwin") {
-        // Apple Silicon optimizations
-        println!("cargo:rustc-link-arg=-framework");
-        println!("cargo:rustc-link-arg=Foundation");
-        println!("cargo:rustc-link-arg=-framework");
-        println!("cargo:rustc-link-arg=Metal");
-        println!("cargo:rustc-link-arg=-framework");
-        println!("cargo:rustc-link-arg=MetalKit");
-        println!("cargo:rustc-link-arg=-framework");
-        println!("cargo:rustc-link-arg=Accelerate");
-        
-        // Enable Apple Silicon specific optimizations
-        println!("cargo:rustc-env=CFLAGS=-march=armv8.2-a -mfpu=neon -O3");
-        println!("cargo:rustc-env=CXXFLAGS=-march=armv8.2-a -mfpu=neon -O3");
+        let mut frameworks = vec!["Foundation", "Accelerate"];
+        if metal_enabled() {
+            frameworks.extend(["Metal", "MetalKit"]);
+        }
+        for framework in frameworks {
+            println!("cargo:rustc-link-arg=-framework");
+            println!("cargo:rustc-link-arg={}", framework);
+        }
+    }
+
+    // Read back with option_env! as `DEFAULT_THREADS` in lib.rs
+    if let Some(threads) = default_threads() {
+        println!("cargo:rustc-env={}={}", DEFAULT_THREADS_ENV, threads);
     }
-    
+
+    #[cfg(feature = "header")]
+    generate_header();
+
     // Tell cargo to invalidate the built crate whenever the wrapper changes
-    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
     println!("cargo:rerun-if-changed=build.rs");
-}
\ No newline at end of file
+    println!("cargo:rerun-if-env-changed={}", DEFAULT_THREADS_ENV);
+}
@@ -0,0 +1,255 @@
+//! Safe Rust interface for using this crate from other Rust code
+//!
+//! Backed by the same model manager as the C API, so models opened here
+//! share memory limits, idle unloading and settings with FFI handles for
+//! the same path.
+//!
+//! ```no_run
+//! use whisper_rust::api::Whisper;
+//!
+//! let whisper = Whisper::open("models/ggml-tiny.en.bin")?;
+//!
+//! // One second of 16kHz mono audio
+//! let audio = vec![0.0f32; 16000];
+//! let text = whisper.transcribe(&audio)?;
+//! println!("{}", text);
+//! # Ok::<(), whisper_rust::WhisperError>(())
+//! ```
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{merge_words, register_path, Decoded, DecodedSegment, DecodedWord, WhisperError, WHISPER_MANAGER};
+
+/// A transcription model, loaded lazily on first use
+#[derive(Debug, Clone)]
+pub struct Whisper {
+    model_id: String,
+}
+
+/// A transcribed span of audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    pub start: Duration, // from the start of the audio
+    pub end: Duration,
+    pub avg_logprob: f32,    // mean log probability of the text tokens
+    pub no_speech_prob: f32, // estimated share of silence in the segment (0-1)
+    pub starts_new_turn: bool, // probable speaker change, when a speaker gap is set
+    pub language: Option<&'static str>, // ISO 639-1 code the segment was decoded as, if known
+}
+
+impl Segment {
+    fn from_decoded(segment: &DecodedSegment) -> Self {
+        Self {
+            text: segment.text.clone(),
+            start: offset(segment.start_ms),
+            end: offset(segment.end_ms),
+            avg_logprob: segment.avg_logprob,
+            no_speech_prob: segment.no_speech_prob,
+            starts_new_turn: segment.starts_new_turn,
+            language: segment.language,
+        }
+    }
+}
+
+/// A word merged from its subword tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub text: String,
+    pub start: Duration,
+    pub end: Duration,
+    pub probability: f32, // mean probability of the word's tokens
+    pub dtw: Option<Duration>, // DTW-aligned start, when DTW timestamps are enabled
+}
+
+impl From<DecodedWord> for Word {
+    fn from(word: DecodedWord) -> Self {
+        Self {
+            text: word.text,
+            start: offset(word.start_ms),
+            end: offset(word.end_ms),
+            probability: word.probability,
+            dtw: word.dtw_ms.map(offset),
+        }
+    }
+}
+
+/// Whisper reports times in milliseconds; negative values never reach callers
+fn offset(ms: i64) -> Duration {
+    Duration::from_millis(ms.max(0) as u64)
+}
+
+/// The result of one transcription, converted into segments or words on demand
+/// 
+/// Nothing is copied out until it's iterated, so callers that only need the
+/// first few segments don't pay for the rest.
+#[derive(Debug, Clone)]
+pub struct Transcription {
+    decoded: Decoded,
+}
+
+impl Transcription {
+    /// The full text, with segments joined and formatted as in [`Whisper::transcribe`]
+    pub fn text(&self) -> String {
+        self.decoded.text()
+    }
+    
+    /// ISO 639-1 code of the spoken language, if known
+    pub fn language(&self) -> Option<&'static str> {
+        self.decoded.language
+    }
+    
+    pub fn segments(&self) -> impl Iterator<Item = Segment> + '_ {
+        self.decoded.segments.iter().map(Segment::from_decoded)
+    }
+    
+    /// Words across all segments, merged one segment at a time
+    pub fn words(&self) -> impl Iterator<Item = Word> + '_ {
+        self.decoded.segments.iter()
+            .flat_map(|segment| merge_words(&segment.tokens))
+            .map(Word::from)
+    }
+}
+
+impl Whisper {
+    /// Register the model file at `path`
+    ///
+    /// The file isn't read until the first transcription, so a missing or
+    /// corrupt model surfaces as [`WhisperError::LoadFailed`] from that call.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, WhisperError> {
+        let path = path.as_ref().to_str().ok_or(WhisperError::InvalidParams)?;
+        let model_id = register_path(path).map_err(|e| e.code)?;
+        Ok(Self { model_id })
+    }
+
+    /// Transcribe 16kHz mono audio into text
+    ///
+    /// ```no_run
+    /// # use whisper_rust::api::Whisper;
+    /// let whisper = Whisper::open("models/ggml-tiny.en.bin")?;
+    /// let text = whisper.transcribe(&[0.0; 16000])?;
+    /// # Ok::<(), whisper_rust::WhisperError>(())
+    /// ```
+    pub fn transcribe(&self, audio: &[f32]) -> Result<String, WhisperError> {
+        if audio.is_empty() {
+            return Err(WhisperError::InvalidParams);
+        }
+        WHISPER_MANAGER
+            .transcribe(&self.model_id, audio)
+            .map(|transcript| transcript.text)
+            .map_err(|e| e.code)
+    }
+
+    /// Transcribe 16kHz mono audio into timed segments
+    ///
+    /// ```no_run
+    /// # use whisper_rust::api::Whisper;
+    /// let whisper = Whisper::open("models/ggml-tiny.en.bin")?;
+    /// for segment in whisper.transcribe_segments(&[0.0; 16000])? {
+    ///     println!("[{:?} - {:?}] {}", segment.start, segment.end, segment.text);
+    /// }
+    /// # Ok::<(), whisper_rust::WhisperError>(())
+    /// ```
+    pub fn transcribe_segments(&self, audio: &[f32]) -> Result<Vec<Segment>, WhisperError> {
+        if audio.is_empty() {
+            return Err(WhisperError::InvalidParams);
+        }
+        let decoded = WHISPER_MANAGER
+            .transcribe_segments_with(&self.model_id, audio, |_| {})
+            .map_err(|e| e.code)?;
+        Ok(decoded.segments.iter().map(Segment::from_decoded).collect())
+    }
+    
+    /// Transcribe 16kHz mono audio, keeping the result for lazy iteration
+    /// 
+    /// Token timestamps are enabled so [`Transcription::words`] has timings.
+    /// 
+    /// ```no_run
+    /// # use whisper_rust::api::Whisper;
+    /// let whisper = Whisper::open("models/ggml-tiny.en.bin")?;
+    /// let audio = vec![0.0f32; 5 * 16000];
+    /// let transcription = whisper.transcription(&audio)?;
+    /// 
+    /// // Only the first two segments are converted
+    /// for segment in transcription.segments().take(2) {
+    ///     println!("[{:?} - {:?}] {}", segment.start, segment.end, segment.text);
+    /// }
+    /// # Ok::<(), whisper_rust::WhisperError>(())
+    /// ```
+    pub fn transcription(&self, audio: &[f32]) -> Result<Transcription, WhisperError> {
+        if audio.is_empty() {
+            return Err(WhisperError::InvalidParams);
+        }
+        WHISPER_MANAGER
+            .transcribe_segments_with(&self.model_id, audio, |params| params.set_token_timestamps(true))
+            .map(|decoded| Transcription { decoded })
+            .map_err(|e| e.code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_model_fails_on_first_use() {
+        let whisper = Whisper::open("/nonexistent/api-tiny.bin").unwrap();
+        assert_eq!(whisper.transcribe(&[0.0; 1600]), Err(WhisperError::LoadFailed));
+        assert_eq!(whisper.transcribe_segments(&[0.0; 1600]), Err(WhisperError::LoadFailed));
+        assert_eq!(whisper.transcription(&[0.0; 1600]).map(|t| t.text()), Err(WhisperError::LoadFailed));
+        assert_eq!(whisper.transcribe(&[]), Err(WhisperError::InvalidParams));
+
+        // Opening the same path again shares the registration
+        let again = Whisper::open("/nonexistent/api-tiny.bin").unwrap();
+        assert_eq!(again.model_id, whisper.model_id);
+        WHISPER_MANAGER.unregister(&whisper.model_id).unwrap();
+    }
+    
+    #[test]
+    fn test_transcription_iterates_lazily() {
+        let token = |text: &str, start_ms, end_ms| crate::DecodedToken {
+            id: 0,
+            bytes: text.as_bytes().to_vec(),
+            p: 0.8,
+            start_ms,
+            end_ms,
+            dtw_ms: None,
+            is_special: false,
+        };
+        let segment = |text: &str, start_ms, end_ms, tokens| DecodedSegment {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            confidence: 0.8,
+            avg_logprob: -0.2,
+            no_speech_prob: 0.0,
+            starts_new_turn: false,
+            language: Some("en"),
+            tokens,
+        };
+        let transcription = Transcription {
+            decoded: Decoded {
+                segments: vec![
+                    segment(" Hello there.", 0, 1200, vec![token(" Hello", 0, 500), token(" there", 500, 1100), token(".", 1100, 1200)]),
+                    segment(" Bye.", 1500, 2000, vec![token(" Bye", 1500, 1900), token(".", 1900, 2000)]),
+                ],
+                language: Some("en"),
+                non_finite: 0,
+                text_format: 0,
+                trim: crate::TrimPolicy::Both,
+                fallbacks: Default::default(),
+            },
+        };
+        
+        assert_eq!(transcription.text(), "Hello there. Bye.");
+        let first = transcription.segments().next().unwrap();
+        assert_eq!((first.start, first.end), (Duration::ZERO, Duration::from_millis(1200)));
+        assert_eq!(transcription.segments().count(), 2);
+        
+        let words: Vec<Word> = transcription.words().collect();
+        let texts: Vec<&str> = words.iter().map(|word| word.text.as_str()).collect();
+        assert_eq!(texts, ["Hello", "there.", "Bye."]);
+        assert_eq!(words[2].start, Duration::from_millis(1500));
+    }
+}
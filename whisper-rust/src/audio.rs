@@ -0,0 +1,204 @@
+//! Input buffer preparation before inference
+
+use std::borrow::Cow;
+
+/// Average interleaved multi-channel audio down to mono
+///
+/// Mono input is passed through without copying. A trailing partial frame
+/// is dropped, and zero channels yields an empty buffer.
+pub fn downmix_to_mono(interleaved: &[f32], channels: u16) -> Cow<'_, [f32]> {
+    match channels {
+        0 => Cow::Owned(Vec::new()),
+        1 => Cow::Borrowed(interleaved),
+        _ => {
+            let channels = channels as usize;
+            Cow::Owned(
+                interleaved
+                    .chunks_exact(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Zero-pad or truncate `samples` to exactly `len` samples
+///
+/// Buffers at least `len` long are borrowed.
+pub fn pad_or_trim(samples: &[f32], len: usize) -> Cow<'_, [f32]> {
+    if samples.len() >= len {
+        Cow::Borrowed(&samples[..len])
+    } else {
+        let mut padded = samples.to_vec();
+        padded.resize(len, 0.0);
+        Cow::Owned(padded)
+    }
+}
+
+/// Share of samples outside the [-1, 1] range of normalized f32 PCM
+///
+/// NaN and infinite samples count as out of range. An empty buffer yields 0.
+pub fn out_of_range_fraction(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let outside = samples.iter().filter(|sample| sample.is_nan() || sample.abs() > 1.0).count();
+    outside as f32 / samples.len() as f32
+}
+
+/// Replace NaN and infinite samples with silence, returning how many there were
+///
+/// Buffers with only finite samples are borrowed.
+pub fn replace_non_finite(samples: &[f32]) -> (Cow<'_, [f32]>, usize) {
+    let count = samples.iter().filter(|sample| !sample.is_finite()).count();
+    if count == 0 {
+        return (Cow::Borrowed(samples), 0);
+    }
+    let cleaned = samples.iter().map(|&sample| if sample.is_finite() { sample } else { 0.0 }).collect();
+    (Cow::Owned(cleaned), count)
+}
+
+/// RMS level auto gain brings quiet speech up to
+pub const AUTO_GAIN_TARGET_RMS: f32 = 0.1;
+
+/// Peak auto gain never exceeds, so loud transients don't clip
+pub const AUTO_GAIN_MAX_PEAK: f32 = 0.95;
+
+/// Scale `samples` so the largest magnitude equals `target_peak`
+///
+/// A silent buffer is left untouched.
+pub fn normalize_peak(samples: &mut [f32], target_peak: f32) {
+    let peak = peak(samples);
+    if peak > 0.0 {
+        scale(samples, target_peak / peak);
+    }
+}
+
+/// Scale `samples` so their RMS level equals `target_rms`
+///
+/// A silent buffer is left untouched.
+pub fn normalize_rms(samples: &mut [f32], target_rms: f32) {
+    let rms = rms(samples);
+    if rms > 0.0 {
+        scale(samples, target_rms / rms);
+    }
+}
+
+/// Bring `samples` to a consistent level for inference
+///
+/// Normalizes to [`AUTO_GAIN_TARGET_RMS`], then pulls the peak back to
+/// [`AUTO_GAIN_MAX_PEAK`] if that would clip.
+pub fn auto_gain(samples: &[f32]) -> Vec<f32> {
+    let mut output = samples.to_vec();
+    normalize_rms(&mut output, AUTO_GAIN_TARGET_RMS);
+    if peak(&output) > AUTO_GAIN_MAX_PEAK {
+        normalize_peak(&mut output, AUTO_GAIN_MAX_PEAK);
+    }
+    output
+}
+
+/// Root mean square level of `samples`, 0 for an empty buffer
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Largest magnitude in `samples`, 0 for an empty buffer
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+}
+
+fn scale(samples: &mut [f32], gain: f32) {
+    for sample in samples {
+        *sample *= gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downmix_stereo_averages_channels() {
+        let interleaved = [1.0, 0.0, 0.5, -0.5, -1.0, 0.25, 0.3];
+        let mono = downmix_to_mono(&interleaved, 2);
+        assert_eq!(&*mono, &[0.5, 0.0, -0.375]);
+    }
+
+    #[test]
+    fn test_out_of_range_fraction() {
+        assert_eq!(out_of_range_fraction(&[]), 0.0);
+        assert_eq!(out_of_range_fraction(&[1.0, -1.0, 0.5, 0.0]), 0.0);
+        assert_eq!(out_of_range_fraction(&[1.5, f32::NAN, -3000.0, 0.5]), 0.75);
+    }
+
+    #[test]
+    fn test_replace_non_finite() {
+        let (cleaned, count) = replace_non_finite(&[0.5, f32::NAN, f32::INFINITY, -0.25, f32::NEG_INFINITY]);
+        assert_eq!(&*cleaned, &[0.5, 0.0, 0.0, -0.25, 0.0]);
+        assert_eq!(count, 3);
+        assert!(matches!(replace_non_finite(&[0.5, -1.0]), (Cow::Borrowed(_), 0)));
+    }
+
+    #[test]
+    fn test_pad_or_trim() {
+        assert_eq!(&*pad_or_trim(&[0.5, 0.25], 4), &[0.5, 0.25, 0.0, 0.0]);
+        assert!(matches!(pad_or_trim(&[0.5, 0.25, 0.1], 2), Cow::Borrowed(&[0.5, 0.25])));
+    }
+
+    #[test]
+    fn test_normalize_peak_scales_to_target() {
+        let mut samples = [0.1, -0.05, 0.025, 0.0];
+        normalize_peak(&mut samples, 0.95);
+        let expected = [0.95, -0.475, 0.2375, 0.0];
+        for (sample, expected) in samples.iter().zip(expected) {
+            assert!((sample - expected).abs() < 1e-6, "{} != {}", sample, expected);
+        }
+    }
+
+    #[test]
+    fn test_silent_buffer_is_unchanged() {
+        let mut samples = [0.0f32; 160];
+        normalize_peak(&mut samples, 0.95);
+        normalize_rms(&mut samples, AUTO_GAIN_TARGET_RMS);
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+        assert!(auto_gain(&samples).iter().all(|sample| sample.is_finite() && *sample == 0.0));
+    }
+
+    #[test]
+    fn test_auto_gain_limits_peak() {
+        // Sparse clicks: reaching the RMS target would push the peak far past full scale
+        let mut samples = vec![0.0f32; 1000];
+        samples[500] = 0.05;
+        let gained = auto_gain(&samples);
+        assert!((peak(&gained) - AUTO_GAIN_MAX_PEAK).abs() < 1e-6);
+
+        let quiet: Vec<f32> = (0..1600).map(|i| 0.01 * (i as f32 * 0.1).sin()).collect();
+        let gained = auto_gain(&quiet);
+        let rms = (gained.iter().map(|s| s * s).sum::<f32>() / gained.len() as f32).sqrt();
+        assert!((rms - AUTO_GAIN_TARGET_RMS).abs() < 1e-4, "{}", rms);
+    }
+
+    #[test]
+    fn test_rms_and_peak_of_known_buffers() {
+        assert_eq!((rms(&[]), peak(&[])), (0.0, 0.0));
+        assert_eq!((rms(&[0.0; 8]), peak(&[0.0; 8])), (0.0, 0.0));
+        assert_eq!((rms(&[0.5, -0.5, 0.5, -0.5]), peak(&[0.5, -0.5, 0.5, -0.5])), (0.5, 0.5));
+        assert_eq!(rms(&[3.0, 4.0, 0.0, 0.0]), 2.5);
+        assert_eq!(peak(&[0.1, -0.9, 0.3]), 0.9);
+        
+        // A full-scale sine sits at 1/sqrt(2)
+        let sine: Vec<f32> = (0..1600).map(|i| (i as f32 * std::f32::consts::TAU / 32.0).sin()).collect();
+        assert!((rms(&sine) - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4, "{}", rms(&sine));
+        assert!((peak(&sine) - 1.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_downmix_mono_is_borrowed() {
+        let samples = [0.1, 0.2, 0.3];
+        assert!(matches!(downmix_to_mono(&samples, 1), Cow::Borrowed(_)));
+        assert!(downmix_to_mono(&samples, 0).is_empty());
+    }
+}
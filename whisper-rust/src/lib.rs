@@ -1,10 +1,38 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_float};
+use std::os::raw::{c_char, c_float, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ops::Range;
 use libc::size_t;
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Duration, Instant};
-use std::collections::HashMap;
-use whisper_rs::{WhisperContext, WhisperContextParameters, FullParams, SamplingStrategy};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, VecDeque};
+pub mod api;
+mod audio;
+mod lock_order;
+mod logging;
+#[cfg(feature = "mock")]
+mod mock;
+mod resample;
+mod ring;
+mod sha256;
+mod spectrum;
+mod stream;
+mod sweeper;
+mod vad;
+mod wav;
+
+use lock_order::ModelGuard;
+use whisper_rs::{
+    DtwMode, DtwModelPreset, DtwParameters, SamplingStrategy, SegmentCallbackData,
+    WhisperContextParameters, WhisperSysContext, WhisperSysState, WhisperToken, WhisperTokenData,
+};
+#[cfg(not(feature = "mock"))]
+use whisper_rs::{FullParams, WhisperContext, WhisperState};
+#[cfg(feature = "mock")]
+use mock::{MockContext as WhisperContext, MockParams as FullParams};
 
 /// Model information for tracking and management
 #[derive(Debug, Clone)]
@@ -15,18 +43,201 @@ pub struct ModelInfo {
     pub cpu_factor: f32,   // relative CPU usage multiplier
 }
 
+impl ModelInfo {
+    /// Whether the footprint is well beyond what its size class allows
+    /// 
+    /// Estimates are approximate, so only a footprint more than
+    /// `SIZE_LIMIT_TOLERANCE` times the class limit counts; that usually
+    /// means a larger model was registered under a smaller size's name.
+    fn exceeds_size_limit(&self, footprint: u64) -> bool {
+        footprint as f64 > self.size.memory_limit() as f64 * SIZE_LIMIT_TOLERANCE
+    }
+    
+    /// Whether the model is multilingual, guessed from an `.en` part in its file name
+    /// 
+    /// The loaded file's header is authoritative, see `whisper_get_model_info`.
+    fn is_multilingual(&self) -> bool {
+        let file_name = self.name.rsplit(['/', '\\']).next().unwrap_or(&self.name);
+        !file_name.split('.').any(|part| part == "en")
+    }
+}
+
+/// Alignment heads whisper.cpp uses for DTW token timestamps, one set per released model
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DtwPreset {
+    TinyEn,
+    Tiny,
+    BaseEn,
+    Base,
+    SmallEn,
+    Small,
+    MediumEn,
+    Medium,
+    LargeV1,
+    LargeV2,
+    LargeV3,
+    LargeV3Turbo,
+}
+
+impl DtwPreset {
+    /// Map a preset from its `WHISPER_DTW_*` code; `WHISPER_DTW_OFF` and `WHISPER_DTW_AUTO` aren't presets
+    fn from_ffi(preset: i32) -> Option<Self> {
+        match preset {
+            WHISPER_DTW_TINY_EN => Some(DtwPreset::TinyEn),
+            WHISPER_DTW_TINY => Some(DtwPreset::Tiny),
+            WHISPER_DTW_BASE_EN => Some(DtwPreset::BaseEn),
+            WHISPER_DTW_BASE => Some(DtwPreset::Base),
+            WHISPER_DTW_SMALL_EN => Some(DtwPreset::SmallEn),
+            WHISPER_DTW_SMALL => Some(DtwPreset::Small),
+            WHISPER_DTW_MEDIUM_EN => Some(DtwPreset::MediumEn),
+            WHISPER_DTW_MEDIUM => Some(DtwPreset::Medium),
+            WHISPER_DTW_LARGE_V1 => Some(DtwPreset::LargeV1),
+            WHISPER_DTW_LARGE_V2 => Some(DtwPreset::LargeV2),
+            WHISPER_DTW_LARGE_V3 => Some(DtwPreset::LargeV3),
+            WHISPER_DTW_LARGE_V3_TURBO => Some(DtwPreset::LargeV3Turbo),
+            _ => None,
+        }
+    }
+    
+    /// The preset matching a model's file name, like `ggml-base.en.bin` or `ggml-large-v3-turbo.bin`
+    /// 
+    /// A plain "large" could be any of the large releases, and the heads
+    /// differ between them, so it has no preset.
+    fn for_model(model_info: &ModelInfo) -> Option<Self> {
+        let file_name = model_info.name.rsplit(['/', '\\']).next().unwrap_or(&model_info.name).to_lowercase();
+        let english = !model_info.is_multilingual();
+        let sized = |en, multilingual| Some(if english { en } else { multilingual });
+        if file_name.contains("large-v3-turbo") || file_name.contains("large-v3_turbo") {
+            Some(DtwPreset::LargeV3Turbo)
+        } else if file_name.contains("large-v3") {
+            Some(DtwPreset::LargeV3)
+        } else if file_name.contains("large-v2") {
+            Some(DtwPreset::LargeV2)
+        } else if file_name.contains("large-v1") {
+            Some(DtwPreset::LargeV1)
+        } else if file_name.contains("large") {
+            None
+        } else if file_name.contains("medium") {
+            sized(DtwPreset::MediumEn, DtwPreset::Medium)
+        } else if file_name.contains("small") {
+            sized(DtwPreset::SmallEn, DtwPreset::Small)
+        } else if file_name.contains("base") {
+            sized(DtwPreset::BaseEn, DtwPreset::Base)
+        } else if file_name.contains("tiny") {
+            sized(DtwPreset::TinyEn, DtwPreset::Tiny)
+        } else {
+            None
+        }
+    }
+    
+    fn model_preset(self) -> DtwModelPreset {
+        match self {
+            DtwPreset::TinyEn => DtwModelPreset::TinyEn,
+            DtwPreset::Tiny => DtwModelPreset::Tiny,
+            DtwPreset::BaseEn => DtwModelPreset::BaseEn,
+            DtwPreset::Base => DtwModelPreset::Base,
+            DtwPreset::SmallEn => DtwModelPreset::SmallEn,
+            DtwPreset::Small => DtwModelPreset::Small,
+            DtwPreset::MediumEn => DtwModelPreset::MediumEn,
+            DtwPreset::Medium => DtwModelPreset::Medium,
+            DtwPreset::LargeV1 => DtwModelPreset::LargeV1,
+            DtwPreset::LargeV2 => DtwModelPreset::LargeV2,
+            DtwPreset::LargeV3 => DtwModelPreset::LargeV3,
+            DtwPreset::LargeV3Turbo => DtwModelPreset::LargeV3Turbo,
+        }
+    }
+}
+
+/// Context parameters for loading a model, on the GPU or not
+/// 
+/// Unlike llama.cpp, whisper.cpp's context params have no mlock option and
+/// the weights live in ggml-owned buffers, so they can't be pinned from here.
+fn context_params(use_gpu: bool, dtw: Option<DtwPreset>) -> WhisperContextParameters<'static> {
+    let mut params = WhisperContextParameters::default();
+    params.use_gpu(use_gpu);
+    if let Some(preset) = dtw {
+        params.dtw_parameters(DtwParameters {
+            mode: DtwMode::ModelPreset { model_preset: preset.model_preset() },
+            ..DtwParameters::default()
+        });
+    }
+    params
+}
+
+/// Thread count baked in at build time from `WHISPER_NODE_DEFAULT_THREADS`
+/// 
+/// build.rs only passes it on as a count from 1 to 256, so the digits
+/// parse without checks. An empty variable leaves it unset.
+const DEFAULT_THREADS: Option<i32> = match option_env!("WHISPER_NODE_DEFAULT_THREADS") {
+    Some(digits) => match parse_thread_count(digits) {
+        0 => None,
+        threads => Some(threads),
+    },
+    None => None,
+};
+
+const fn parse_thread_count(digits: &str) -> i32 {
+    let bytes = digits.as_bytes();
+    let (mut i, mut threads) = (0, 0);
+    while i < bytes.len() {
+        threads = threads * 10 + (bytes[i] - b'0') as i32;
+        i += 1;
+    }
+    threads
+}
+
+/// Defaults a model starts with, picked by size and whether it's multilingual
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tuning {
+    n_threads: i32,
+    sampling: SamplingMode,
+    temperature: f32,
+}
+
+impl Tuning {
+    /// Small English-only models are accurate enough decoding greedily; larger
+    /// and multilingual ones gain more from beam search and extra threads.
+    /// A build-time `DEFAULT_THREADS` replaces the thread counts. Thread
+    /// counts never exceed the machine's cores.
+    fn for_model(model_info: &ModelInfo) -> Self {
+        let (n_threads, beam_size) = match (model_info.size, model_info.is_multilingual()) {
+            (ModelSize::Tiny, _) => (4, None),
+            (ModelSize::Small, false) => (4, None),
+            (ModelSize::Small, true) => (6, Some(3)),
+            (ModelSize::Medium, false) => (8, Some(3)),
+            (ModelSize::Medium, true) => (8, Some(5)),
+            (ModelSize::Large, _) => (8, Some(5)),
+        };
+        let n_threads = DEFAULT_THREADS.unwrap_or(n_threads);
+        let cores = std::thread::available_parallelism().map_or(n_threads, |cores| cores.get() as i32);
+        Self {
+            n_threads: n_threads.min(cores),
+            sampling: match beam_size {
+                Some(beam_size) => SamplingMode::BeamSearch { beam_size, patience: -1.0 },
+                None => SamplingMode::default(),
+            },
+            temperature: 0.0,
+        }
+    }
+}
+
+/// Headroom over a size class's memory limit before a model is treated as mislabeled
+const SIZE_LIMIT_TOLERANCE: f64 = 1.25;
+
 /// Model size variants
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ModelSize {
     Tiny,
     Small,
     Medium,
+    Large, // large, large-v2 and large-v3
 }
 
 impl ModelSize {
     fn from_name(name: &str) -> Self {
         if name.contains("tiny") { ModelSize::Tiny }
         else if name.contains("small") { ModelSize::Small }  
+        else if name.contains("large") { ModelSize::Large }
         else { ModelSize::Medium }
     }
     
@@ -35,6 +246,7 @@ impl ModelSize {
             ModelSize::Tiny => 100 * 1024 * 1024,   // 100MB
             ModelSize::Small => 400 * 1024 * 1024,  // 400MB 
             ModelSize::Medium => 700 * 1024 * 1024, // 700MB
+            ModelSize::Large => 4 * 1024 * 1024 * 1024, // 4GB
         }
     }
     
@@ -43,525 +255,9439 @@ impl ModelSize {
     }
 }
 
-/// Whisper model with lazy loading and memory management
-pub struct WhisperModel {
-    ctx: Option<WhisperContext>,
-    model_path: String,
-    model_info: ModelInfo,
-    last_used: Instant,
-    idle_timeout: Duration,
-    is_loading: bool,
+/// Machine-readable error category reported across the FFI
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhisperError {
+    None = 0,
+    InvalidParams = 1,
+    ModelNotFound = 2,
+    LoadFailed = 3,
+    InferenceFailed = 4,
+    ConversionFailed = 5,
+    Cancelled = 6,
+    FileNotFound = 7,
+    InvalidModel = 8,
+    Internal = 9,
+    TimedOut = 10,
+    InvalidAudio = 11,
+    AudioTooLong = 12,
+    ChecksumMismatch = 13,
 }
 
-impl WhisperModel {
-    fn new(model_path: String, model_info: ModelInfo) -> Self {
-        Self {
-            ctx: None,
-            model_path,
-            model_info,
-            last_used: Instant::now(),
-            idle_timeout: Duration::from_secs(30),
-            is_loading: false,
+impl std::fmt::Display for WhisperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WhisperError::None => "no error",
+            WhisperError::InvalidParams => "invalid parameters",
+            WhisperError::ModelNotFound => "model not registered",
+            WhisperError::LoadFailed => "model failed to load",
+            WhisperError::InferenceFailed => "inference failed",
+            WhisperError::ConversionFailed => "output conversion failed",
+            WhisperError::Cancelled => "transcription cancelled",
+            WhisperError::FileNotFound => "model file not found",
+            WhisperError::InvalidModel => "not a ggml or gguf model file",
+            WhisperError::Internal => "internal error",
+            WhisperError::TimedOut => "transcription timed out",
+            WhisperError::InvalidAudio => "audio is not normalized f32 samples",
+            WhisperError::AudioTooLong => "audio too long for one call",
+            WhisperError::ChecksumMismatch => "model file doesn't match its expected checksum",
+        })
+    }
+}
+
+impl std::error::Error for WhisperError {}
+
+/// Error returned by the manager, carrying its FFI code and a message
+#[derive(Debug, Clone, PartialEq)]
+struct ManagerError {
+    code: WhisperError,
+    message: String,
+}
+
+impl ManagerError {
+    fn new(code: WhisperError, message: impl Into<String>) -> Self {
+        ManagerError { code, message: message.into() }
+    }
+    
+    fn not_found(model_id: &str) -> Self {
+        Self::new(WhisperError::ModelNotFound, format!("Model '{}' not found", model_id))
+    }
+    
+    /// A poisoned lock means an earlier inference panicked
+    fn lock(which: &str) -> Self {
+        Self::new(WhisperError::InferenceFailed, format!("Failed to acquire {} lock", which))
+    }
+}
+
+impl std::fmt::Display for ManagerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// What to do when input doesn't look like normalized f32 samples
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum AudioCheck {
+    Off,
+    #[default]
+    Warn,   // log and transcribe anyway
+    Reject, // fail with `InvalidAudio`
+}
+
+impl AudioCheck {
+    fn from_ffi(mode: i32) -> Option<Self> {
+        match mode {
+            WHISPER_AUDIO_CHECK_OFF => Some(AudioCheck::Off),
+            WHISPER_AUDIO_CHECK_WARN => Some(AudioCheck::Warn),
+            WHISPER_AUDIO_CHECK_REJECT => Some(AudioCheck::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Above this share of out-of-range samples, input is reported as the wrong format
+const MAX_OUT_OF_RANGE_FRACTION: f32 = 0.05;
+
+/// Which kind of core inference threads should run on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum CorePreference {
+    #[default]
+    Auto,        // performance for interactive calls, efficiency for batches
+    Performance, // lowest latency
+    Efficiency,  // lowest power draw, at the cost of latency
+}
+
+impl CorePreference {
+    fn from_ffi(preference: i32) -> Option<Self> {
+        match preference {
+            WHISPER_CORES_AUTO => Some(CorePreference::Auto),
+            WHISPER_CORES_PERFORMANCE => Some(CorePreference::Performance),
+            WHISPER_CORES_EFFICIENCY => Some(CorePreference::Efficiency),
+            _ => None,
         }
     }
     
-    fn ensure_loaded(&mut self) -> Result<&WhisperContext, String> {
-        self.last_used = Instant::now();
-        
-        if self.ctx.is_none() && !self.is_loading {
-            self.is_loading = true;
-            
-            let params = WhisperContextParameters::default();
-            match WhisperContext::new_with_params(&self.model_path, params) {
-                Ok(context) => {
-                    self.ctx = Some(context);
-                    self.is_loading = false;
-                }
-                Err(e) => {
-                    self.is_loading = false;
-                    return Err(format!("Failed to load model: {}", e));
-                }
-            }
+    /// Settle `Auto` for a batch job, which saves power since nobody is waiting on each result
+    fn for_batch(self) -> Self {
+        match self {
+            CorePreference::Auto => CorePreference::Efficiency,
+            other => other,
         }
-        
-        self.ctx.as_ref().ok_or_else(|| "Model not loaded".to_string())
     }
     
-    fn should_unload(&self) -> bool {
-        self.ctx.is_some() && 
-        self.last_used.elapsed() > self.idle_timeout
+    /// Threads to decode with, at most `tuned`; `Auto` counts as interactive
+    /// 
+    /// Without separate efficiency cores, the efficiency preference uses
+    /// half of the cores instead.
+    fn thread_count(self, tuned: i32, cores: CoreCounts) -> i32 {
+        let available = match self {
+            CorePreference::Auto | CorePreference::Performance => cores.performance,
+            CorePreference::Efficiency if cores.efficiency > 0 => cores.efficiency,
+            CorePreference::Efficiency => cores.performance / 2,
+        };
+        tuned.min(available as i32).max(1)
+    }
+}
+
+/// Physical cores by kind; machines without efficiency cores count all as performance cores
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CoreCounts {
+    performance: u32,
+    efficiency: u32,
+}
+
+/// Cores of this machine, read once
+static CORE_COUNTS: once_cell::sync::Lazy<CoreCounts> = once_cell::sync::Lazy::new(core_counts);
+
+/// Decoding strategy used for the first inference pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SamplingMode {
+    Greedy { best_of: i32 },
+    BeamSearch { beam_size: i32, patience: f32 },
+}
+
+impl Default for SamplingMode {
+    fn default() -> Self {
+        SamplingMode::Greedy { best_of: 1 }
+    }
+}
+
+impl SamplingMode {
+    /// Build a mode from its FFI code, rejecting unknown codes and params below 1
+    fn from_ffi(mode: i32, param: i32) -> Option<Self> {
+        if param < 1 {
+            return None;
+        }
+        match mode {
+            WHISPER_SAMPLING_GREEDY => Some(SamplingMode::Greedy { best_of: param }),
+            WHISPER_SAMPLING_BEAM_SEARCH => Some(SamplingMode::BeamSearch { beam_size: param, patience: -1.0 }),
+            _ => None,
+        }
     }
     
-    fn unload(&mut self) {
-        self.ctx = None;
+    /// Greedy decoding keeping the best of `best_of` candidates, capped at `WHISPER_MAX_BEST_OF`
+    fn greedy_best_of(best_of: i32) -> Self {
+        SamplingMode::Greedy { best_of: best_of.clamp(1, WHISPER_MAX_BEST_OF) }
     }
     
-    fn memory_usage(&self) -> u64 {
-        if self.ctx.is_some() {
-            self.model_info.memory_usage
-        } else {
-            0
+    fn strategy(&self) -> SamplingStrategy {
+        match *self {
+            SamplingMode::Greedy { best_of } => SamplingStrategy::Greedy { best_of },
+            SamplingMode::BeamSearch { beam_size, patience } => SamplingStrategy::BeamSearch { beam_size, patience },
         }
     }
 }
 
-/// Thread-safe whisper model manager with automatic memory management
-pub struct WhisperManager {
-    models: RwLock<HashMap<String, Arc<Mutex<WhisperModel>>>>,
-    memory_limit: u64,
-    cpu_monitor: Arc<Mutex<CpuMonitor>>,
+/// Retry a low-confidence greedy transcription once with beam search
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ConfidenceRetry {
+    threshold: f32, // mean token probability below which the retry fires
+    beam_size: i32,
 }
 
-/// CPU usage monitoring for automatic model downgrade
-pub struct CpuMonitor {
-    cpu_samples: Vec<f32>,
-    sample_count: usize,
-    max_samples: usize,
+impl ConfidenceRetry {
+    fn should_retry(&self, confidence: f32) -> bool {
+        confidence < self.threshold
+    }
 }
 
-impl CpuMonitor {
-    fn new() -> Self {
-        Self {
-            cpu_samples: Vec::new(),
-            sample_count: 0,
-            max_samples: 10, // Track last 10 inference operations
+/// Silence trimming applied before inference
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct VadConfig {
+    threshold: f32, // frame RMS counted as speech
+    pre_roll_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        VadConfig {
+            threshold: vad::DEFAULT_THRESHOLD,
+            pre_roll_ms: vad::DEFAULT_PRE_ROLL_MS,
         }
     }
-    
-    fn record_cpu_usage(&mut self, cpu_percent: f32) {
-        if self.cpu_samples.len() < self.max_samples {
-            self.cpu_samples.push(cpu_percent);
+}
+
+/// Per-model transcription settings that persist across loads
+#[derive(Debug, Clone, Default, PartialEq)]
+struct TranscribeSettings {
+    sampling: SamplingMode,
+    initial_prompt: Option<String>, // biases decoding towards this vocabulary
+    confidence_retry: Option<ConfidenceRetry>,
+    min_avg_logprob: Option<f32>, // segments below this are dropped
+    check_sample_rate: bool,      // warn when the spectrum doesn't match 16kHz
+    audio_check: AudioCheck,      // reaction to samples outside [-1, 1]
+    vad: Option<VadConfig>,       // trim leading/trailing silence when set
+    translate: bool,              // emit English text whatever the spoken language
+    temperature: f32,             // initial sampling temperature, 0 = deterministic
+    temperature_inc: Option<f32>, // fallback step; None keeps whisper's default of 0.2
+    suppress_non_speech: bool,    // strip [BLANK_AUDIO], (wind blowing) and similar
+    auto_gain: bool,              // normalize input levels before inference
+    context_carryover: bool,      // seed each call with the previous call's trailing tokens
+    language: Option<&'static str>, // explicit source language; None means English, or detection when translating
+    n_threads: Option<i32>,       // None uses the model's tuned thread count
+    timeout: Option<Duration>,    // wall-clock inference budget; only set for a single call
+    suppress_blank: Option<bool>, // None keeps whisper's default of suppressing blank starts
+    suppress_nst: bool,           // stop the decoder emitting non-speech tokens
+    collapse_repeats: bool,       // cut hallucinated repetition loops at the end of segments
+    max_segment_len: Option<i32>, // split segments at word boundaries past this many characters
+    lead_silence_ms: u32,         // silence prepended before inference, removed from timestamps
+    speaker_gap_ms: Option<u32>,  // mark a probable speaker change after silences longer than this
+    text_format: u32,             // WHISPER_TEXT_* flags applied to the joined text
+    suppress_tokens: Vec<WhisperToken>, // token ids the decoder may never emit
+    max_audio_secs: Option<u32>,  // longest buffer one run accepts; None keeps DEFAULT_MAX_AUDIO_SECS
+    strict_segments: bool,        // fail on unreadable segment text instead of a placeholder
+    core_preference: CorePreference, // cores to run on, see `whisper_set_core_preference`
+    dtw: Option<DtwPreset>,       // alignment heads for DTW token timestamps, applied when the context loads
+    trim: TrimPolicy,             // whitespace kept around the joined text
+    range: Option<(i32, i32)>,    // (offset_ms, duration_ms) window from `range_window`; only set for a single call
+}
+
+impl TranscribeSettings {
+    /// Source language passed to whisper
+    /// 
+    /// Translation needs the real source language, so unless one is given it
+    /// switches to detection.
+    fn language(&self) -> &'static str {
+        if let Some(language) = self.language {
+            language
+        } else if self.translate {
+            "auto"
         } else {
-            self.cpu_samples[self.sample_count % self.max_samples] = cpu_percent;
+            "en"
         }
-        self.sample_count += 1;
     }
     
-    fn average_cpu_usage(&self) -> f32 {
-        if self.cpu_samples.is_empty() {
-            0.0
-        } else {
-            self.cpu_samples.iter().sum::<f32>() / self.cpu_samples.len() as f32
-        }
+    /// The requested language, unless whisper is left to detect it
+    fn fixed_language(&self) -> Option<&'static str> {
+        Some(self.language()).filter(|&language| language != "auto")
     }
     
-    fn should_downgrade(&self, threshold: f32) -> bool {
-        self.average_cpu_usage() > threshold
+    /// Threads to decode with: the explicit count if set, otherwise the
+    /// model's tuned count limited to the preferred cores
+    fn thread_count(&self, model_info: &ModelInfo) -> i32 {
+        self.n_threads.unwrap_or_else(|| {
+            self.core_preference.thread_count(Tuning::for_model(model_info).n_threads, *CORE_COUNTS)
+        })
     }
 }
 
-impl WhisperManager {
-    fn new() -> Self {
-        Self {
-            models: RwLock::new(HashMap::new()),
-            memory_limit: 700 * 1024 * 1024, // 700MB peak limit
-            cpu_monitor: Arc::new(Mutex::new(CpuMonitor::new())),
+/// Text of one decoded segment with its timing and confidence
+#[derive(Debug, Clone)]
+struct DecodedSegment {
+    text: String,
+    start_ms: i64,
+    end_ms: i64,
+    confidence: f32,     // mean probability of the segment's text tokens
+    avg_logprob: f32,    // mean log probability of the segment's text tokens
+    no_speech_prob: f32, // estimated from the segment's audio, see `silent_fraction`
+    starts_new_turn: bool, // follows a gap longer than the speaker gap, see `mark_speaker_turns`
+    language: Option<&'static str>, // language of the run that decoded it, see `Decoded::language`
+    tokens: Vec<DecodedToken>,
+}
+
+/// One decoded token; times are only meaningful with token timestamps enabled
+#[derive(Debug, Clone)]
+struct DecodedToken {
+    id: WhisperToken,
+    bytes: Vec<u8>, // raw text bytes, may split a multi-byte character
+    p: f32,
+    start_ms: i64,
+    end_ms: i64,
+    dtw_ms: Option<i64>, // DTW-aligned time, only when DTW timestamps are enabled
+    is_special: bool, // timestamp, SOT, EOT and other control tokens
+}
+
+/// A whole word merged from its subword tokens
+#[derive(Debug, Clone, PartialEq)]
+struct DecodedWord {
+    text: String,
+    start_ms: i64,
+    end_ms: i64,
+    probability: f32, // mean probability of the merged tokens
+    dtw_ms: Option<i64>, // DTW-aligned time of the first token, only when DTW timestamps are enabled
+}
+
+/// Merge subword tokens into words
+/// 
+/// A token starting with a space begins a new word; any other token
+/// (including punctuation) extends the current one. Special tokens are skipped.
+fn merge_words(tokens: &[DecodedToken]) -> Vec<DecodedWord> {
+    fn finish(bytes: &[u8], start_ms: i64, end_ms: i64, dtw_ms: Option<i64>, probabilities: &[f32], words: &mut Vec<DecodedWord>) {
+        let text = String::from_utf8_lossy(bytes).trim().to_string();
+        if !text.is_empty() {
+            words.push(DecodedWord {
+                text,
+                start_ms,
+                end_ms,
+                probability: probabilities.iter().sum::<f32>() / probabilities.len() as f32,
+                dtw_ms,
+            });
         }
     }
     
-    fn register_model(&self, id: String, model_path: String, model_info: ModelInfo) -> Result<(), String> {
-        let model = WhisperModel::new(model_path, model_info);
-        let mut models = self.models.write().map_err(|_| "Failed to acquire write lock")?;
-        models.insert(id, Arc::new(Mutex::new(model)));
-        Ok(())
-    }
+    let mut words = Vec::new();
+    let mut bytes = Vec::new();
+    let mut probabilities = Vec::new();
+    let (mut start_ms, mut end_ms, mut dtw_ms) = (0, 0, None);
     
-    fn transcribe(&self, model_id: &str, audio_data: &[f32]) -> Result<String, String> {
-        let start_time = Instant::now();
-        
-        // Check memory usage before inference
-        self.manage_memory()?;
-        
-        let result = {
-            let models = self.models.read().map_err(|_| "Failed to acquire read lock")?;
-            let model_arc = models.get(model_id)
-                .ok_or_else(|| format!("Model '{}' not found", model_id))?;
-            
-            let mut model = model_arc.lock().map_err(|_| "Failed to acquire model lock")?;
-            let context = model.ensure_loaded()?;
-            
-            // Prepare inference parameters
-            let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-            params.set_n_threads(4); // Optimize for Apple Silicon
-            params.set_language(Some("en"));
-            params.set_print_special(false);
-            params.set_print_progress(false);
-            params.set_print_realtime(false);
-            params.set_print_timestamps(false);
-            
-            // Create state for inference
-            let mut state = context.create_state()
-                .map_err(|e| format!("Failed to create state: {}", e))?;
-
-            // Run inference
-            state.full(params, audio_data)
-                .map_err(|e| format!("Transcription failed: {}", e))?;
-
-            // Extract text results
-            let num_segments = state.full_n_segments()
-                .map_err(|e| format!("Failed to get segment count: {}", e))?;
-
-            let mut full_text = String::new();
-            for i in 0..num_segments {
-                match state.full_get_segment_text(i) {
-                    Ok(segment_text) => {
-                        full_text.push_str(&segment_text);
-                        if i < num_segments - 1 {
-                            full_text.push(' ');
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Warning: Failed to get segment {} text: {}", i, e);
-                        continue;
-                    }
-                }
-            }
-            
-            Ok(full_text.trim().to_string())
-        };
-        
-        // Record CPU usage (simplified - in real implementation would measure actual CPU)
-        let inference_duration = start_time.elapsed();
-        let estimated_cpu = (inference_duration.as_secs_f32() * 100.0).min(100.0);
-        
-        if let Ok(mut monitor) = self.cpu_monitor.lock() {
-            monitor.record_cpu_usage(estimated_cpu);
+    for token in tokens.iter().filter(|token| !token.is_special) {
+        if token.bytes.first() == Some(&b' ') && !bytes.is_empty() {
+            finish(&bytes, start_ms, end_ms, dtw_ms, &probabilities, &mut words);
+            bytes.clear();
+            probabilities.clear();
         }
-        
-        result
+        if bytes.is_empty() {
+            start_ms = token.start_ms;
+            dtw_ms = token.dtw_ms;
+        }
+        bytes.extend_from_slice(&token.bytes);
+        probabilities.push(token.p);
+        end_ms = token.end_ms;
+    }
+    if !bytes.is_empty() {
+        finish(&bytes, start_ms, end_ms, dtw_ms, &probabilities, &mut words);
     }
     
-    fn manage_memory(&self) -> Result<(), String> {
-        let current_usage = self.current_memory_usage();
-        
-        if current_usage > self.memory_limit {
-            // Unload idle models to free memory
-            let models = self.models.read().map_err(|_| "Failed to acquire read lock")?;
-            
-            for model_arc in models.values() {
-                if let Ok(mut model) = model_arc.lock() {
-                    if model.should_unload() {
-                        model.unload();
-                    }
-                }
-            }
+    words
+}
+
+/// Frame length used when estimating silence within a segment
+const SILENCE_FRAME_MS: i64 = 20;
+
+/// RMS level below which a frame is considered silent
+const SILENCE_RMS_THRESHOLD: f32 = 0.01;
+
+/// Fraction of 20ms frames between `start_ms` and `end_ms` that are silent
+/// 
+/// whisper-rs does not expose whisper.cpp's per-segment no-speech
+/// probability for a separate state, so this is the energy-based stand-in.
+fn silent_fraction(audio: &[f32], start_ms: i64, end_ms: i64) -> f32 {
+    let to_sample = |ms: i64| ((ms.max(0) as usize) * WHISPER_SAMPLE_RATE / 1000).min(audio.len());
+    let window = &audio[to_sample(start_ms)..to_sample(end_ms.max(start_ms))];
+    let frame_len = (SILENCE_FRAME_MS as usize) * WHISPER_SAMPLE_RATE / 1000;
+    
+    let mut frames = 0usize;
+    let mut silent = 0usize;
+    for frame in window.chunks(frame_len) {
+        frames += 1;
+        if audio::rms(frame) < SILENCE_RMS_THRESHOLD {
+            silent += 1;
         }
-        
-        Ok(())
     }
     
-    fn current_memory_usage(&self) -> u64 {
-        let models = match self.models.read() {
-            Ok(models) => models,
-            Err(_) => return 0,
-        };
-        
-        models.values()
-            .filter_map(|model_arc| {
-                model_arc.lock().ok().map(|model| model.memory_usage())
-            })
-            .sum()
+    if frames == 0 {
+        1.0
+    } else {
+        silent as f32 / frames as f32
     }
-    
-    fn suggest_model_downgrade(&self, current_model: &str) -> Option<String> {
-        if let Ok(monitor) = self.cpu_monitor.lock() {
-            if monitor.should_downgrade(80.0) {
-                // Suggest smaller model based on current model
-                if current_model.contains("medium") {
-                    return Some("small".to_string());
-                } else if current_model.contains("small") {
-                    return Some("tiny".to_string());
-                }
-            }
-        }
-        None
+}
+
+/// Shortest input passed to whisper; whisper.cpp skips anything under 1s,
+/// and the margin covers mel frame rounding
+const MIN_INPUT_SAMPLES: usize = WHISPER_SAMPLE_RATE + WHISPER_SAMPLE_RATE / 10;
+
+/// A quiet 220Hz tone for benchmarking; silence would decode unrealistically fast
+fn benchmark_audio(samples: usize) -> Vec<f32> {
+    (0..samples)
+        .map(|i| 0.1 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / WHISPER_SAMPLE_RATE as f32).sin())
+        .collect()
+}
+
+/// Time `run` against `audio_ms` of audio, as inference time over audio time
+fn real_time_factor<E>(audio_ms: u64, run: impl FnOnce() -> Result<(), E>) -> Result<f32, E> {
+    let start = Instant::now();
+    run()?;
+    Ok(start.elapsed().as_secs_f32() * 1000.0 / audio_ms as f32)
+}
+
+/// Append silence to inputs shorter than `MIN_INPUT_SAMPLES`
+/// 
+/// Only trailing silence is added, so timestamps keep their meaning.
+fn pad_short_input(input: Cow<'_, [f32]>) -> Cow<'_, [f32]> {
+    if input.len() >= MIN_INPUT_SAMPLES {
+        return input;
     }
+    Cow::Owned(audio::pad_or_trim(&input, MIN_INPUT_SAMPLES).into_owned())
 }
 
-/// Global whisper manager instance
-static WHISPER_MANAGER: once_cell::sync::Lazy<WhisperManager> = 
-    once_cell::sync::Lazy::new(|| WhisperManager::new());
+/// Longest lead-in `whisper_set_lead_silence_ms` accepts, one encoder window
+const MAX_LEAD_SILENCE_MS: u32 = 30_000;
 
-/// Opaque handle for FFI - now just contains model ID
-pub struct WhisperHandle {
-    model_id: String,
+/// Longest buffer a single inference run accepts unless `whisper_set_max_audio_secs` says otherwise
+/// 
+/// Matches the longest capture buffer; even a medium model handles this much
+/// in one call, while an hour-long recording could exhaust host memory.
+const DEFAULT_MAX_AUDIO_SECS: u32 = 600;
+
+/// Prepend `lead_ms` of silence to `audio`, borrowing it unchanged when there's none
+fn prepend_silence(audio: &[f32], lead_ms: u32) -> Cow<'_, [f32]> {
+    let lead = lead_ms as usize * WHISPER_SAMPLE_RATE / 1000;
+    if lead == 0 {
+        return Cow::Borrowed(audio);
+    }
+    let mut padded = vec![0.0; lead + audio.len()];
+    padded[lead..].copy_from_slice(audio);
+    Cow::Owned(padded)
 }
 
-/// FFI-safe result structure
-#[repr(C)]
-pub struct WhisperResult {
-    pub success: bool,
-    pub text: *mut c_char,
-    pub error: *mut c_char,
+/// Clamp segment and token times to `0..=end_ms`, so padding never shows up in timestamps
+fn clamp_segments(segments: &mut [DecodedSegment], end_ms: i64) {
+    for segment in segments {
+        segment.start_ms = segment.start_ms.clamp(0, end_ms);
+        segment.end_ms = segment.end_ms.clamp(0, end_ms);
+        for token in &mut segment.tokens {
+            token.start_ms = token.start_ms.clamp(0, end_ms);
+            token.end_ms = token.end_ms.clamp(0, end_ms);
+        }
+    }
 }
 
-/// Initialize whisper context with model path
-#[no_mangle]
-pub extern "C" fn whisper_init(model_path: *const c_char) -> *mut WhisperHandle {
-    if model_path.is_null() {
-        return std::ptr::null_mut();
+/// Offset and duration whisper decodes for a `range` window once `lead_ms` of silence is prepended
+/// 
+/// The lead-in moves the whole buffer later, so the window moves with it
+/// and still covers the same audio.
+fn padded_window((offset_ms, duration_ms): (i32, i32), lead_ms: i64) -> (i32, i32) {
+    (offset_ms.saturating_add(lead_ms as i32), duration_ms)
+}
+
+/// Map times decoded over padded input back onto the caller's audio
+/// 
+/// `lead_ms` of silence was prepended to `len_ms` of audio that starts
+/// `offset_ms` into the caller's buffer. Anything decoded over the padding
+/// is pulled onto the real audio.
+fn restore_timeline(segments: &mut [DecodedSegment], lead_ms: i64, len_ms: i64, offset_ms: i64) {
+    offset_segments(segments, -lead_ms);
+    clamp_segments(segments, len_ms);
+    offset_segments(segments, offset_ms);
+}
+
+/// Shift segment and token timestamps by `offset_ms`
+fn offset_segments(segments: &mut [DecodedSegment], offset_ms: i64) {
+    for segment in segments {
+        segment.start_ms += offset_ms;
+        segment.end_ms += offset_ms;
+        for token in &mut segment.tokens {
+            token.start_ms += offset_ms;
+            token.end_ms += offset_ms;
+            if let Some(dtw_ms) = &mut token.dtw_ms {
+                *dtw_ms += offset_ms;
+            }
+        }
+    }
+}
+
+/// Sample ranges of `chunk`-long windows over `len` samples, each starting `overlap` before the last one ended
+/// 
+/// `overlap` must be smaller than `chunk`. The final window is cut short at
+/// the end of the buffer.
+fn chunk_windows(len: usize, chunk: usize, overlap: usize) -> Vec<(usize, usize)> {
+    let step = chunk - overlap;
+    let mut windows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + chunk).min(len);
+        windows.push((start, end));
+        if end == len {
+            return windows;
+        }
+        start += step;
+    }
+}
+
+/// Word compared across chunk boundaries, ignoring case and punctuation
+fn boundary_word(word: &str) -> String {
+    word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Append a chunk's segments, dropping what the previous chunk already covered
+/// 
+/// Segments ending before the stitched transcript does are repeats of the
+/// overlap. The first remaining segment loses any leading words that repeat
+/// the transcript's last segment, and starts no earlier than it ended.
+fn stitch_chunk(stitched: &mut Vec<DecodedSegment>, chunk: Vec<DecodedSegment>) {
+    let covered_ms = stitched.last().map_or(i64::MIN, |segment| segment.end_ms);
+    let mut chunk = chunk.into_iter().filter(|segment| segment.end_ms > covered_ms).peekable();
+    
+    if let (Some(previous), Some(first)) = (stitched.last(), chunk.peek_mut()) {
+        let tail: Vec<String> = previous.text.split_whitespace().map(boundary_word).collect();
+        let head: Vec<&str> = first.text.split_whitespace().collect();
+        let repeated = (1..=tail.len().min(head.len()))
+            .rev()
+            .find(|&n| {
+                tail[tail.len() - n..].iter()
+                    .zip(&head[..n])
+                    .all(|(previous, next)| *previous == boundary_word(next))
+            })
+            .unwrap_or(0);
+        if repeated > 0 {
+            first.text = head[repeated..].iter().fold(String::new(), |text, word| text + " " + word);
+        }
+        first.start_ms = first.start_ms.max(previous.end_ms);
+    }
+    
+    stitched.extend(chunk.filter(|segment| !segment.text.trim().is_empty()));
+}
+
+/// Flag each segment that starts more than `gap_ms` after the previous one ended
+/// 
+/// A long pause is a cheap hint that someone else is speaking; the first
+/// segment is never flagged since there's nothing to compare it with.
+fn mark_speaker_turns(segments: &mut [DecodedSegment], gap_ms: u32) {
+    let mut previous_end = None;
+    for segment in segments {
+        segment.starts_new_turn = previous_end.is_some_and(|end_ms| segment.start_ms - end_ms > gap_ms as i64);
+        previous_end = Some(segment.end_ms);
+    }
+}
+
+/// Drop segments whose mean token log probability is below `min_avg_logprob`
+fn filter_low_confidence(segments: Vec<DecodedSegment>, min_avg_logprob: f32) -> Vec<DecodedSegment> {
+    segments.into_iter()
+        .filter(|segment| segment.avg_logprob >= min_avg_logprob)
+        .collect()
+}
+
+/// The parts of a finished inference state `collect_segments` reads, so tests can stand in for it
+trait SegmentSource {
+    fn full_n_segments(&self) -> Result<i32, whisper_rs::WhisperError>;
+    fn full_get_segment_text(&self, segment: i32) -> Result<String, whisper_rs::WhisperError>;
+    fn full_get_segment_t0(&self, segment: i32) -> Result<i64, whisper_rs::WhisperError>;
+    fn full_get_segment_t1(&self, segment: i32) -> Result<i64, whisper_rs::WhisperError>;
+    fn full_n_tokens(&self, segment: i32) -> Result<i32, whisper_rs::WhisperError>;
+    fn full_get_token_data(&self, segment: i32, token: i32) -> Result<WhisperTokenData, whisper_rs::WhisperError>;
+    fn full_get_token_bytes(&self, segment: i32, token: i32) -> Result<Vec<u8>, whisper_rs::WhisperError>;
+}
+
+#[cfg(not(feature = "mock"))]
+impl SegmentSource for WhisperState {
+    fn full_n_segments(&self) -> Result<i32, whisper_rs::WhisperError> {
+        WhisperState::full_n_segments(self)
+    }
+    
+    fn full_get_segment_text(&self, segment: i32) -> Result<String, whisper_rs::WhisperError> {
+        WhisperState::full_get_segment_text(self, segment)
+    }
+    
+    fn full_get_segment_t0(&self, segment: i32) -> Result<i64, whisper_rs::WhisperError> {
+        WhisperState::full_get_segment_t0(self, segment)
+    }
+    
+    fn full_get_segment_t1(&self, segment: i32) -> Result<i64, whisper_rs::WhisperError> {
+        WhisperState::full_get_segment_t1(self, segment)
+    }
+    
+    fn full_n_tokens(&self, segment: i32) -> Result<i32, whisper_rs::WhisperError> {
+        WhisperState::full_n_tokens(self, segment)
+    }
+    
+    fn full_get_token_data(&self, segment: i32, token: i32) -> Result<WhisperTokenData, whisper_rs::WhisperError> {
+        WhisperState::full_get_token_data(self, segment, token)
+    }
+    
+    fn full_get_token_bytes(&self, segment: i32, token: i32) -> Result<Vec<u8>, whisper_rs::WhisperError> {
+        WhisperState::full_get_token_bytes(self, segment, token)
+    }
+}
+
+/// Text given to a segment whose text couldn't be read, unless segments are strict
+/// 
+/// U+FFFD, the Unicode replacement character, so the gap shows in the text
+/// rather than vanishing; non-speech suppression leaves it alone.
+const UNREADABLE_SEGMENT_TEXT: &str = " \u{FFFD}";
+
+/// Read every segment out of a finished inference state
+/// 
+/// A segment whose text can't be read fails the whole call when `strict`
+/// is set, naming its index. Otherwise it keeps its timing with
+/// `UNREADABLE_SEGMENT_TEXT` in place of the text, and the indices are logged.
+fn collect_segments(
+    state: &impl SegmentSource,
+    token_eot: WhisperToken,
+    audio: &[f32],
+    strict: bool,
+) -> Result<Vec<DecodedSegment>, ManagerError> {
+    let num_segments = state.full_n_segments()
+        .map_err(|e| ManagerError::new(WhisperError::InferenceFailed, format!("Failed to get segment count: {}", e)))?;
+    
+    let mut segments = Vec::with_capacity(num_segments.max(0) as usize);
+    let mut unreadable = Vec::new();
+    for i in 0..num_segments {
+        let text = match state.full_get_segment_text(i) {
+            Ok(text) => text,
+            Err(e) if strict => {
+                return Err(ManagerError::new(
+                    WhisperError::ConversionFailed,
+                    format!("Failed to read the text of segment {}: {}", i, e),
+                ));
+            }
+            Err(_) => {
+                unreadable.push(i.to_string());
+                UNREADABLE_SEGMENT_TEXT.to_string()
+            }
+        };
+        
+        // Segment timestamps are in 10ms units
+        let start_ms = state.full_get_segment_t0(i).unwrap_or(0) * 10;
+        let end_ms = state.full_get_segment_t1(i).unwrap_or(0) * 10;
+        
+        // Special tokens (timestamps, SOT, EOT) sort after EOT in the vocabulary
+        let num_tokens = state.full_n_tokens(i).unwrap_or(0);
+        let mut tokens = Vec::with_capacity(num_tokens.max(0) as usize);
+        let (mut p_sum, mut plog_sum, mut text_tokens) = (0.0f32, 0.0f32, 0usize);
+        for j in 0..num_tokens {
+            let Ok(data) = state.full_get_token_data(i, j) else {
+                continue;
+            };
+            let is_special = data.id >= token_eot;
+            if !is_special {
+                p_sum += data.p;
+                plog_sum += data.plog;
+                text_tokens += 1;
+            }
+            tokens.push(DecodedToken {
+                id: data.id,
+                bytes: state.full_get_token_bytes(i, j).unwrap_or_default(),
+                p: data.p,
+                start_ms: data.t0 * 10,
+                end_ms: data.t1 * 10,
+                dtw_ms: (data.t_dtw >= 0).then_some(data.t_dtw * 10),
+                is_special,
+            });
+        }
+        let (confidence, avg_logprob) = if text_tokens == 0 {
+            (0.0, f32::NEG_INFINITY)
+        } else {
+            (p_sum / text_tokens as f32, plog_sum / text_tokens as f32)
+        };
+        
+        segments.push(DecodedSegment {
+            text,
+            start_ms,
+            end_ms,
+            confidence,
+            avg_logprob,
+            no_speech_prob: silent_fraction(audio, start_ms, end_ms),
+            starts_new_turn: false,
+            language: None,
+            tokens,
+        });
+    }
+    
+    if !unreadable.is_empty() {
+        logging::warn(&format!(
+            "Text of segment(s) {} couldn't be read and was replaced with U+FFFD",
+            unreadable.join(", ")
+        ));
+    }
+    Ok(segments)
+}
+
+/// Mean confidence across segments, or None when nothing was decoded
+fn mean_confidence(segments: &[DecodedSegment]) -> Option<f32> {
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments.iter().map(|segment| segment.confidence).sum::<f32>() / segments.len() as f32)
+    }
+}
+
+/// Remove bracketed and parenthesized non-speech annotations from `text`
+/// 
+/// Whisper marks non-speech audio as `[BLANK_AUDIO]`, `[MUSIC]`,
+/// `(wind blowing)` and so on. It doesn't use parentheses for spoken words,
+/// so every parenthesized span is treated as an annotation too, including
+/// sound descriptions like "(laughing)". Unbalanced brackets are kept.
+fn strip_non_speech(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find(['[', '(']) {
+        let close = if rest.as_bytes()[open] == b'[' { ']' } else { ')' };
+        let Some(len) = rest[open..].find(close) else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        rest = &rest[open + len + 1..];
+    }
+    out.push_str(rest);
+    
+    // Collapse the gaps left behind, keeping the leading space whisper uses between segments
+    let collapsed = out.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.starts_with(' ') && !collapsed.is_empty() {
+        format!(" {}", collapsed)
+    } else {
+        collapsed
+    }
+}
+
+/// Strip non-speech annotations, dropping segments left without text
+fn suppress_non_speech(segments: Vec<DecodedSegment>) -> Vec<DecodedSegment> {
+    segments.into_iter()
+        .filter_map(|mut segment| {
+            segment.text = strip_non_speech(&segment.text);
+            (!segment.text.trim().is_empty()).then_some(segment)
+        })
+        .collect()
+}
+
+/// Repeats of a phrase kept at the end of a segment, so "no no no" survives
+const MAX_TRAILING_REPEATS: usize = 3;
+
+/// Cut a phrase repeated more than `max_repeat` times at the end of `text` to one occurrence
+/// 
+/// Whisper sometimes loops on its last words ("you you you you"). Phrases of
+/// any length are matched, ignoring case and surrounding punctuation, and
+/// the last occurrence is the one kept. Words that are only punctuation,
+/// like "-" or "...", are skipped when matching, so they never form a run
+/// of their own. Text without such a run is returned unchanged.
+fn collapse_repetitions(text: &str, max_repeat: usize) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let (positions, normalized): (Vec<usize>, Vec<String>) = words.iter()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .enumerate()
+        .filter(|(_, word)| !word.is_empty())
+        .unzip();
+    
+    for phrase_len in 1..=normalized.len() / (max_repeat + 1) {
+        let phrase = &normalized[normalized.len() - phrase_len..];
+        let repeats = normalized.rchunks_exact(phrase_len)
+            .take_while(|chunk| *chunk == phrase)
+            .count();
+        if repeats > max_repeat {
+            // Everything before the run, then its last occurrence and what follows it
+            let run_start = positions[normalized.len() - repeats * phrase_len];
+            let last_start = positions[normalized.len() - phrase_len];
+            let collapsed = [&words[..run_start], &words[last_start..]].concat().join(" ");
+            return if text.starts_with(' ') { format!(" {}", collapsed) } else { collapsed };
+        }
+    }
+    text.to_string()
+}
+
+/// Languages written without spaces between words
+const SPACELESS_LANGUAGES: [&str; 8] = ["ja", "zh", "yue", "th", "lo", "my", "km", "bo"];
+
+/// Whether `c` belongs to a script written without spaces between words
+fn is_spaceless_char(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{30FF}'   // CJK punctuation, hiragana, katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{FF00}'..='\u{FFEF}' // full-width forms
+        | '\u{0E00}'..='\u{0EFF}' // Thai, Lao
+        | '\u{1000}'..='\u{109F}' // Myanmar
+        | '\u{1780}'..='\u{17FF}' // Khmer
+        | '\u{0F00}'..='\u{0FFF}' // Tibetan
+    )
+}
+
+/// Join segment texts into the final transcription
+/// 
+/// Whisper starts most segments with a space, so whitespace is normalized
+/// once over the result: runs collapse to a single space, and segments are
+/// separated by exactly one. No space is inserted between segments of a
+/// space-less language, or, when the language is unknown, between
+/// segments whose touching characters are both from such a script.
+fn join_segments(segments: &[DecodedSegment], language: Option<&str>) -> String {
+    join_segments_with_ranges(segments, language).0
+}
+
+/// `join_segments`, plus the byte range each segment's normalized text takes in the result
+/// 
+/// Separators belong to no segment, and a segment left empty by
+/// normalization gets an empty range where it would have gone.
+fn join_segments_with_ranges(segments: &[DecodedSegment], language: Option<&str>) -> (String, Vec<Range<usize>>) {
+    let spaceless_language = language.is_some_and(|code| SPACELESS_LANGUAGES.contains(&code));
+    let mut text = String::new();
+    let mut ranges = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let piece = segment.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let (Some(last), Some(first)) = (text.chars().last(), piece.chars().next()) {
+            let spaceless = match language {
+                Some(_) => spaceless_language,
+                None => is_spaceless_char(last) && is_spaceless_char(first),
+            };
+            if !spaceless {
+                text.push(' ');
+            }
+        }
+        let start = text.len();
+        text.push_str(&piece);
+        ranges.push(start..text.len());
+    }
+    (text, ranges)
+}
+
+/// `whisper_set_text_format` flag: lowercase the whole text
+pub const WHISPER_TEXT_LOWERCASE: u32 = 1;
+/// `whisper_set_text_format` flag: drop punctuation ending the text
+pub const WHISPER_TEXT_STRIP_TRAILING_PUNCT: u32 = 2;
+/// `whisper_set_text_format` flag: lowercase the first letter of the text
+pub const WHISPER_TEXT_STRIP_LEADING_CAPITAL: u32 = 4;
+const WHISPER_TEXT_ALL: u32 = WHISPER_TEXT_LOWERCASE | WHISPER_TEXT_STRIP_TRAILING_PUNCT | WHISPER_TEXT_STRIP_LEADING_CAPITAL;
+
+/// Sentence punctuation `WHISPER_TEXT_STRIP_TRAILING_PUNCT` removes; closing brackets and quotes are kept
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', '!', '?', ';', ':', '…', '。', '、', '！', '？'];
+
+/// Which ends of the joined text lose whisper's surrounding whitespace
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum TrimPolicy {
+    None,
+    #[default]
+    Both,
+    LeadingOnly,
+    TrailingOnly, // keeps the leading space, e.g. for inserting after a word at the cursor
+}
+
+impl TrimPolicy {
+    fn from_ffi(policy: i32) -> Option<Self> {
+        match policy {
+            WHISPER_TRIM_NONE => Some(TrimPolicy::None),
+            WHISPER_TRIM_BOTH => Some(TrimPolicy::Both),
+            WHISPER_TRIM_LEADING => Some(TrimPolicy::LeadingOnly),
+            WHISPER_TRIM_TRAILING => Some(TrimPolicy::TrailingOnly),
+            _ => None,
+        }
+    }
+    
+    /// Whitespace to put back before and after the joined text, taken from the ends of `raw`
+    /// 
+    /// Text that is all whitespace gets none, so an empty result stays empty.
+    fn padding(self, raw: &str) -> (&str, &str) {
+        let (leading, trailing) = match self {
+            TrimPolicy::None => (true, true),
+            TrimPolicy::Both => (false, false),
+            TrimPolicy::LeadingOnly => (false, true),
+            TrimPolicy::TrailingOnly => (true, false),
+        };
+        if raw.trim().is_empty() {
+            return ("", "");
+        }
+        let before = if leading { &raw[..raw.len() - raw.trim_start().len()] } else { "" };
+        let after = if trailing { &raw[raw.trim_end().len()..] } else { "" };
+        (before, after)
+    }
+}
+
+/// Apply `WHISPER_TEXT_*` flags to joined transcription text
+/// 
+/// Stripping the leading capital keeps the pronoun "I" and words that look
+/// like acronyms, such as "API", as they are.
+fn format_text(text: String, flags: u32) -> String {
+    let mut text = if flags & WHISPER_TEXT_LOWERCASE != 0 { text.to_lowercase() } else { text };
+    if flags & WHISPER_TEXT_STRIP_TRAILING_PUNCT != 0 {
+        text.truncate(text.trim_end().trim_end_matches(TRAILING_PUNCTUATION).trim_end().len());
+    }
+    if flags & WHISPER_TEXT_STRIP_LEADING_CAPITAL != 0 {
+        let first_word = text.split(|c: char| !c.is_alphanumeric() && c != '\'').next().unwrap_or("");
+        let mut letters = first_word.chars();
+        let keep = first_word == "I"
+            || first_word.starts_with("I'")
+            || letters.nth(1).is_some_and(char::is_uppercase);
+        if let Some(first) = text.chars().next().filter(|first| first.is_uppercase() && !keep) {
+            text = first.to_lowercase().chain(text[first.len_utf8()..].chars()).collect();
+        }
+    }
+    text
+}
+
+/// Most tokens carried into the next call, well under whisper's 224-token prompt cap
+const CARRYOVER_MAX_TOKENS: usize = 64;
+
+/// The last `max_tokens` text tokens of a result, for seeding the next call
+/// 
+/// Returns None when nothing was decoded, so a silent chunk keeps the
+/// context from before it.
+fn carryover_tokens(segments: &[DecodedSegment], max_tokens: usize) -> Option<Vec<WhisperToken>> {
+    let mut tail: Vec<WhisperToken> = segments.iter()
+        .rev()
+        .flat_map(|segment| segment.tokens.iter().rev())
+        .filter(|token| !token.is_special)
+        .map(|token| token.id)
+        .take(max_tokens)
+        .collect();
+    if tail.is_empty() {
+        return None;
+    }
+    tail.reverse();
+    Some(tail)
+}
+
+/// Prompt tokens for a call: the initial prompt's tokens followed by the carried context
+/// 
+/// whisper ignores `prompt_tokens` when an initial prompt is set, so the two
+/// are combined here instead. They are cut to `budget` tokens the way
+/// whisper cuts a prompt, keeping the end: the carried context gets what
+/// room the initial prompt leaves, and a prompt over budget on its own keeps
+/// only its last tokens.
+fn carryover_prompt(initial_prompt: &[WhisperToken], carryover: &[WhisperToken], budget: usize) -> Vec<WhisperToken> {
+    let initial = &initial_prompt[initial_prompt.len().saturating_sub(budget)..];
+    let room = budget - initial.len();
+    let carried = &carryover[carryover.len().saturating_sub(room)..];
+    initial.iter().chain(carried).copied().collect()
+}
+
+/// Segments from one inference run and the language they were decoded as
+/// 
+/// whisper.cpp detects the language once per run, from its first window,
+/// and decodes every segment as that language; it keeps no language per
+/// segment. So each segment's `language` is its run's. Long transcriptions
+/// run once per window, which makes speech switching languages between
+/// windows come out labelled, and decoded, per window.
+#[derive(Debug, Clone)]
+struct Decoded {
+    segments: Vec<DecodedSegment>,
+    language: Option<&'static str>, // None when auto-detection never ran
+    non_finite: usize,              // NaN or infinite input samples decoded as silence
+    text_format: u32,               // WHISPER_TEXT_* flags for `text`
+    trim: TrimPolicy,               // whitespace `text` keeps around the joined segments
+    fallbacks: Fallbacks,
+}
+
+impl Decoded {
+    /// The joined transcription text with the requested formatting applied
+    fn text(&self) -> String {
+        self.pad(format_text(join_segments(&self.segments, self.language), self.text_format))
+    }
+    
+    /// `text`, plus the byte range of each segment in it
+    fn text_with_ranges(&self) -> (String, Vec<Range<usize>>) {
+        let (text, ranges) = self.formatted_with_ranges();
+        let leading = self.trim.padding(&self.raw_text()).0.len();
+        let ranges = ranges.into_iter().map(|range| range.start + leading..range.end + leading).collect();
+        (self.pad(text), ranges)
+    }
+    
+    /// Segment texts as whisper returned them, whitespace and all
+    fn raw_text(&self) -> String {
+        self.segments.iter().map(|segment| segment.text.as_str()).collect()
+    }
+    
+    /// Put back the whitespace the trim policy keeps; an empty text stays empty
+    fn pad(&self, text: String) -> String {
+        if text.is_empty() {
+            return text;
+        }
+        let raw = self.raw_text();
+        let (leading, trailing) = self.trim.padding(&raw);
+        format!("{}{}{}", leading, text, trailing)
+    }
+    
+    /// The formatted text before padding, plus the byte range of each segment in it
+    /// 
+    /// Formatting only changes letter case, which can change how many bytes
+    /// a letter takes, and strips the end. So each boundary moves by the
+    /// change in length of the text before it, capped at the new end.
+    fn formatted_with_ranges(&self) -> (String, Vec<Range<usize>>) {
+        let (joined, ranges) = join_segments_with_ranges(&self.segments, self.language);
+        let flags = self.text_format;
+        let text = format_text(joined.clone(), flags);
+        if text == joined {
+            return (text, ranges);
+        }
+        
+        let recased_len = |slice: &str| match flags & WHISPER_TEXT_LOWERCASE {
+            0 => slice.len(),
+            _ => slice.to_lowercase().len(),
+        };
+        // Change in length from lowering the leading capital, after any full lowercasing
+        let leading = format_text(joined.clone(), flags & !WHISPER_TEXT_STRIP_TRAILING_PUNCT).len() as isize
+            - recased_len(&joined) as isize;
+        let (mut last, mut moved) = (0, 0);
+        let mut map = |at: usize| {
+            moved += recased_len(&joined[last..at]);
+            last = at;
+            let shifted = if at > 0 { moved.saturating_add_signed(leading) } else { 0 };
+            shifted.min(text.len())
+        };
+        let ranges = ranges.into_iter().map(|range| map(range.start)..map(range.end)).collect();
+        (text, ranges)
+    }
+}
+
+/// No-speech probability above which a segment counts as silence, as in whisper.cpp
+const NO_SPEECH_THRESHOLD: f32 = 0.6;
+
+/// Joined transcription text along with how many segments produced it
+#[derive(Debug, PartialEq)]
+struct Transcript {
+    text: String,
+    segment_count: usize,
+    language: Option<&'static str>,
+    non_finite: usize,
+    fallbacks: Fallbacks,
+    no_speech: bool, // nothing was decoded, or every segment is mostly silence
+}
+
+impl Transcript {
+    fn from_decoded(decoded: &Decoded) -> Self {
+        Transcript {
+            text: decoded.text(),
+            segment_count: decoded.segments.len(),
+            language: decoded.language,
+            non_finite: decoded.non_finite,
+            fallbacks: decoded.fallbacks,
+            no_speech: decoded.segments.iter().all(|segment| segment.no_speech_prob > NO_SPEECH_THRESHOLD),
+        }
+    }
+}
+
+/// Most transcriptions kept for `whisper_get_history_json`
+const HISTORY_CAPACITY: usize = 32;
+
+/// A past transcription result, kept so callers can replay it without inference
+#[derive(Debug, Clone, PartialEq)]
+struct HistoryEntry {
+    model_id: String,
+    text: String,
+    timestamp_ms: u64, // wall-clock completion time, milliseconds since the Unix epoch
+}
+
+impl HistoryEntry {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"model_id\":{},\"text\":{},\"timestamp_ms\":{}}}",
+            json_string(&self.model_id),
+            json_string(&self.text),
+            self.timestamp_ms,
+        )
+    }
+}
+
+/// Whether models are loaded on the GPU, per the `metal` and `cpu-only` features
+const GPU_ENABLED: bool = cfg!(all(feature = "metal", not(feature = "cpu-only")));
+
+/// Sample rate whisper expects for input audio
+const WHISPER_SAMPLE_RATE: usize = 16000;
+
+/// Audio whisper encodes per window (30s), used for language detection
+const MEL_WINDOW_SAMPLES: usize = 30 * WHISPER_SAMPLE_RATE;
+
+/// Default idle time before a loaded model becomes eligible for unloading
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 30;
+
+/// Default peak memory budget for loaded models
+const DEFAULT_MEMORY_LIMIT: u64 = 700 * 1024 * 1024;
+
+/// Smallest accepted budget, enough for the tiny model's estimate
+const MIN_MEMORY_LIMIT: u64 = 39 * 1024 * 1024;
+
+/// Average CPU percent above which a smaller model is suggested
+const DEFAULT_DOWNGRADE_THRESHOLD: f32 = 80.0;
+
+/// Upper bound on pooled contexts per model
+const MAX_CONCURRENCY: usize = 8;
+
+/// Map key of a model's `index`th pooled replica (`index` >= 1)
+/// 
+/// Replicas live in the manager's map next to their model, so memory
+/// accounting, eviction and stats cover them without special cases.
+fn replica_id(model_id: &str, index: usize) -> String {
+    format!("{}#{}", model_id, index)
+}
+
+/// The pooled replicas registered for `model_id`, in index order
+fn replicas<'a>(
+    models: &'a HashMap<String, Arc<Mutex<WhisperModel>>>,
+    model_id: &str,
+) -> Vec<&'a Arc<Mutex<WhisperModel>>> {
+    (1..).map_while(|index| models.get(&replica_id(model_id, index))).collect()
+}
+
+/// One batch entry's transcription outcome and how long it took
+type BatchOutcome = (Result<Transcript, ManagerError>, Duration);
+
+/// Run `load` on the GPU when available, retrying once on the CPU if that fails
+/// 
+/// Returns the loaded value and whether it's on the GPU. If the CPU retry
+/// fails too, its error is returned.
+fn load_with_cpu_fallback<T, E: std::fmt::Display>(
+    gpu_available: bool,
+    mut load: impl FnMut(bool) -> Result<T, E>,
+) -> Result<(T, bool), E> {
+    if !gpu_available {
+        return load(false).map(|loaded| (loaded, false));
+    }
+    
+    match load(true) {
+        Ok(loaded) => Ok((loaded, true)),
+        Err(gpu_error) => {
+            let loaded = load(false)?;
+            logging::warn(&format!("GPU model load failed ({}), running on the CPU instead", gpu_error));
+            Ok((loaded, false))
+        }
+    }
+}
+
+/// Conditions under which whisper.cpp is told to stop a running decode
+struct AbortCheck<'a> {
+    flag: &'a AtomicBool,      // set by whisper_abort()
+    deadline: Option<Instant>, // set for calls with a timeout
+}
+
+impl AbortCheck<'_> {
+    fn timed_out(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+    
+    fn should_abort(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) || self.timed_out()
+    }
+}
+
+/// Abort callback for whisper.cpp; `user_data` points at an `AbortCheck`
+unsafe extern "C" fn abort_requested(user_data: *mut c_void) -> bool {
+    (*(user_data as *const AbortCheck)).should_abort()
+}
+
+/// whisper.cpp's default `temperature_inc`, kept unless `whisper_set_temperature_fallback` changes it
+const WHISPER_DEFAULT_TEMPERATURE_INC: f32 = 0.2;
+
+/// Temperature fallbacks one inference run took
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Fallbacks {
+    count: u32,             // decode attempts beyond the first, summed over windows
+    final_temperature: f32, // highest temperature any window was decoded at
+}
+
+/// Per-run decode bookkeeping shared with whisper.cpp's callbacks
+/// 
+/// whisper.cpp keeps its fallback counters private, so they're rebuilt
+/// here: the encoder starts once per 30s window, and every decode attempt
+/// of a window, fallbacks included, begins with a logits call on an empty
+/// token sequence. The encoder callback runs on the thread calling `full`,
+/// but with best-of or beam search whisper.cpp filters logits on worker
+/// threads, so the counts are atomic.
+#[derive(Debug, Default)]
+struct DecodeTracker {
+    suppress_tokens: Vec<WhisperToken>, // in-vocabulary ids to rule out
+    windows: AtomicU32,
+    attempts: AtomicU32,
+    window_attempts: AtomicU32, // attempts so far in the current window
+    most_attempts: AtomicU32,   // highest attempt count of any window
+}
+
+impl DecodeTracker {
+    fn new(suppress_tokens: Vec<WhisperToken>) -> Self {
+        Self { suppress_tokens, ..Self::default() }
+    }
+    
+    /// Forget earlier runs, so the counts describe the next `full` only
+    fn reset(&self) {
+        for count in [&self.windows, &self.attempts, &self.window_attempts, &self.most_attempts] {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+    
+    fn window_started(&self) {
+        self.windows.fetch_add(1, Ordering::Relaxed);
+        self.window_attempts.store(0, Ordering::Relaxed);
+    }
+    
+    fn attempt_started(&self) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        let window_attempts = self.window_attempts.fetch_add(1, Ordering::Relaxed) + 1;
+        self.most_attempts.fetch_max(window_attempts, Ordering::Relaxed);
+    }
+    
+    /// Fallbacks taken, given the run's starting temperature and fallback step
+    fn fallbacks(&self, temperature: f32, temperature_inc: f32) -> Fallbacks {
+        let extra_steps = self.most_attempts.load(Ordering::Relaxed).saturating_sub(1);
+        Fallbacks {
+            count: self.attempts.load(Ordering::Relaxed).saturating_sub(self.windows.load(Ordering::Relaxed)),
+            final_temperature: temperature + extra_steps as f32 * temperature_inc,
+        }
+    }
+}
+
+/// Encoder callback for whisper.cpp; `user_data` points at a `DecodeTracker`
+unsafe extern "C" fn encoder_starting(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    user_data: *mut c_void,
+) -> bool {
+    (*(user_data as *const DecodeTracker)).window_started();
+    true
+}
+
+/// Logits filter for whisper.cpp; `user_data` points at a `DecodeTracker`
+/// 
+/// Runs before every sampling step, so a suppressed token can never win.
+unsafe extern "C" fn filter_logits(
+    _ctx: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    _tokens: *const WhisperTokenData,
+    n_tokens: i32,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    let tracker = &*(user_data as *const DecodeTracker);
+    if n_tokens == 0 {
+        tracker.attempt_started();
+    }
+    for &id in &tracker.suppress_tokens {
+        *logits.add(id as usize) = f32::NEG_INFINITY;
+    }
+}
+
+/// A model's memory figures as of its last load or unload
+/// 
+/// Kept outside the model's `Mutex` so stats can be read during inference.
+#[derive(Debug, Default)]
+struct MemoryFigures {
+    estimated: AtomicU64,
+    actual: AtomicU64,
+}
+
+impl MemoryFigures {
+    fn get(&self) -> (u64, u64) {
+        (self.estimated.load(Ordering::Relaxed), self.actual.load(Ordering::Relaxed))
+    }
+    
+    fn set(&self, estimated: u64, actual: u64) {
+        self.estimated.store(estimated, Ordering::Relaxed);
+        self.actual.store(actual, Ordering::Relaxed);
+    }
+}
+
+/// Whisper model with lazy loading and memory management
+pub struct WhisperModel {
+    ctx: Option<WhisperContext>,
+    model_path: String,
+    model_info: ModelInfo,
+    last_used: Instant,
+    idle_timeout: Duration,
+    actual_memory: Option<u64>, // measured resident growth across the last load
+    settings: TranscribeSettings,
+    abort: Arc<AtomicBool>,     // set by whisper_abort, shared with the manager's abort_flags
+    gpu_enabled: bool,          // loaded context runs on the GPU rather than a CPU fallback
+    loaded_dtw: Option<DtwPreset>, // DTW heads the loaded context was created with
+    carryover: Vec<WhisperToken>, // trailing tokens of the last result, see `context_carryover`
+    memory: Arc<MemoryFigures>, // shared with the manager's memory_figures
+}
+
+impl WhisperModel {
+    fn new(model_path: String, model_info: ModelInfo) -> Self {
+        let tuning = Tuning::for_model(&model_info);
+        let settings = TranscribeSettings {
+            sampling: tuning.sampling,
+            temperature: tuning.temperature,
+            ..TranscribeSettings::default()
+        };
+        Self {
+            ctx: None,
+            model_path,
+            model_info,
+            last_used: Instant::now(),
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS),
+            actual_memory: None,
+            settings,
+            abort: Arc::new(AtomicBool::new(false)),
+            gpu_enabled: false,
+            loaded_dtw: None,
+            carryover: Vec::new(),
+            memory: Arc::default(),
+        }
+    }
+    
+    /// Load the context if it isn't loaded yet
+    /// 
+    /// The model sits behind a `Mutex`, so no separate loading flag is needed:
+    /// a failed load leaves `ctx` empty and the next call simply retries.
+    fn ensure_loaded(&mut self) -> Result<&WhisperContext, ManagerError> {
+        self.last_used = Instant::now();
+        
+        let dtw = self.settings.dtw;
+        if self.ctx.is_none() {
+            let rss_before = process_resident_bytes();
+            let gpu_available = GPU_ENABLED;
+            let (context, on_gpu) = load_with_cpu_fallback(gpu_available, |use_gpu| {
+                WhisperContext::new_with_params(&self.model_path, context_params(use_gpu, dtw))
+            })
+            .map_err(|e| ManagerError::new(WhisperError::LoadFailed, format!("Failed to load model: {}", e)))?;
+            self.gpu_enabled = on_gpu;
+            self.loaded_dtw = dtw;
+            
+            // Best-effort: other allocations during the load skew this figure
+            self.actual_memory = match (rss_before, process_resident_bytes()) {
+                (Some(before), Some(after)) if after > before => Some(after - before),
+                _ => None,
+            };
+            self.ctx = Some(context);
+            self.memory.set(self.memory_usage(), self.actual_memory_usage());
+        }
+        
+        self.ctx.as_ref().ok_or_else(|| ManagerError::new(WhisperError::LoadFailed, "Model not loaded"))
+    }
+    
+    fn should_unload(&self) -> bool {
+        self.ctx.is_some() && self.idle_expired()
+    }
+    
+    /// Whether the model has been idle longer than its timeout.
+    /// A zero timeout means the model is never auto-unloaded.
+    fn idle_expired(&self) -> bool {
+        !self.idle_timeout.is_zero() && self.last_used.elapsed() > self.idle_timeout
+    }
+    
+    fn unload(&mut self) {
+        self.ctx = None;
+        self.actual_memory = None;
+        self.gpu_enabled = false;
+        self.memory.set(0, 0);
+    }
+    
+    fn memory_usage(&self) -> u64 {
+        if self.ctx.is_some() {
+            self.model_info.memory_usage
+        } else {
+            0
+        }
+    }
+    
+    /// Settings inference would run with, as a JSON object
+    fn effective_params_json(&self) -> String {
+        let settings = &self.settings;
+        let n_threads = settings.thread_count(&self.model_info);
+        let (sampling, sampling_param) = match settings.sampling {
+            SamplingMode::Greedy { best_of } => ("greedy", best_of),
+            SamplingMode::BeamSearch { beam_size, .. } => ("beam_search", beam_size),
+        };
+        format!(
+            "{{\"n_threads\":{},\"sampling\":{},\"sampling_param\":{},\"temperature\":{},\"language\":{},\"translate\":{}}}",
+            n_threads,
+            json_string(sampling),
+            sampling_param,
+            settings.temperature,
+            json_string(settings.language()),
+            settings.translate,
+        )
+    }
+    
+    /// Measured footprint of the loaded context, falling back to the estimate
+    /// when no measurement is available
+    fn actual_memory_usage(&self) -> u64 {
+        if self.ctx.is_some() {
+            self.actual_memory.unwrap_or(self.model_info.memory_usage)
+        } else {
+            0
+        }
+    }
+}
+
+/// Thread-safe whisper model manager with automatic memory management
+/// 
+/// Lock ordering: the `models` map lock is always taken before a model's
+/// `Mutex`, and no code takes the map lock while holding a model lock. The
+/// write lock (register/unregister) therefore waits for in-flight
+/// transcriptions to finish instead of deadlocking with them. The
+/// `abort_flags` and `memory_figures` locks are only held briefly and never
+/// while taking another.
+/// At most one model lock is held at a time, except by `evict_idle`, which
+/// only ever uses `try_lock` for the second. Both locks are only taken
+/// through `lock_order`, which checks this order in debug builds.
+pub struct WhisperManager {
+    models: RwLock<HashMap<String, Arc<Mutex<WhisperModel>>>>,
+    abort_flags: Mutex<HashMap<String, Arc<AtomicBool>>>, // reachable while the model is locked mid-transcription
+    memory_figures: Mutex<HashMap<String, Arc<MemoryFigures>>>, // likewise, for stats during inference
+    memory_limit: AtomicU64,
+    cpu_monitor: Arc<Mutex<CpuMonitor>>,
+    default_idle_timeout_secs: AtomicU64, // applied to newly registered models
+    committed_memory: AtomicU64,          // estimated bytes of loaded and in-flight models
+    next_slot: AtomicUsize,               // round-robin cursor over pooled contexts
+    downgrade_targets: RwLock<HashMap<String, (String, String)>>, // model id -> (target id, target name)
+    downgrade_threshold: AtomicU32,       // average CPU percent, stored as f32 bits
+    history: Mutex<VecDeque<HistoryEntry>>, // newest last, at most HISTORY_CAPACITY
+    recording: AtomicBool,                // between whisper_mark_active() and whisper_mark_idle()
+    last_activity: Mutex<Instant>,        // latest transcription or activity mark
+}
+
+/// CPU usage monitoring for automatic model downgrade
+pub struct CpuMonitor {
+    cpu_samples: Vec<f32>,
+    next_index: usize, // slot overwritten next once the window is full
+    max_samples: usize,
+}
+
+impl CpuMonitor {
+    fn new() -> Self {
+        Self {
+            cpu_samples: Vec::new(),
+            next_index: 0,
+            max_samples: 10, // Track last 10 inference operations
+        }
+    }
+    
+    fn record_cpu_usage(&mut self, cpu_percent: f32) {
+        if self.cpu_samples.len() < self.max_samples {
+            self.cpu_samples.push(cpu_percent);
+        } else {
+            self.cpu_samples[self.next_index] = cpu_percent;
+        }
+        self.next_index = (self.next_index + 1) % self.max_samples;
+    }
+    
+    /// Forget every sample, e.g. between recording sessions
+    fn reset(&mut self) {
+        self.cpu_samples.clear();
+        self.next_index = 0;
+    }
+    
+    fn average_cpu_usage(&self) -> f32 {
+        if self.cpu_samples.is_empty() {
+            0.0
+        } else {
+            self.cpu_samples.iter().sum::<f32>() / self.cpu_samples.len() as f32
+        }
+    }
+    
+    fn should_downgrade(&self, threshold: f32) -> bool {
+        self.average_cpu_usage() > threshold
+    }
+}
+
+impl WhisperManager {
+    fn new() -> Self {
+        Self {
+            models: RwLock::new(HashMap::new()),
+            abort_flags: Mutex::new(HashMap::new()),
+            memory_figures: Mutex::new(HashMap::new()),
+            memory_limit: AtomicU64::new(DEFAULT_MEMORY_LIMIT),
+            cpu_monitor: Arc::new(Mutex::new(CpuMonitor::new())),
+            default_idle_timeout_secs: AtomicU64::new(DEFAULT_IDLE_TIMEOUT_SECS),
+            committed_memory: AtomicU64::new(0),
+            next_slot: AtomicUsize::new(0),
+            downgrade_targets: RwLock::new(HashMap::new()),
+            downgrade_threshold: AtomicU32::new(DEFAULT_DOWNGRADE_THRESHOLD.to_bits()),
+            history: Mutex::new(VecDeque::with_capacity(HISTORY_CAPACITY)),
+            recording: AtomicBool::new(false),
+            last_activity: Mutex::new(Instant::now()),
+        }
+    }
+    
+    /// Register a model under `id`, replacing any other model with that id
+    /// 
+    /// Registering the same path under the same id again keeps the existing
+    /// entry, so its settings and loaded context survive.
+    fn register_model(&self, id: String, model_path: String, model_info: ModelInfo) -> Result<(), ManagerError> {
+        let mut models = lock_order::write_map(&self.models).map_err(|_| ManagerError::lock("write"))?;
+        if let Some(existing) = models.get(&id) {
+            let existing = lock_order::lock_model(existing).map_err(|_| ManagerError::lock("model"))?;
+            if existing.model_path == model_path {
+                return Ok(());
+            }
+        }
+        
+        let model = self.new_model(model_path, model_info);
+        
+        // A replaced model's pool would still point at the old file
+        let stale = self.remove_replicas(&mut models, &id, 1);
+        self.insert_model(&mut models, id, model)?;
+        drop(models);
+        self.unload_all(stale);
+        Ok(())
+    }
+    
+    /// Register a model under `id`, failing if that id is already taken
+    fn register_new_model(&self, id: String, model_path: String, model_info: ModelInfo) -> Result<(), ManagerError> {
+        let mut models = lock_order::write_map(&self.models).map_err(|_| ManagerError::lock("write"))?;
+        if models.contains_key(&id) {
+            return Err(ManagerError::new(WhisperError::InvalidParams, format!("Model id '{}' is already registered", id)));
+        }
+        let model = self.new_model(model_path, model_info);
+        self.insert_model(&mut models, id, model)
+    }
+    
+    /// An unloaded model with the manager's default idle timeout
+    fn new_model(&self, model_path: String, model_info: ModelInfo) -> WhisperModel {
+        if model_info.exceeds_size_limit(model_info.memory_usage) {
+            logging::warn(&format!(
+                "Model '{}' is estimated at {}MB, beyond the {}MB limit for its size; it may be mislabeled and will be refused at load",
+                model_info.name,
+                model_info.memory_usage / (1024 * 1024),
+                model_info.size.memory_limit() / (1024 * 1024)
+            ));
+        }
+        let mut model = WhisperModel::new(model_path, model_info);
+        model.idle_timeout = Duration::from_secs(self.default_idle_timeout_secs.load(Ordering::Relaxed));
+        model
+    }
+    
+    fn insert_model(
+        &self,
+        models: &mut HashMap<String, Arc<Mutex<WhisperModel>>>,
+        id: String,
+        model: WhisperModel,
+    ) -> Result<(), ManagerError> {
+        self.abort_flags.lock().map_err(|_| ManagerError::lock("abort"))?
+            .insert(id.clone(), Arc::clone(&model.abort));
+        self.memory_figures.lock().map_err(|_| ManagerError::lock("memory"))?
+            .insert(id.clone(), Arc::clone(&model.memory));
+        models.insert(id, Arc::new(Mutex::new(model)));
+        Ok(())
+    }
+    
+    /// Take replicas from index `from` upwards out of the map
+    /// 
+    /// They're returned rather than unloaded so the caller can drop them
+    /// after releasing the map lock.
+    fn remove_replicas(
+        &self,
+        models: &mut HashMap<String, Arc<Mutex<WhisperModel>>>,
+        model_id: &str,
+        from: usize,
+    ) -> Vec<Arc<Mutex<WhisperModel>>> {
+        let ids: Vec<String> = (from..)
+            .map(|index| replica_id(model_id, index))
+            .take_while(|id| models.contains_key(id))
+            .collect();
+        if let Ok(mut flags) = self.abort_flags.lock() {
+            for id in &ids {
+                flags.remove(id);
+            }
+        }
+        if let Ok(mut figures) = self.memory_figures.lock() {
+            for id in &ids {
+                figures.remove(id);
+            }
+        }
+        ids.iter().filter_map(|id| models.remove(id)).collect()
+    }
+    
+    fn unload_all(&self, removed: Vec<Arc<Mutex<WhisperModel>>>) {
+        for model_arc in removed {
+            if let Ok(mut model) = lock_order::lock_model(&model_arc) {
+                self.unload_model(&mut model);
+            }
+        }
+    }
+    
+    /// Resize the pool of contexts serving `model_id` to `size`, counting the model itself
+    /// 
+    /// New replicas copy the model's path, settings and idle timeout and load
+    /// lazily. Shrinking unloads the removed replicas.
+    fn resize_pool(&self, model_id: &str, size: usize) -> Result<(), ManagerError> {
+        if size == 0 || size > MAX_CONCURRENCY {
+            return Err(ManagerError::new(
+                WhisperError::InvalidParams,
+                format!("Concurrency must be between 1 and {}", MAX_CONCURRENCY),
+            ));
+        }
+        
+        let mut models = lock_order::write_map(&self.models).map_err(|_| ManagerError::lock("write"))?;
+        let template = {
+            let model_arc = models.get(model_id)
+                .ok_or_else(|| ManagerError::not_found(model_id))?;
+            let model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+            (model.model_path.clone(), model.model_info.clone(), model.settings.clone(), model.idle_timeout)
+        };
+        
+        let removed = self.remove_replicas(&mut models, model_id, size);
+        for index in 1..size {
+            let id = replica_id(model_id, index);
+            if models.contains_key(&id) {
+                continue;
+            }
+            let (model_path, model_info, settings, idle_timeout) = template.clone();
+            let mut replica = WhisperModel::new(model_path, model_info);
+            replica.settings = settings;
+            replica.idle_timeout = idle_timeout;
+            self.insert_model(&mut models, id, replica)?;
+        }
+        drop(models);
+        self.unload_all(removed);
+        Ok(())
+    }
+    
+    /// Allocate up to `size` contexts for `model_id` so requests can run in parallel
+    /// 
+    /// Contexts are loaded one at a time and the pool stops growing at the
+    /// first one the memory limit can't fit. Returns the resulting pool size.
+    fn set_concurrency(&self, model_id: &str, size: usize) -> Result<usize, ManagerError> {
+        self.resize_pool(model_id, size)?;
+        if let Err(e) = self.preload(model_id) {
+            self.resize_pool(model_id, 1)?;
+            return Err(e);
+        }
+        
+        for index in 1..size {
+            if let Err(e) = self.preload(&replica_id(model_id, index)) {
+                logging::warn(&format!("Limiting '{}' to {} contexts: {}", model_id, index, e));
+                self.resize_pool(model_id, index)?;
+                return Ok(index);
+            }
+        }
+        Ok(size)
+    }
+    
+    /// Lock a free context for `model_id`, preferring pooled replicas round-robin
+    /// 
+    /// Only loaded replicas are considered so a lazy reload can't fail a call
+    /// the model itself could serve. When every context is busy this waits
+    /// for the model's own lock, so the default pool of one behaves exactly
+    /// like locking the model directly.
+    fn select_slot<'a>(
+        &self,
+        models: &'a HashMap<String, Arc<Mutex<WhisperModel>>>,
+        model_id: &str,
+    ) -> Result<ModelGuard<'a, WhisperModel>, ManagerError> {
+        let primary = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        let replicas = replicas(models, model_id);
+        if replicas.is_empty() {
+            return lock_order::lock_model(primary).map_err(|_| ManagerError::lock("model"));
+        }
+        
+        let slots: Vec<&Arc<Mutex<WhisperModel>>> = std::iter::once(primary).chain(replicas).collect();
+        let start = self.next_slot.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..slots.len() {
+            let index = (start + offset) % slots.len();
+            if let Ok(model) = lock_order::try_lock_model(slots[index]) {
+                if index == 0 || model.ctx.is_some() {
+                    return Ok(model);
+                }
+            }
+        }
+        lock_order::lock_model(primary).map_err(|_| ManagerError::lock("model"))
+    }
+    
+    /// Remove a model registration, dropping its context if it was loaded
+    fn unregister(&self, model_id: &str) -> Result<(), ManagerError> {
+        let removed = {
+            let mut models = lock_order::write_map(&self.models).map_err(|_| ManagerError::lock("write"))?;
+            if let Ok(mut flags) = self.abort_flags.lock() {
+                flags.remove(model_id);
+            }
+            if let Ok(mut figures) = self.memory_figures.lock() {
+                figures.remove(model_id);
+            }
+            let replicas = self.remove_replicas(&mut models, model_id, 1);
+            models.remove(model_id).map(|model_arc| (model_arc, replicas))
+        };
+        
+        // Dropped outside the map lock so freeing the context doesn't block other models
+        let (model_arc, replicas) = removed.ok_or_else(|| ManagerError::not_found(model_id))?;
+        if let Ok(mut targets) = self.downgrade_targets.write() {
+            targets.remove(model_id);
+        }
+        self.unload_all(replicas);
+        if let Ok(mut model) = lock_order::lock_model(&model_arc) {
+            self.unload_model(&mut model);
+        }
+        Ok(())
+    }
+    
+    /// Remove every registered model
+    #[cfg(test)]
+    fn clear(&self) {
+        let removed = match lock_order::write_map(&self.models) {
+            Ok(mut models) => std::mem::take(&mut *models),
+            Err(_) => return,
+        };
+        if let Ok(mut flags) = self.abort_flags.lock() {
+            flags.clear();
+        }
+        if let Ok(mut figures) = self.memory_figures.lock() {
+            figures.clear();
+        }
+        for model_arc in removed.values() {
+            if let Ok(mut model) = lock_order::lock_model(model_arc) {
+                self.unload_model(&mut model);
+            }
+        }
+    }
+    
+    /// Restore a freshly constructed state for test isolation
+    #[cfg(test)]
+    fn reset(&self) {
+        self.clear();
+        self.set_default_idle_timeout(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS));
+        self.memory_limit.store(DEFAULT_MEMORY_LIMIT, Ordering::SeqCst);
+        self.downgrade_threshold.store(DEFAULT_DOWNGRADE_THRESHOLD.to_bits(), Ordering::SeqCst);
+        if let Ok(mut targets) = self.downgrade_targets.write() {
+            targets.clear();
+        }
+        self.clear_history();
+        self.recording.store(false, Ordering::SeqCst);
+        if let Ok(mut monitor) = self.cpu_monitor.lock() {
+            *monitor = CpuMonitor::new();
+        }
+    }
+    
+    /// Run a closure against a registered model while holding its lock
+    /// 
+    /// Settings or idle timeout changes are copied to the model's pooled
+    /// replicas afterwards.
+    fn with_model<T>(&self, model_id: &str, f: impl FnOnce(&mut WhisperModel) -> T) -> Result<T, ManagerError> {
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let model_arc = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        
+        let (result, changed) = {
+            let mut model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+            let before = (model.settings.clone(), model.idle_timeout);
+            let result = f(&mut model);
+            let after = (model.settings.clone(), model.idle_timeout);
+            (result, (after != before).then_some(after))
+        };
+        
+        if let Some((settings, idle_timeout)) = changed {
+            for replica_arc in replicas(&models, model_id) {
+                let mut replica = lock_order::lock_model(replica_arc).map_err(|_| ManagerError::lock("model"))?;
+                replica.settings = settings.clone();
+                replica.idle_timeout = idle_timeout;
+            }
+        }
+        Ok(result)
+    }
+    
+    /// Ask the model's in-flight transcription, if any, to stop
+    /// 
+    /// Doesn't wait for the model lock, so it works while a transcription is
+    /// running. The flag is cleared when the next transcription starts, so
+    /// aborting an idle model has no effect.
+    fn abort(&self, model_id: &str) -> Result<(), ManagerError> {
+        let flags = self.abort_flags.lock().map_err(|_| ManagerError::lock("abort"))?;
+        flags.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?
+            .store(true, Ordering::Relaxed);
+        
+        // A pooled model may be busy on any of its contexts
+        for flag in (1..).map_while(|index| flags.get(&replica_id(model_id, index))) {
+            flag.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+    
+    /// Load a model eagerly instead of on its first transcription
+    fn preload(&self, model_id: &str) -> Result<(), ManagerError> {
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let model_arc = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        
+        let mut model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+        self.load_model(&models, &mut model)
+    }
+    
+    /// Load a model only if it fits beside the models already loaded
+    /// 
+    /// Unlike `preload`, nothing is evicted to make room, so warming a model
+    /// ahead of time never unloads the one in use.
+    fn warm(&self, model_id: &str) -> Result<(), ManagerError> {
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let model_arc = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        
+        let mut model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+        self.load_model_evicting(None, &mut model)
+    }
+    
+    /// Drop a model's context and load a fresh one from its file
+    /// 
+    /// Each context is reloaded under its lock, so transcriptions wait for
+    /// the reload instead of running on a half-replaced context. Pooled
+    /// replicas that are loaded get fresh contexts too. A failed load leaves
+    /// the model unloaded, and the next call retries as usual.
+    fn reload(&self, model_id: &str) -> Result<(), ManagerError> {
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let model_arc = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        
+        {
+            let mut model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+            self.unload_model(&mut model);
+            self.load_model(&models, &mut model)?;
+        }
+        for replica_arc in replicas(&models, model_id) {
+            let mut replica = lock_order::lock_model(replica_arc).map_err(|_| ManagerError::lock("model"))?;
+            if replica.ctx.is_some() {
+                self.unload_model(&mut replica);
+                self.load_model(&models, &mut replica)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Load `model` if needed, reserving its memory against the limit first
+    /// 
+    /// The reservation is taken before the context is allocated, so
+    /// concurrent loads see each other's memory. When the load would exceed
+    /// the limit, idle models are evicted; if that isn't enough the load is
+    /// refused. A model is always allowed to load when nothing else is, even
+    /// if its estimate alone exceeds the limit. `models` is the already held
+    /// map, and `model` must be locked by the caller.
+    fn load_model(
+        &self,
+        models: &HashMap<String, Arc<Mutex<WhisperModel>>>,
+        model: &mut WhisperModel,
+    ) -> Result<(), ManagerError> {
+        self.load_model_evicting(Some(models), model)
+    }
+    
+    /// Load `model` as `load_model` does, evicting idle models from
+    /// `evict_from` only if it's given
+    fn load_model_evicting(
+        &self,
+        evict_from: Option<&HashMap<String, Arc<Mutex<WhisperModel>>>>,
+        model: &mut WhisperModel,
+    ) -> Result<(), ManagerError> {
+        if model.ctx.is_some() {
+            if model.loaded_dtw == model.settings.dtw {
+                return Ok(());
+            }
+            // DTW heads are fixed when the context is created; unloading
+            // releases its reservation so the reload reserves afresh
+            logging::info(&format!("Reloading {} for DTW heads {:?}", model.model_path, model.settings.dtw));
+            self.unload_model(model);
+        }
+        
+        let bytes = model.model_info.memory_usage;
+        if model.model_info.exceeds_size_limit(bytes) {
+            return Err(ManagerError::new(WhisperError::LoadFailed, format!(
+                "Model '{}' ({}MB) exceeds the {}MB limit for its size class",
+                model.model_info.name,
+                bytes / (1024 * 1024),
+                model.model_info.size.memory_limit() / (1024 * 1024)
+            )));
+        }
+        
+        if !self.try_reserve_memory(bytes) {
+            if let Some(models) = evict_from {
+                self.evict_idle(models);
+            }
+            if !self.try_reserve_memory(bytes) {
+                return Err(ManagerError::new(WhisperError::LoadFailed, format!(
+                    "Loading model '{}' ({}MB) would exceed the {}MB memory limit",
+                    model.model_info.name,
+                    bytes / (1024 * 1024),
+                    self.memory_limit() / (1024 * 1024)
+                )));
+            }
+        }
+        
+        if let Err(e) = model.ensure_loaded() {
+            self.release_memory(bytes);
+            return Err(e);
+        }
+        
+        // The measurement is noisy, so a large one only warrants a warning
+        if let Some(measured) = model.actual_memory.filter(|&measured| model.model_info.exceeds_size_limit(measured)) {
+            logging::warn(&format!(
+                "Model '{}' grew resident memory by {}MB, beyond the {}MB limit for its size",
+                model.model_info.name,
+                measured / (1024 * 1024),
+                model.model_info.size.memory_limit() / (1024 * 1024)
+            ));
+        }
+        Ok(())
+    }
+    
+    fn memory_limit(&self) -> u64 {
+        self.memory_limit.load(Ordering::SeqCst)
+    }
+    
+    /// Change the memory budget for loaded models
+    /// 
+    /// Lowering it doesn't unload anything by itself; idle models are evicted
+    /// on the next load or transcription that finds the budget exceeded.
+    fn set_memory_limit(&self, bytes: u64) -> Result<(), ManagerError> {
+        if bytes < MIN_MEMORY_LIMIT {
+            return Err(ManagerError::new(WhisperError::InvalidParams, format!(
+                "Memory limit of {}MB is below the smallest model ({}MB)",
+                bytes / (1024 * 1024),
+                MIN_MEMORY_LIMIT / (1024 * 1024)
+            )));
+        }
+        self.memory_limit.store(bytes, Ordering::SeqCst);
+        Ok(())
+    }
+    
+    fn try_reserve_memory(&self, bytes: u64) -> bool {
+        let limit = self.memory_limit();
+        self.committed_memory
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used == 0 || used + bytes <= limit).then_some(used + bytes)
+            })
+            .is_ok()
+    }
+    
+    fn release_memory(&self, bytes: u64) {
+        let _ = self.committed_memory
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| Some(used.saturating_sub(bytes)));
+    }
+    
+    /// Unload a model's context and release its reservation
+    fn unload_model(&self, model: &mut WhisperModel) {
+        if model.ctx.is_some() {
+            model.unload();
+            self.release_memory(model.model_info.memory_usage);
+        }
+    }
+    
+    /// Unload idle models, skipping any that are busy
+    /// 
+    /// Uses `try_lock` because the caller may already hold a model lock;
+    /// blocking on a second model here could deadlock against another loader.
+    fn evict_idle(&self, models: &HashMap<String, Arc<Mutex<WhisperModel>>>) {
+        for model_arc in models.values() {
+            if let Ok(mut model) = lock_order::try_lock_model(model_arc) {
+                if model.should_unload() {
+                    self.unload_model(&mut model);
+                }
+            }
+        }
+    }
+    
+    /// Unload every idle model regardless of memory pressure
+    /// 
+    /// Nothing is unloaded while a recording is marked active, and a model's
+    /// idle time also counts from the last activity, so a model isn't dropped
+    /// between two push-to-talk presses.
+    fn sweep_idle(&self) {
+        if let Ok(models) = lock_order::read_map(&self.models) {
+            for model_arc in models.values() {
+                if let Ok(mut model) = lock_order::try_lock_model(model_arc) {
+                    if model.ctx.is_some() && self.sweep_due(&model) {
+                        self.unload_model(&mut model);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Whether the sweeper may unload `model` now, ignoring whether it's loaded
+    fn sweep_due(&self, model: &WhisperModel) -> bool {
+        !self.recording.load(Ordering::SeqCst)
+            && model.idle_expired()
+            && self.last_activity.lock().is_ok_and(|last| last.elapsed() > model.idle_timeout)
+    }
+    
+    /// Note activity, restarting the sweeper's idle countdown
+    fn touch_activity(&self) {
+        if let Ok(mut last) = self.last_activity.lock() {
+            *last = Instant::now();
+        }
+    }
+    
+    fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::SeqCst);
+        self.touch_activity();
+    }
+    
+    fn set_default_idle_timeout(&self, timeout: Duration) {
+        self.default_idle_timeout_secs.store(timeout.as_secs(), Ordering::Relaxed);
+    }
+    
+    fn transcribe(&self, model_id: &str, audio_data: &[f32]) -> Result<Transcript, ManagerError> {
+        self.transcribe_with(model_id, audio_data, |_| {})
+    }
+    
+    /// Transcribe with a hook to adjust the inference parameters for this call only
+    fn transcribe_with(
+        &self,
+        model_id: &str,
+        audio_data: &[f32],
+        configure: impl Fn(&mut FullParams),
+    ) -> Result<Transcript, ManagerError> {
+        self.transcribe_segments_with(model_id, audio_data, configure)
+            .map(|decoded| Transcript::from_decoded(&decoded))
+    }
+    
+    /// Transcribe into individual segments, dropping any below the model's confidence floor
+    fn transcribe_segments_with(
+        &self,
+        model_id: &str,
+        audio_data: &[f32],
+        configure: impl Fn(&mut FullParams),
+    ) -> Result<Decoded, ManagerError> {
+        self.transcribe_overriding(model_id, audio_data, |_| {}, configure)
+    }
+    
+    /// Transcribe with `adjust` applied to a copy of the model's settings for this call only
+    fn transcribe_overriding(
+        &self,
+        model_id: &str,
+        audio_data: &[f32],
+        adjust: impl Fn(&mut TranscribeSettings),
+        configure: impl Fn(&mut FullParams),
+    ) -> Result<Decoded, ManagerError> {
+        // Check memory usage before inference
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let mut model = self.select_slot(&models, model_id)?;
+        let decoded = self.decode_locked(&models, &mut model, audio_data, &adjust, &configure)?;
+        self.record_history(model_id, decoded.text());
+        Ok(decoded)
+    }
+    
+    /// Remember a transcription's text, evicting the oldest entry at capacity
+    /// 
+    /// Empty results are skipped so a silent clip doesn't hide the last text.
+    fn record_history(&self, model_id: &str, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        if let Ok(mut history) = self.history.lock() {
+            if history.len() == HISTORY_CAPACITY {
+                history.pop_front();
+            }
+            history.push_back(HistoryEntry { model_id: model_id.to_string(), text, timestamp_ms });
+        }
+    }
+    
+    /// Up to `n` of the most recent transcriptions, newest first
+    fn recent_history(&self, n: usize) -> Vec<HistoryEntry> {
+        self.history.lock()
+            .map(|history| history.iter().rev().take(n).cloned().collect())
+            .unwrap_or_default()
+    }
+    
+    fn clear_history(&self) {
+        if let Ok(mut history) = self.history.lock() {
+            history.clear();
+        }
+    }
+    
+    /// Transcribe a long buffer as overlapping windows under a single model lock
+    /// 
+    /// Each window's timestamps are shifted to its position in the buffer and
+    /// the text repeated across each overlap is dropped, see `stitch_chunk`.
+    /// The language is the first one any window reported; each segment
+    /// keeps the language of its own window.
+    fn transcribe_long(
+        &self,
+        model_id: &str,
+        audio_data: &[f32],
+        chunk_samples: usize,
+        overlap_samples: usize,
+    ) -> Result<Decoded, ManagerError> {
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let mut model = self.select_slot(&models, model_id)?;
+        let mut stitched = Decoded {
+            segments: Vec::new(),
+            language: None,
+            non_finite: 0,
+            text_format: model.settings.text_format,
+            trim: model.settings.trim,
+            fallbacks: Fallbacks::default(),
+        };
+        let batch = |settings: &mut TranscribeSettings| settings.core_preference = settings.core_preference.for_batch();
+        for (start, end) in chunk_windows(audio_data.len(), chunk_samples, overlap_samples) {
+            let mut chunk = self.decode_locked(&models, &mut model, &audio_data[start..end], &batch, &|_| {})?;
+            offset_segments(&mut chunk.segments, (start * 1000 / WHISPER_SAMPLE_RATE) as i64);
+            stitch_chunk(&mut stitched.segments, chunk.segments);
+            stitched.language = stitched.language.or(chunk.language);
+            stitched.non_finite += chunk.non_finite;
+            stitched.fallbacks.count += chunk.fallbacks.count;
+            stitched.fallbacks.final_temperature = stitched.fallbacks.final_temperature.max(chunk.fallbacks.final_temperature);
+        }
+        // Turns are marked again so gaps between windows count too
+        if let Some(gap_ms) = model.settings.speaker_gap_ms {
+            mark_speaker_turns(&mut stitched.segments, gap_ms);
+        }
+        self.record_history(model_id, stitched.text());
+        Ok(stitched)
+    }
+    
+    /// Transcribe several buffers in order under a single model lock
+    /// 
+    /// Each buffer gets its own outcome and inference time, so one failing
+    /// buffer doesn't affect the others. The outer error is for failures that
+    /// apply to the whole batch, such as an unknown model.
+    fn transcribe_batch(
+        &self,
+        model_id: &str,
+        buffers: &[&[f32]],
+    ) -> Result<Vec<BatchOutcome>, ManagerError> {
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let mut model = self.select_slot(&models, model_id)?;
+        let batch = |settings: &mut TranscribeSettings| settings.core_preference = settings.core_preference.for_batch();
+        Ok(buffers
+            .iter()
+            .map(|audio_data| {
+                let start_time = Instant::now();
+                let result = self.decode_locked(&models, &mut model, audio_data, &batch, &|_| {})
+                    .map(|decoded| {
+                        self.record_history(model_id, decoded.text());
+                        Transcript::from_decoded(&decoded)
+                    });
+                (result, start_time.elapsed())
+            })
+            .collect())
+    }
+    
+    /// Run inference on an already locked model and record its CPU cost
+    /// 
+    /// `models` is the map guard the caller locked `model` through. `adjust`
+    /// can change a copy of the model's settings for this run.
+    fn decode_locked(
+        &self,
+        models: &HashMap<String, Arc<Mutex<WhisperModel>>>,
+        model: &mut WhisperModel,
+        audio_data: &[f32],
+        adjust: &impl Fn(&mut TranscribeSettings),
+        configure: &impl Fn(&mut FullParams),
+    ) -> Result<Decoded, ManagerError> {
+        // An abort only applies to the transcription that was running when it was requested
+        model.abort.store(false, Ordering::Relaxed);
+        
+        let start_time = Instant::now();
+        let result = self.run_inference(models, model, audio_data, adjust, configure);
+        
+        // Record CPU usage (simplified - in real implementation would measure actual CPU)
+        let inference_duration = start_time.elapsed();
+        let estimated_cpu = (inference_duration.as_secs_f32() * 100.0).min(100.0);
+        
+        if let Ok(mut monitor) = self.cpu_monitor.lock() {
+            monitor.record_cpu_usage(estimated_cpu);
+        }
+        self.touch_activity();
+        
+        result
+    }
+    
+    fn run_inference(
+        &self,
+        models: &HashMap<String, Arc<Mutex<WhisperModel>>>,
+        model: &mut WhisperModel,
+        audio_data: &[f32],
+        adjust: &impl Fn(&mut TranscribeSettings),
+        configure: &impl Fn(&mut FullParams),
+    ) -> Result<Decoded, ManagerError> {
+        let mut settings = model.settings.clone();
+        adjust(&mut settings);
+        let n_threads = settings.thread_count(&model.model_info);
+        
+        // Refused before anything is loaded or allocated; chunked and range calls check their window
+        let max_secs = settings.max_audio_secs.unwrap_or(DEFAULT_MAX_AUDIO_SECS);
+        let decoded_len = settings.range
+            .and_then(|(offset, duration)| range_window(audio_data.len(), offset as u32, duration as u32))
+            .map_or(audio_data.len(), |(_, _, window_len)| window_len);
+        if decoded_len > max_secs as usize * WHISPER_SAMPLE_RATE {
+            return Err(ManagerError::new(
+                WhisperError::AudioTooLong,
+                format!(
+                    "{}s of audio is over the {}s limit for one call; use whisper_transcribe_long",
+                    decoded_len / WHISPER_SAMPLE_RATE,
+                    max_secs
+                ),
+            ));
+        }
+        
+        if settings.audio_check != AudioCheck::Off {
+            let fraction = audio::out_of_range_fraction(audio_data);
+            if fraction > MAX_OUT_OF_RANGE_FRACTION {
+                let message = format!(
+                    "{:.0}% of samples are outside [-1, 1]; expected 16kHz mono f32, not integer samples",
+                    fraction * 100.0
+                );
+                if settings.audio_check == AudioCheck::Reject {
+                    return Err(ManagerError::new(WhisperError::InvalidAudio, message));
+                }
+                logging::warn(&message);
+            }
+        }
+        
+        // NaN or infinite samples poison the whole mel spectrogram, so they're silenced
+        let (sanitized, non_finite) = audio::replace_non_finite(audio_data);
+        let audio_data = &*sanitized;
+        
+        if settings.check_sample_rate {
+            if let Some(rate) = spectrum::detect_band_limited_rate(audio_data, WHISPER_SAMPLE_RATE as u32) {
+                logging::warn(&format!(
+                    "Audio declared as {}Hz looks band-limited to {}Hz content; check the capture sample rate",
+                    WHISPER_SAMPLE_RATE, rate
+                ));
+            }
+        }
+        
+        // Only decode the speech region; timestamps are shifted back afterwards
+        let (audio_data, offset_ms) = match settings.vad {
+            Some(vad) => {
+                // A range is cut from the caller's audio before trimming moves it
+                let (window_start, audio_data) = match settings.range.take() {
+                    Some((offset, duration)) => {
+                        let window_len = range_window(audio_data.len(), offset as u32, duration as u32)
+                            .map_or(0, |(_, _, window_len)| window_len);
+                        let start = (offset as usize * WHISPER_SAMPLE_RATE / 1000).min(audio_data.len());
+                        (start, &audio_data[start..(start + window_len).min(audio_data.len())])
+                    }
+                    None => (0, audio_data),
+                };
+                let (start, end) = vad::trim_silence(audio_data, vad.threshold);
+                if start == end {
+                    return Ok(Decoded {
+                        segments: Vec::new(),
+                        language: settings.fixed_language(),
+                        non_finite,
+                        text_format: settings.text_format,
+                        trim: settings.trim,
+                        fallbacks: Fallbacks::default(),
+                    });
+                }
+                let start = vad::with_pre_roll(start, vad.pre_roll_ms);
+                (&audio_data[start..end], ((window_start + start) * 1000 / WHISPER_SAMPLE_RATE) as i64)
+            }
+            None => (audio_data, 0),
+        };
+        
+        // A lead-in gives the encoder context before the first word
+        let speech_ms = (audio_data.len() * 1000 / WHISPER_SAMPLE_RATE) as i64;
+        let lead_padded = prepend_silence(audio_data, settings.lead_silence_ms);
+        let lead_ms = ((lead_padded.len() - audio_data.len()) * 1000 / WHISPER_SAMPLE_RATE) as i64;
+        let audio_data = &*lead_padded;
+        
+        // Silence estimates in collect_segments keep using the original levels
+        let input = if settings.auto_gain {
+            Cow::Owned(audio::auto_gain(audio_data))
+        } else {
+            Cow::Borrowed(audio_data)
+        };
+        let input = pad_short_input(input);
+        
+        let abort = Arc::clone(&model.abort);
+        
+        if !settings.context_carryover {
+            model.carryover.clear();
+        }
+        let carryover = model.carryover.clone();
+        
+        self.load_model(models, model)?;
+        let context = model.ensure_loaded()?;
+        
+        let prompt_tokens = match (&settings.initial_prompt, carryover.is_empty()) {
+            (_, true) => Vec::new(),
+            (None, false) => carryover,
+            (Some(prompt), false) => {
+                // whisper keeps at most half its text context as prompt, and
+                // text never tokenizes to more tokens than it has bytes
+                let budget = (context.model_n_text_ctx() / 2).max(0) as usize;
+                let initial = context.tokenize(prompt, prompt.len().max(budget))
+                    .map_err(|e| ManagerError::new(WhisperError::InferenceFailed, format!("Failed to tokenize prompt: {}", e)))?;
+                carryover_prompt(&initial, &carryover, budget)
+            }
+        };
+        
+        // The budget covers inference, including a confidence retry, but not loading
+        let abort_check = AbortCheck {
+            flag: &abort,
+            deadline: settings.timeout.map(|timeout| Instant::now() + timeout),
+        };
+        let full_error = |e: whisper_rs::WhisperError| {
+            if abort.load(Ordering::Relaxed) {
+                ManagerError::new(WhisperError::Cancelled, "Transcription cancelled")
+            } else if abort_check.timed_out() {
+                ManagerError::new(WhisperError::TimedOut, "Transcription exceeded its time budget")
+            } else {
+                ManagerError::new(WhisperError::InferenceFailed, format!("Transcription failed: {}", e))
+            }
+        };
+        
+        // Ids past this model's vocabulary would index outside the logits
+        let tracker = DecodeTracker::new(
+            settings.suppress_tokens.iter().copied().filter(|&id| id < context.n_vocab()).collect(),
+        );
+        
+        // Prepare inference parameters
+        let build_params = |strategy: SamplingStrategy| {
+            let mut params = FullParams::new(strategy);
+            params.set_n_threads(n_threads);
+            params.set_language(Some(settings.language()));
+            params.set_translate(settings.translate);
+            params.set_temperature(settings.temperature);
+            if let Some(increment) = settings.temperature_inc {
+                params.set_temperature_inc(increment);
+            }
+            // whisper only splits segments by length when token timestamps are on
+            if let Some(max_len) = settings.max_segment_len {
+                params.set_token_timestamps(true);
+                params.set_max_len(max_len);
+                params.set_split_on_word(true);
+            }
+            params.set_suppress_blank(settings.suppress_blank.unwrap_or(true));
+            params.set_suppress_nst(settings.suppress_nst);
+            params.set_print_special(false);
+            params.set_print_progress(false);
+            params.set_print_realtime(false);
+            params.set_print_timestamps(false);
+            if !prompt_tokens.is_empty() {
+                params.set_tokens(&prompt_tokens);
+            } else if let Some(prompt) = &settings.initial_prompt {
+                params.set_initial_prompt(prompt);
+            }
+            if let Some(range) = settings.range {
+                let (offset, duration) = padded_window(range, lead_ms);
+                params.set_offset_ms(offset);
+                params.set_duration_ms(duration);
+            }
+            // `abort_check` outlives `full` since it lives until this function returns
+            unsafe {
+                params.set_abort_callback(Some(abort_requested));
+                params.set_abort_callback_user_data(&abort_check as *const AbortCheck as *mut c_void);
+            }
+            // Likewise `tracker`
+            unsafe {
+                params.set_start_encoder_callback(Some(encoder_starting));
+                params.set_start_encoder_callback_user_data(&tracker as *const DecodeTracker as *mut c_void);
+                params.set_filter_logits_callback(Some(filter_logits));
+                params.set_filter_logits_callback_user_data(&tracker as *const DecodeTracker as *mut c_void);
+            }
+            configure(&mut params);
+            params
+        };
+        
+        // whisper.cpp's worker threads inherit the calling thread's QoS class
+        let _qos = settings.core_preference.apply_to_current_thread();
+        
+        // Create state for inference
+        let mut state = context.create_state()
+            .map_err(|e| ManagerError::new(WhisperError::InferenceFailed, format!("Failed to create state: {}", e)))?;
+
+        // Run inference
+        state.full(build_params(settings.sampling.strategy()), &input)
+            .map_err(&full_error)?;
+
+        // Extract text results
+        let mut segments = collect_segments(&state, context.token_eot(), audio_data, settings.strict_segments)?;
+        
+        // Escalate to beam search once if the greedy result looks unreliable
+        let greedy = matches!(settings.sampling, SamplingMode::Greedy { .. });
+        if let Some(retry) = settings.confidence_retry.filter(|_| greedy) {
+            if mean_confidence(&segments).is_some_and(|confidence| retry.should_retry(confidence)) {
+                let strategy = SamplingStrategy::BeamSearch { beam_size: retry.beam_size, patience: -1.0 };
+                tracker.reset();
+                state.full(build_params(strategy), &input)
+                    .map_err(&full_error)?;
+                segments = collect_segments(&state, context.token_eot(), audio_data, settings.strict_segments)?;
+            }
+        }
+        
+        if let Some(min_logprob) = settings.min_avg_logprob {
+            segments = filter_low_confidence(segments, min_logprob);
+        }
+        if settings.suppress_non_speech {
+            segments = suppress_non_speech(segments);
+        }
+        if settings.collapse_repeats {
+            for segment in &mut segments {
+                segment.text = collapse_repetitions(&segment.text, MAX_TRAILING_REPEATS);
+            }
+        }
+        restore_timeline(&mut segments, lead_ms, speech_ms, offset_ms);
+        if let Some(gap_ms) = settings.speaker_gap_ms {
+            mark_speaker_turns(&mut segments, gap_ms);
+        }
+        
+        let language = settings.fixed_language().or_else(|| {
+            state.full_lang_id_from_state().ok().and_then(whisper_rs::get_lang_str)
+        });
+        for segment in &mut segments {
+            segment.language = language;
+        }
+        if settings.context_carryover {
+            if let Some(tail) = carryover_tokens(&segments, CARRYOVER_MAX_TOKENS) {
+                model.carryover = tail;
+            }
+        }
+        let fallbacks = tracker.fallbacks(
+            settings.temperature,
+            settings.temperature_inc.unwrap_or(WHISPER_DEFAULT_TEMPERATURE_INC),
+        );
+        Ok(Decoded { segments, language, non_finite, text_format: settings.text_format, trim: settings.trim, fallbacks })
+    }
+    
+    /// Identify the spoken language from the first 30s window without decoding text
+    /// 
+    /// Returns the ISO 639-1 code and its probability. English-only models
+    /// always report English with full confidence.
+    fn detect_language(&self, model_id: &str, audio_data: &[f32]) -> Result<(&'static str, f32), ManagerError> {
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let model_arc = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        
+        let mut model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+        self.load_model(&models, &mut model)?;
+        let context = model.ensure_loaded()?;
+        if !context.is_multilingual() {
+            return Ok(("en", 1.0));
+        }
+        
+        let inference_error = |e: whisper_rs::WhisperError| {
+            ManagerError::new(WhisperError::InferenceFailed, format!("Language detection failed: {}", e))
+        };
+        
+        // Short clips are zero-padded to a full window rather than rejected
+        let window = audio::pad_or_trim(audio_data, MEL_WINDOW_SAMPLES);
+        let mut state = context.create_state().map_err(inference_error)?;
+        state.pcm_to_mel(&window, 4).map_err(inference_error)?;
+        let (lang_id, probabilities) = state.lang_detect(0, 4).map_err(inference_error)?;
+        
+        let code = whisper_rs::get_lang_str(lang_id)
+            .ok_or_else(|| ManagerError::new(WhisperError::InferenceFailed, format!("Unknown language id {}", lang_id)))?;
+        let probability = probabilities.get(lang_id as usize).copied().unwrap_or(0.0);
+        Ok((code, probability))
+    }
+    
+    /// Read a model's architecture from its loaded context, loading it if needed
+    fn model_metadata(&self, model_id: &str) -> Result<ModelMetadata, ManagerError> {
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let model_arc = models.get(model_id)
+            .ok_or_else(|| ManagerError::not_found(model_id))?;
+        
+        let mut model = lock_order::lock_model(model_arc).map_err(|_| ManagerError::lock("model"))?;
+        self.load_model(&models, &mut model)?;
+        let context = model.ensure_loaded()?;
+        Ok(ModelMetadata {
+            model_type: context.model_type_readable().unwrap_or_else(|_| "unknown".to_string()),
+            multilingual: context.is_multilingual(),
+            n_vocab: context.model_n_vocab(),
+            n_audio_ctx: context.model_n_audio_ctx(),
+            n_text_ctx: context.model_n_text_ctx(),
+            n_mels: context.model_n_mels(),
+        })
+    }
+    
+    /// Measure the real-time factor of decoding `seconds` of synthetic audio
+    /// 
+    /// The model is loaded before timing starts. Inference runs without the
+    /// bookkeeping in `decode_locked` and `transcribe_overriding`, so CPU
+    /// samples and the history only reflect real transcriptions, and any
+    /// carried-over context is left as it was.
+    fn benchmark(&self, model_id: &str, seconds: u32) -> Result<f32, ManagerError> {
+        self.manage_memory()?;
+        
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let mut model = self.select_slot(&models, model_id)?;
+        self.load_model(&models, &mut model)?;
+        
+        let audio = benchmark_audio(seconds as usize * WHISPER_SAMPLE_RATE);
+        let carryover = model.carryover.clone();
+        model.abort.store(false, Ordering::Relaxed);
+        let factor = real_time_factor(seconds as u64 * 1000, || {
+            // VAD would trim the quiet tone and skip inference altogether
+            self.run_inference(&models, &mut model, &audio, &|settings| settings.vad = None, &|_| {})
+                .map(drop)
+        });
+        model.carryover = carryover;
+        factor
+    }
+    
+    fn manage_memory(&self) -> Result<(), ManagerError> {
+        let current_usage = self.current_memory_usage();
+        
+        if current_usage > self.memory_limit() {
+            // Unload idle models to free memory
+            let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+            self.evict_idle(&models);
+        }
+        
+        Ok(())
+    }
+    
+    /// Collect manager-wide state in a single pass
+    /// 
+    /// Models locked by an in-flight transcription are counted as loaded
+    /// rather than waited on, so the snapshot never blocks behind inference.
+    fn stats(&self) -> Result<WhisperStats, ManagerError> {
+        let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+        let loaded_models = models
+            .values()
+            .filter(|model_arc| match lock_order::try_lock_model(model_arc) {
+                Ok(model) => model.ctx.is_some(),
+                Err(TryLockError::WouldBlock) => true,
+                Err(TryLockError::Poisoned(_)) => false,
+            })
+            .count();
+        let avg_cpu = self.cpu_monitor.lock().map(|monitor| monitor.average_cpu_usage()).unwrap_or(0.0);
+        
+        Ok(WhisperStats {
+            loaded_models: loaded_models as u32,
+            total_memory: self.current_memory_usage(),
+            avg_cpu,
+            memory_limit: self.memory_limit(),
+        })
+    }
+    
+    /// A model's estimated and measured memory, without waiting on inference
+    /// 
+    /// Read from the figures each load and unload records, so neither the
+    /// map lock nor the model lock is taken: a writer queued behind a
+    /// running transcription would otherwise stall this until it finished.
+    fn model_memory(&self, model_id: &str) -> Result<(u64, u64), ManagerError> {
+        let figures = self.memory_figures.lock().map_err(|_| ManagerError::lock("memory"))?;
+        figures.get(model_id)
+            .map(|figures| figures.get())
+            .ok_or_else(|| ManagerError::not_found(model_id))
+    }
+    
+    /// Estimated memory of loaded models plus loads still in flight
+    fn current_memory_usage(&self) -> u64 {
+        self.committed_memory.load(Ordering::SeqCst)
+    }
+    
+    fn suggest_model_downgrade(&self, current_model: &str) -> Option<String> {
+        let threshold = f32::from_bits(self.downgrade_threshold.load(Ordering::SeqCst));
+        let overloaded = self.cpu_monitor.lock()
+            .map(|monitor| monitor.should_downgrade(threshold))
+            .unwrap_or(false);
+        if overloaded {
+            self.downgrade_target(current_model)
+        } else {
+            None
+        }
+    }
+    
+    /// Model to suggest in place of `model_id`, ignoring CPU load
+    /// 
+    /// A registered target is named by the path it was registered with;
+    /// without one, the large -> medium -> small -> tiny chain applies by model name.
+    fn downgrade_target(&self, model_id: &str) -> Option<String> {
+        let models = lock_order::read_map(&self.models).ok()?;
+        let target = self.downgrade_targets.read().ok()?.get(model_id).cloned();
+        if let Some((target_id, target_name)) = target {
+            // A target that has since been unregistered suggests nothing
+            return models.contains_key(&target_id).then_some(target_name);
+        }
+        
+        // Ids are path hashes, so the default chain matches on the registered name
+        let model = lock_order::lock_model(models.get(model_id)?).ok()?;
+        let name = &model.model_info.name;
+        if name.contains("large") {
+            Some("medium".to_string())
+        } else if name.contains("medium") {
+            Some("small".to_string())
+        } else if name.contains("small") {
+            Some("tiny".to_string())
+        } else {
+            None
+        }
+    }
+    
+    /// Suggest `to` instead of `from` under CPU pressure, or restore the default chain with None
+    fn set_downgrade_target(&self, from: &str, to: Option<&str>) -> Result<(), ManagerError> {
+        let target = {
+            let models = lock_order::read_map(&self.models).map_err(|_| ManagerError::lock("read"))?;
+            if !models.contains_key(from) {
+                return Err(ManagerError::not_found(from));
+            }
+            match to {
+                Some(to) => {
+                    let target_arc = models.get(to).ok_or_else(|| ManagerError::not_found(to))?;
+                    let target_model = lock_order::lock_model(target_arc).map_err(|_| ManagerError::lock("model"))?;
+                    Some((to.to_string(), target_model.model_info.name.clone()))
+                }
+                None => None,
+            }
+        };
+        
+        let mut targets = self.downgrade_targets.write().map_err(|_| ManagerError::lock("write"))?;
+        match target {
+            Some(target) => targets.insert(from.to_string(), target),
+            None => targets.remove(from),
+        };
+        Ok(())
+    }
+    
+    /// Set the average CPU percent above which a downgrade is suggested
+    fn set_downgrade_threshold(&self, percent: f32) -> Result<(), ManagerError> {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(ManagerError::new(WhisperError::InvalidParams, "Downgrade threshold must be within 0-100%"));
+        }
+        self.downgrade_threshold.store(percent.to_bits(), Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Resident set size of the current process in bytes, if the platform exposes it
+#[cfg(target_os = "macos")]
+#[allow(deprecated)] // libc points at the mach2 crate for mach_task_self
+fn process_resident_bytes() -> Option<u64> {
+    let mut info: libc::mach_task_basic_info = unsafe { std::mem::zeroed() };
+    let mut count = libc::MACH_TASK_BASIC_INFO_COUNT;
+    let result = unsafe {
+        libc::task_info(
+            libc::mach_task_self(),
+            libc::MACH_TASK_BASIC_INFO,
+            &mut info as *mut libc::mach_task_basic_info as libc::task_info_t,
+            &mut count,
+        )
+    };
+    
+    if result == libc::KERN_SUCCESS {
+        Some(info.resident_size)
+    } else {
+        None
+    }
+}
+
+/// Resident set size of the current process in bytes, if the platform exposes it
+#[cfg(target_os = "linux")]
+fn process_resident_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some(resident_pages * page_size as u64)
+}
+
+/// Resident set size of the current process in bytes, if the platform exposes it
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn process_resident_bytes() -> Option<u64> {
+    None
+}
+
+/// Physical performance and efficiency cores, from the perflevel sysctls
+#[cfg(target_os = "macos")]
+fn core_counts() -> CoreCounts {
+    let physical_cpus = |name: &std::ffi::CStr| {
+        let mut count: u32 = 0;
+        let mut size = std::mem::size_of::<u32>();
+        let result = unsafe {
+            libc::sysctlbyname(name.as_ptr(), &mut count as *mut u32 as *mut c_void, &mut size, std::ptr::null_mut(), 0)
+        };
+        (result == 0).then_some(count)
+    };
+    match physical_cpus(c"hw.perflevel0.physicalcpu") {
+        // perflevel1 only exists on chips with efficiency cores
+        Some(performance) => CoreCounts { performance, efficiency: physical_cpus(c"hw.perflevel1.physicalcpu").unwrap_or(0) },
+        None => all_performance_cores(),
+    }
+}
+
+/// Every core counted as a performance core, where the kinds aren't reported
+#[cfg(not(target_os = "macos"))]
+fn core_counts() -> CoreCounts {
+    all_performance_cores()
+}
+
+fn all_performance_cores() -> CoreCounts {
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get() as u32);
+    CoreCounts { performance: cores, efficiency: 0 }
+}
+
+/// The calling thread's previous QoS class, restored when dropped
+/// 
+/// Only Apple platforms steer threads between core kinds by QoS; elsewhere
+/// the preference just sets the thread count.
+struct QosOverride {
+    #[cfg(target_vendor = "apple")]
+    previous: Option<libc::qos_class_t>,
+}
+
+impl CorePreference {
+    /// Run the calling thread at this preference's QoS class until the result is dropped
+    /// 
+    /// The background class keeps threads on the efficiency cores;
+    /// user-initiated work may use the performance cores.
+    #[cfg(target_vendor = "apple")]
+    fn apply_to_current_thread(self) -> QosOverride {
+        let qos = match self {
+            CorePreference::Auto | CorePreference::Performance => libc::qos_class_t::QOS_CLASS_USER_INITIATED,
+            CorePreference::Efficiency => libc::qos_class_t::QOS_CLASS_BACKGROUND,
+        };
+        let mut previous = libc::qos_class_t::QOS_CLASS_UNSPECIFIED;
+        let mut relative_priority = 0;
+        let read = unsafe { libc::pthread_get_qos_class_np(libc::pthread_self(), &mut previous, &mut relative_priority) };
+        if read != 0 || unsafe { libc::pthread_set_qos_class_self_np(qos, 0) } != 0 {
+            logging::warn("Could not set the inference thread's QoS class");
+            return QosOverride { previous: None };
+        }
+        QosOverride { previous: Some(previous) }
+    }
+    
+    #[cfg(not(target_vendor = "apple"))]
+    fn apply_to_current_thread(self) -> QosOverride {
+        QosOverride {}
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+impl Drop for QosOverride {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous {
+            unsafe { libc::pthread_set_qos_class_self_np(previous, 0) };
+        }
+    }
+}
+
+/// Global whisper manager instance
+static WHISPER_MANAGER: once_cell::sync::Lazy<WhisperManager> = 
+    once_cell::sync::Lazy::new(|| WhisperManager::new());
+
+/// Background idle sweeper, if started
+static IDLE_SWEEPER: Mutex<Option<sweeper::IdleSweeper>> = Mutex::new(None);
+
+/// Opaque handle for FFI - now just contains model ID
+pub struct WhisperHandle {
+    model_id: String,
+}
+
+/// Largest capture buffer `whisper_buffer_create` allocates, ten minutes of audio
+const MAX_BUFFER_SAMPLES: usize = 600 * WHISPER_SAMPLE_RATE;
+
+/// Default of `whisper_set_max_audio_len`, four hours of 16kHz audio
+/// 
+/// Well past any recording `whisper_transcribe_long` is given, and far below
+/// the lengths a miscomputed size produces, such as a byte count of a long
+/// buffer or an uninitialized variable.
+const DEFAULT_MAX_AUDIO_LEN: usize = 4 * 3600 * WHISPER_SAMPLE_RATE;
+
+/// Sample count above which caller audio is rejected, see `whisper_set_max_audio_len`
+static MAX_AUDIO_LEN: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_AUDIO_LEN);
+
+/// Whether a caller's `len` is over the audio length ceiling, logging it if so
+/// 
+/// Checked before a raw pointer becomes a slice. The allocation's real size
+/// can't be known, but a length no real buffer has would otherwise read far
+/// past it.
+fn exceeds_audio_len_ceiling(len: usize) -> bool {
+    let ceiling = MAX_AUDIO_LEN.load(Ordering::Relaxed);
+    if len <= ceiling {
+        return false;
+    }
+    logging::warn(&format!(
+        "Rejected an audio length of {} samples, over the ceiling of {}; check how the length is computed",
+        len, ceiling
+    ));
+    true
+}
+
+/// Opaque capture buffer for FFI, shared between a capture and a transcribing thread
+pub struct WhisperBuffer {
+    ring: Mutex<ring::RingBuffer>,
+}
+
+/// FFI-safe result structure
+#[repr(C)]
+pub struct WhisperResult {
+    pub success: bool,
+    pub text: *mut c_char,
+    pub error: *mut c_char,
+    pub error_code: i32,         // WhisperError, None on success
+    pub inference_ms: u64,       // wall time of the call, including any lazy model load
+    pub audio_duration_ms: u64,  // length of the input audio at 16kHz
+    pub segment_count: u32,      // segments in the text, 0 when no speech was found
+    pub has_text: bool,          // false when a successful call produced no text
+    pub detected_lang: *mut c_char, // language decoded as, null if unknown; caller frees
+    pub non_finite_samples: u64, // NaN or infinite input samples decoded as silence
+    pub warning: *mut c_char,    // set when many samples were non-finite, else null; caller frees
+    pub fallback_count: u32,     // temperature fallbacks whisper needed; several suggest unreliable text
+    pub final_temperature: c_float, // highest temperature any window was decoded at
+    pub no_speech: bool,         // success, but no segments or only mostly silent ones
+}
+
+impl WhisperResult {
+    fn error(code: WhisperError, msg: &str) -> Self {
+        WhisperResult {
+            success: false,
+            text: std::ptr::null_mut(),
+            error: create_error_string(msg),
+            error_code: code as i32,
+            inference_ms: 0,
+            audio_duration_ms: 0,
+            segment_count: 0,
+            has_text: false,
+            detected_lang: std::ptr::null_mut(),
+            non_finite_samples: 0,
+            warning: std::ptr::null_mut(),
+            fallback_count: 0,
+            final_temperature: 0.0,
+            no_speech: false,
+        }
+    }
+}
+
+/// FFI-safe transcription segment
+#[repr(C)]
+pub struct WhisperSegment {
+    pub text: *mut c_char,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub avg_logprob: c_float,    // mean log probability of the text tokens
+    pub no_speech_prob: c_float, // estimated share of silence in the segment (0-1)
+    pub starts_new_turn: bool,   // probable speaker change, see whisper_set_speaker_gap
+    pub byte_start: u32,         // UTF-8 byte range of the segment in the array's `text`
+    pub byte_end: u32,
+    pub language: *mut c_char,   // language of the run or window that decoded it, null if unknown
+}
+
+/// FFI-safe word with buffer-relative timing
+#[repr(C)]
+pub struct WhisperWord {
+    pub text: *mut c_char,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub probability: c_float,
+    pub t_dtw: i64, // DTW-aligned start in ms, -1 unless DTW timestamps are enabled
+}
+
+/// FFI-safe decoded token
+#[repr(C)]
+pub struct WhisperTokenInfo {
+    pub id: i32,
+    pub text: *mut c_char, // raw token bytes, may be a partial UTF-8 sequence
+    pub p: c_float,
+    pub t_dtw: i64,        // DTW-aligned time in ms, -1 unless DTW timestamps are enabled
+    pub is_special: bool,  // timestamp, SOT, EOT and other control tokens
+}
+
+/// `WhisperArray` kind: no elements, as returned when an entry point panicked
+pub const WHISPER_ARRAY_NONE: i32 = 0;
+/// `WhisperArray` kind: `WhisperResult` elements
+pub const WHISPER_ARRAY_RESULTS: i32 = 1;
+/// `WhisperArray` kind: `WhisperSegment` elements
+pub const WHISPER_ARRAY_SEGMENTS: i32 = 2;
+/// `WhisperArray` kind: `WhisperWord` elements
+pub const WHISPER_ARRAY_WORDS: i32 = 3;
+/// `WhisperArray` kind: `WhisperTokenInfo` elements
+pub const WHISPER_ARRAY_TOKENS: i32 = 4;
+
+/// FFI-safe array of results, segments, words or tokens, freed with `whisper_free_array`
+/// 
+/// Every array-returning call hands back this one envelope. `kind` says
+/// what `ptr` points at, so a single free releases the elements, the
+/// strings they own and the envelope's own strings.
+#[repr(C)]
+pub struct WhisperArray {
+    pub success: bool,
+    pub kind: i32,                  // WHISPER_ARRAY_*, set even when the call failed
+    pub ptr: *mut c_void,           // `len` elements of `kind`, null on failure
+    pub len: size_t,
+    pub error: *mut c_char,
+    pub error_code: i32,
+    pub detected_lang: *mut c_char, // segments only: language decoded as, null if unknown
+    pub text: *mut c_char,          // segments only: joined and formatted text, as whisper_transcribe returns it
+}
+
+impl WhisperArray {
+    fn new<T: ArrayElement>(elements: Vec<T>) -> Self {
+        let len = elements.len();
+        Self {
+            success: true,
+            kind: T::KIND,
+            ptr: Box::into_raw(elements.into_boxed_slice()).cast(),
+            len,
+            error: std::ptr::null_mut(),
+            error_code: WhisperError::None as i32,
+            detected_lang: std::ptr::null_mut(),
+            text: std::ptr::null_mut(),
+        }
+    }
+    
+    fn error(kind: i32, code: WhisperError, msg: &str) -> Self {
+        Self {
+            success: false,
+            kind,
+            ptr: std::ptr::null_mut(),
+            len: 0,
+            error: create_error_string(msg),
+            error_code: code as i32,
+            detected_lang: std::ptr::null_mut(),
+            text: std::ptr::null_mut(),
+        }
+    }
+}
+
+/// Element type of a `WhisperArray`, tying its kind to how its strings are freed
+trait ArrayElement: Sized {
+    const KIND: i32;
+    
+    fn free_strings(elements: &[Self]);
+}
+
+impl ArrayElement for WhisperResult {
+    const KIND: i32 = WHISPER_ARRAY_RESULTS;
+    
+    fn free_strings(results: &[Self]) {
+        free_result_strings(results);
+    }
+}
+
+impl ArrayElement for WhisperSegment {
+    const KIND: i32 = WHISPER_ARRAY_SEGMENTS;
+    
+    fn free_strings(segments: &[Self]) {
+        free_segment_texts(segments);
+    }
+}
+
+impl ArrayElement for WhisperWord {
+    const KIND: i32 = WHISPER_ARRAY_WORDS;
+    
+    fn free_strings(words: &[Self]) {
+        free_word_texts(words);
+    }
+}
+
+impl ArrayElement for WhisperTokenInfo {
+    const KIND: i32 = WHISPER_ARRAY_TOKENS;
+    
+    fn free_strings(tokens: &[Self]) {
+        free_token_texts(tokens);
+    }
+}
+
+/// Per-call transcription options for `whisper_transcribe_params`
+/// 
+/// Every field applies to that call only; the handle's stored settings are
+/// used for everything else.
+#[repr(C)]
+pub struct WhisperParams {
+    pub language: *const c_char, // ISO 639-1 code or "auto"; NULL keeps English, or detection when translating
+    pub temperature: c_float,    // clamped to [0.0, 1.0]; NaN is rejected
+    pub sampling_mode: i32,      // WHISPER_SAMPLING_GREEDY or WHISPER_SAMPLING_BEAM_SEARCH
+    pub sampling_param: i32,     // best_of or beam size, at least 1
+    pub translate: bool,
+    pub n_threads: i32,          // 0 keeps the default
+}
+
+/// Validated form of `WhisperParams`, free of borrowed C data
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ParamOverrides {
+    language: Option<&'static str>,
+    temperature: f32,
+    sampling: SamplingMode,
+    translate: bool,
+    n_threads: Option<i32>,
+}
+
+impl ParamOverrides {
+    /// Validate caller-filled params, or None if any field is out of range
+    /// 
+    /// # Safety
+    /// `params.language` must be NULL or a valid null-terminated C string.
+    unsafe fn from_ffi(params: &WhisperParams) -> Option<Self> {
+        let language = if params.language.is_null() {
+            None
+        } else {
+            match CStr::from_ptr(params.language).to_str().ok()? {
+                "auto" => Some("auto"),
+                code => Some(whisper_rs::get_lang_id(code).and_then(whisper_rs::get_lang_str)?),
+            }
+        };
+        if params.temperature.is_nan() || params.n_threads < 0 {
+            return None;
+        }
+        
+        Some(Self {
+            language,
+            temperature: params.temperature.clamp(0.0, 1.0),
+            sampling: SamplingMode::from_ffi(params.sampling_mode, params.sampling_param)?,
+            translate: params.translate,
+            n_threads: (params.n_threads > 0).then_some(params.n_threads),
+        })
+    }
+    
+    fn apply(&self, settings: &mut TranscribeSettings) {
+        settings.language = self.language;
+        settings.temperature = self.temperature;
+        settings.sampling = self.sampling;
+        settings.translate = self.translate;
+        settings.n_threads = self.n_threads;
+    }
+}
+
+/// FFI-safe snapshot of manager-wide state
+#[repr(C)]
+pub struct WhisperStats {
+    pub loaded_models: u32,
+    pub total_memory: u64, // estimated bytes of loaded models and in-flight loads
+    pub avg_cpu: c_float,
+    pub memory_limit: u64,
+}
+
+/// Derive a model id from the canonical absolute form of `path`
+/// 
+/// Returns the id and the resolved path. Paths that don't exist yet are made
+/// absolute without resolving symlinks, so they still get a consistent id.
+fn model_id_for_path(path: &str) -> (String, String) {
+    let resolved = std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .map(|resolved| resolved.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    
+    // FNV-1a, so ids don't depend on the standard library's hasher
+    let hash = resolved.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    (format!("model_{:016x}", hash), resolved)
+}
+
+/// Leading bytes of the model formats whisper.cpp can load
+/// 
+/// Legacy ggml containers store their magic as a little-endian u32, so the
+/// bytes on disk read backwards.
+const MODEL_MAGICS: [&[u8; 4]; 4] = [b"lmgg", b"fmgg", b"tjgg", b"GGUF"];
+
+/// Check that `path` is a readable file starting with a ggml or gguf header
+fn check_model_file(path: &str) -> Result<(), WhisperError> {
+    use std::io::Read;
+    
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(WhisperError::FileNotFound),
+        Err(_) => return Err(WhisperError::LoadFailed),
+    };
+    if !file.metadata().map_err(|_| WhisperError::LoadFailed)?.is_file() {
+        return Err(WhisperError::InvalidModel);
+    }
+    
+    let mut magic = [0u8; 4];
+    match file.read_exact(&mut magic) {
+        Ok(()) if MODEL_MAGICS.contains(&&magic) => Ok(()),
+        Ok(()) => Err(WhisperError::InvalidModel),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Err(WhisperError::InvalidModel),
+        Err(_) => Err(WhisperError::LoadFailed),
+    }
+}
+
+/// SHA-256 of the file at `path`, read in fixed-size chunks
+fn file_sha256(path: &str) -> Result<[u8; 32], WhisperError> {
+    use std::io::Read;
+    
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(WhisperError::FileNotFound),
+        Err(_) => return Err(WhisperError::LoadFailed),
+    };
+    let mut hasher = sha256::Sha256::new();
+    let mut chunk = vec![0u8; 1 << 20];
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => return Ok(hasher.finish()),
+            Ok(read) => hasher.update(&chunk[..read]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return Err(WhisperError::LoadFailed),
+        }
+    }
+}
+
+/// A SHA-256 digest written as 64 hex digits, in either case
+fn parse_sha256(hex: &str) -> Option<String> {
+    (hex.len() == 64 && hex.bytes().all(|byte| byte.is_ascii_hexdigit())).then(|| hex.to_ascii_lowercase())
+}
+
+/// Register the model file at `path` with the global manager, returning its id
+fn register_path(path: &str) -> Result<String, ManagerError> {
+    let (model_id, resolved_path) = model_id_for_path(path);
+    WHISPER_MANAGER.register_model(model_id.clone(), resolved_path, model_info_for_path(path))?;
+    Ok(model_id)
+}
+
+/// Register the model file at `path` under the caller's `id`
+/// 
+/// Ids must be non-empty and can't contain `#`, which is reserved for
+/// pooled replicas. Taken ids are rejected rather than replaced.
+fn register_named(path: &str, id: &str) -> Result<(), ManagerError> {
+    if id.is_empty() || id.contains('#') {
+        return Err(ManagerError::new(WhisperError::InvalidParams, format!("Invalid model id '{}'", id)));
+    }
+    let (_, resolved_path) = model_id_for_path(path);
+    WHISPER_MANAGER.register_new_model(id.to_string(), resolved_path, model_info_for_path(path))
+}
+
+/// Size estimates for the model at `path`, guessed from its file name
+fn model_info_for_path(path: &str) -> ModelInfo {
+    let model_size = ModelSize::from_name(path);
+    ModelInfo {
+        name: path.to_string(),
+        size: model_size,
+        memory_usage: match model_size {
+            ModelSize::Tiny => 39 * 1024 * 1024,   // ~39MB
+            ModelSize::Small => 244 * 1024 * 1024,  // ~244MB
+            ModelSize::Medium => 769 * 1024 * 1024, // ~769MB
+            ModelSize::Large => 3 * 1024 * 1024 * 1024, // ~3GB
+        },
+        cpu_factor: match model_size {
+            ModelSize::Tiny => 1.0,
+            ModelSize::Small => 2.5,
+            ModelSize::Medium => 4.0,
+            ModelSize::Large => 8.0,
+        },
+    }
+}
+
+/// Check a model file without registering or loading it
+/// 
+/// Only the file's header is read. Returns `WHISPER_ERROR_NONE` for a
+/// readable ggml or gguf file, `WHISPER_ERROR_FILE_NOT_FOUND` if nothing
+/// exists at `path`, `WHISPER_ERROR_LOAD_FAILED` if it can't be read, and
+/// `WHISPER_ERROR_INVALID_MODEL` for anything else, such as an interrupted
+/// download saved as an HTML error page. A valid header doesn't guarantee the
+/// rest of the file is intact.
+/// 
+/// # Safety
+/// - model_path must be a valid null-terminated C string
+#[no_mangle]
+pub extern "C" fn whisper_validate_model(model_path: *const c_char) -> i32 {
+    ffi_guard("whisper_validate_model", || {
+        if model_path.is_null() {
+            return WhisperError::InvalidParams as i32;
+        }
+        
+        let outcome = match unsafe { CStr::from_ptr(model_path) }.to_str() {
+            Ok(path) => check_model_file(path),
+            Err(_) => Err(WhisperError::InvalidParams),
+        };
+        outcome.err().unwrap_or(WhisperError::None) as i32
+    })
+}
+
+/// Initialize whisper context with model path
+#[no_mangle]
+pub extern "C" fn whisper_init(model_path: *const c_char) -> *mut WhisperHandle {
+    ffi_guard("whisper_init", || {
+        if model_path.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        let path_str = unsafe {
+            match CStr::from_ptr(model_path).to_str() {
+                Ok(s) => s,
+                Err(_) => return std::ptr::null_mut(),
+            }
+        };
+        
+        match register_path(path_str) {
+            Ok(model_id) => {
+                let handle = Box::new(WhisperHandle { model_id });
+                Box::into_raw(handle)
+            }
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Register a model like `whisper_init`, only if the file's SHA-256 matches
+/// 
+/// Catches truncated or corrupted downloads, which pass the header check in
+/// `whisper_validate_model` but fail at load or inference time. The whole
+/// file is hashed in chunks, so for a large model this takes a moment.
+/// `expected_sha256` is 64 hex digits in either case. Returns
+/// `WHISPER_ERROR_NONE` and stores the new handle in `*handle_out`;
+/// `WHISPER_ERROR_CHECKSUM_MISMATCH` if the contents differ,
+/// `WHISPER_ERROR_FILE_NOT_FOUND` or `WHISPER_ERROR_LOAD_FAILED` if the
+/// file can't be read, and `WHISPER_ERROR_INVALID_PARAMS` for a malformed
+/// digest. `*handle_out` is left untouched on failure.
+/// 
+/// # Safety
+/// - model_path and expected_sha256 must be valid null-terminated C strings
+/// - handle_out must be a valid pointer to write the handle to
+#[no_mangle]
+pub extern "C" fn whisper_init_verified(
+    model_path: *const c_char,
+    expected_sha256: *const c_char,
+    handle_out: *mut *mut WhisperHandle,
+) -> i32 {
+    ffi_guard("whisper_init_verified", || {
+        if model_path.is_null() || expected_sha256.is_null() || handle_out.is_null() {
+            return WhisperError::InvalidParams as i32;
+        }
+        
+        let strings = unsafe { (CStr::from_ptr(model_path).to_str(), CStr::from_ptr(expected_sha256).to_str()) };
+        let (Ok(path), Ok(expected)) = strings else {
+            return WhisperError::InvalidParams as i32;
+        };
+        let Some(expected) = parse_sha256(expected) else {
+            return WhisperError::InvalidParams as i32;
+        };
+        
+        let actual = match file_sha256(path) {
+            Ok(digest) => sha256::to_hex(&digest),
+            Err(code) => return code as i32,
+        };
+        if actual != expected {
+            logging::warn(&format!(
+                "Model '{}' has SHA-256 {}, expected {}; the file may be incomplete or corrupt",
+                path, actual, expected
+            ));
+            return WhisperError::ChecksumMismatch as i32;
+        }
+        
+        match register_path(path) {
+            Ok(model_id) => {
+                unsafe { *handle_out = Box::into_raw(Box::new(WhisperHandle { model_id })) };
+                WhisperError::None as i32
+            }
+            Err(e) => e.code as i32,
+        }
+    })
+}
+
+/// Initialize a model registered under a caller-chosen id, e.g. "fast" or "accurate"
+/// 
+/// Unlike `whisper_init`, the id doesn't depend on the path, so the file
+/// behind a name can be swapped by unregistering and registering it again.
+/// Returns null if `id` is empty, contains `#`, or is already registered.
+/// 
+/// # Safety
+/// - model_path and id must be valid null-terminated C strings
+#[no_mangle]
+pub extern "C" fn whisper_init_named(model_path: *const c_char, id: *const c_char) -> *mut WhisperHandle {
+    ffi_guard("whisper_init_named", || {
+        if model_path.is_null() || id.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        let (path_str, id_str) = match unsafe { (CStr::from_ptr(model_path).to_str(), CStr::from_ptr(id).to_str()) } {
+            (Ok(path), Ok(id)) => (path, id),
+            _ => return std::ptr::null_mut(),
+        };
+        
+        match register_named(path_str, id_str) {
+            Ok(()) => Box::into_raw(Box::new(WhisperHandle { model_id: id_str.to_string() })),
+            Err(e) => {
+                logging::warn(&e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Root mean square level of `len` samples, for level meters
+/// 
+/// The same measure the energy VAD (`whisper_set_vad`) and the segments'
+/// no-speech estimate compare against their 0.01 threshold, so a buffer
+/// staying below it would be treated as silence. Returns 0 for an empty or
+/// NULL buffer.
+/// 
+/// # Safety
+/// - samples must point to `len` valid f32 samples, or be NULL
+#[no_mangle]
+pub extern "C" fn whisper_audio_rms(samples: *const c_float, len: size_t) -> c_float {
+    ffi_guard("whisper_audio_rms", || {
+        if samples.is_null() || len == 0 || exceeds_audio_len_ceiling(len) {
+            return 0.0;
+        }
+        audio::rms(unsafe { std::slice::from_raw_parts(samples, len) })
+    })
+}
+
+/// Largest sample magnitude among `len` samples, for clipping indicators
+/// 
+/// Returns 0 for an empty or NULL buffer.
+/// 
+/// # Safety
+/// - samples must point to `len` valid f32 samples, or be NULL
+#[no_mangle]
+pub extern "C" fn whisper_audio_peak(samples: *const c_float, len: size_t) -> c_float {
+    ffi_guard("whisper_audio_peak", || {
+        if samples.is_null() || len == 0 || exceeds_audio_len_ceiling(len) {
+            return 0.0;
+        }
+        audio::peak(unsafe { std::slice::from_raw_parts(samples, len) })
+    })
+}
+
+/// Transcribe audio data using the whisper model manager
+/// 
+/// Implements full whisper.cpp integration with lazy loading, memory management,
+/// and CPU monitoring for automatic model downgrade.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples  
+/// - audio_len must accurately represent the length of audio_data
+#[no_mangle]
+pub extern "C" fn whisper_transcribe(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe", || {
+        if handle.is_null() || audio_data.is_null() || audio_len <= 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        
+        // Convert audio data to slice
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        // Perform transcription using the manager
+        let result = WHISPER_MANAGER.transcribe(&handle_ref.model_id, audio_slice);
+        if result.is_ok() {
+            // Check if model downgrade is suggested
+            if let Some(suggested_model) = WHISPER_MANAGER.suggest_model_downgrade(&handle_ref.model_id) {
+                logging::info(&format!("High CPU usage detected, consider switching to {} model", suggested_model));
+            }
+        }
+        
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Transcribe audio data with options for this call only
+/// 
+/// The handle's stored settings are left untouched, so calls with different
+/// options can be interleaved on one model from several threads. A NULL
+/// `params` uses the stored settings alone, like `whisper_transcribe`.
+/// Out-of-range params fail with `WHISPER_ERROR_INVALID_PARAMS`.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - params must be NULL or point to a valid WhisperParams
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_params(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    params: *const WhisperParams,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_params", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let overrides = if params.is_null() {
+            None
+        } else {
+            match unsafe { ParamOverrides::from_ffi(&*params) } {
+                Some(overrides) => Some(overrides),
+                None => return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters"),
+            }
+        };
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let adjust = |settings: &mut TranscribeSettings| {
+            if let Some(overrides) = &overrides {
+                overrides.apply(settings);
+            }
+        };
+        let result = WHISPER_MANAGER
+            .transcribe_overriding(&handle_ref.model_id, audio_slice, adjust, |_| {})
+            .map(|decoded| Transcript::from_decoded(&decoded));
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Transcribe audio, giving up once inference runs longer than `timeout_ms`
+/// 
+/// The budget starts after the model is loaded. An overrun stops decoding
+/// at whisper's next abort check and fails with `TimedOut`.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to audio_len valid f32 values
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_with_timeout(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    timeout_ms: u32,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_with_timeout", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || timeout_ms == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let timeout = Duration::from_millis(timeout_ms as u64);
+        let result = WHISPER_MANAGER
+            .transcribe_overriding(&handle_ref.model_id, audio_slice, |settings| settings.timeout = Some(timeout), |_| {})
+            .map(|decoded| Transcript::from_decoded(&decoded));
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Transcribe audio recorded at an arbitrary sample rate
+/// 
+/// Resamples to 16kHz before inference; audio already at 16kHz is passed
+/// through untouched. `audio_duration_ms` in the result reflects the audio
+/// length, which is unchanged by resampling.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 mono audio samples at `src_rate`
+/// - audio_len must accurately represent the length of audio_data
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_resampled(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    src_rate: u32,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_resampled", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || src_rate == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let resampled = resample::resample_to_16k(audio_slice, src_rate);
+        let result = WHISPER_MANAGER.transcribe(&handle_ref.model_id, &resampled);
+        transcription_result(result, start_time.elapsed(), resampled.len())
+    })
+}
+
+/// Transcribe a WAV file
+/// 
+/// 16-bit integer and 32-bit float PCM are accepted at any sample rate and
+/// channel count, and converted to 16kHz mono. Unreadable files and other
+/// formats fail with `WHISPER_ERROR_INVALID_PARAMS` and a message naming the
+/// problem.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - path must be a valid null-terminated C string
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_wav(handle: *mut WhisperHandle, path: *const c_char) -> WhisperResult {
+    ffi_guard("whisper_transcribe_wav", || {
+        if handle.is_null() || path.is_null() {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let path = match unsafe { CStr::from_ptr(path) }.to_str() {
+            Ok(path) => path,
+            Err(_) => return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters"),
+        };
+        
+        let samples = match wav::load_wav_16k_mono(path) {
+            Ok(samples) if !samples.is_empty() => samples,
+            Ok(_) => return WhisperResult::error(WhisperError::InvalidParams, "WAV file contains no audio"),
+            Err(message) => return WhisperResult::error(WhisperError::InvalidParams, &message),
+        };
+        let result = WHISPER_MANAGER.transcribe(&handle_ref.model_id, &samples);
+        transcription_result(result, start_time.elapsed(), samples.len())
+    })
+}
+
+/// Transcribe interleaved multi-channel 16kHz audio
+/// 
+/// Channels are averaged to mono before inference; mono input is passed
+/// through without copying. `audio_len` counts samples across all channels.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid interleaved f32 samples
+/// - audio_len must accurately represent the length of audio_data
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_multichannel(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    channels: u16,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_multichannel", || {
+        if handle.is_null() || audio_data.is_null() || audio_len < channels as usize || channels == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let mono = audio::downmix_to_mono(audio_slice, channels);
+        let result = WHISPER_MANAGER.transcribe(&handle_ref.model_id, &mono);
+        transcription_result(result, start_time.elapsed(), mono.len())
+    })
+}
+
+/// Validate a millisecond window into a buffer of `audio_len` 16kHz samples
+/// 
+/// A zero `duration_ms` runs to the end of the buffer. Returns the window's
+/// offset and duration as whisper expects them, plus its length in samples,
+/// or `None` when the window doesn't fit inside the buffer.
+fn range_window(audio_len: usize, offset_ms: u32, duration_ms: u32) -> Option<(i32, i32, usize)> {
+    let total_ms = (audio_len * 1000 / WHISPER_SAMPLE_RATE) as u64;
+    let (offset, duration) = (offset_ms as u64, duration_ms as u64);
+    if offset >= total_ms || offset + duration > total_ms {
+        return None;
+    }
+    
+    let window_ms = if duration == 0 { total_ms - offset } else { duration };
+    Some((
+        i32::try_from(offset).ok()?,
+        i32::try_from(duration).ok()?,
+        window_ms as usize * WHISPER_SAMPLE_RATE / 1000,
+    ))
+}
+
+/// Transcribe only a window of a larger 16kHz buffer
+/// 
+/// Whisper skips to `offset_ms` and stops after `duration_ms` (0 means to
+/// the end of the buffer) without the audio being copied.
+/// `audio_duration_ms` in the result is the window's length. When VAD is
+/// enabled only the silence inside the window is trimmed, and timestamps
+/// stay relative to the start of the buffer.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_range(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    offset_ms: u32,
+    duration_ms: u32,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_range", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        let Some((offset, duration, window_len)) = range_window(audio_len, offset_ms, duration_ms) else {
+            return WhisperResult::error(WhisperError::InvalidParams, "Range exceeds the audio length");
+        };
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let result = WHISPER_MANAGER
+            .transcribe_overriding(
+                &handle_ref.model_id,
+                audio_slice,
+                |settings| settings.range = Some((offset, duration)),
+                |_| {},
+            )
+            .map(|decoded| Transcript::from_decoded(&decoded));
+        transcription_result(result, start_time.elapsed(), window_len)
+    })
+}
+
+/// Detect the spoken language without transcribing
+/// 
+/// Runs whisper's language detection on the first 30 seconds of audio
+/// (shorter clips are zero-padded) and returns the ISO 639-1 code, e.g.
+/// "de". When `confidence` is non-null it receives the detected language's
+/// probability. Returns null on failure. English-only models always
+/// report "en".
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - confidence must be null or point to a writable float
+/// - the returned string must be released with whisper_free_string
+#[no_mangle]
+pub extern "C" fn whisper_detect_language(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    confidence: *mut c_float,
+) -> *mut c_char {
+    ffi_guard("whisper_detect_language", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return std::ptr::null_mut();
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        match WHISPER_MANAGER.detect_language(&handle_ref.model_id, audio_slice) {
+            Ok((code, probability)) => {
+                if !confidence.is_null() {
+                    unsafe { *confidence = probability };
+                }
+                match CString::new(code) {
+                    Ok(c_string) => c_string.into_raw(),
+                    Err(_) => std::ptr::null_mut(),
+                }
+            }
+            Err(e) => {
+                logging::warn(&e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Describe the model's architecture as JSON (caller must free result)
+/// 
+/// Loads the model if it isn't loaded yet. The object has the keys
+/// `type` (e.g. "medium"), `is_multilingual`, `n_vocab`, `n_audio_ctx`,
+/// `n_text_ctx` and `n_mels`, read from the model file rather than guessed
+/// from its name. English-only models report `is_multilingual: false` and
+/// can't transcribe other languages. Returns null if the model can't be
+/// loaded.
+#[no_mangle]
+pub extern "C" fn whisper_get_model_info(handle: *mut WhisperHandle) -> *mut c_char {
+    ffi_guard("whisper_get_model_info", || {
+        if handle.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        match WHISPER_MANAGER.model_metadata(&handle_ref.model_id) {
+            Ok(metadata) => match CString::new(metadata.to_json()) {
+                Ok(c_string) => c_string.into_raw(),
+                Err(_) => std::ptr::null_mut(),
+            },
+            Err(e) => {
+                logging::warn(&e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Describe the settings the next transcription will use as JSON (caller must free result)
+/// 
+/// Models start with defaults picked by size and whether they're
+/// multilingual: tiny and English-only small models decode greedily on 4
+/// threads, larger ones use beam search on more. Setters such as
+/// `whisper_set_sampling` change them. The object has the keys `n_threads`,
+/// `sampling` ("greedy" or "beam_search"), `sampling_param` (best_of or beam
+/// size), `temperature`, `language` ("auto" when detecting) and `translate`.
+/// Returns null for an unknown handle.
+#[no_mangle]
+pub extern "C" fn whisper_get_effective_params(handle: *mut WhisperHandle) -> *mut c_char {
+    ffi_guard("whisper_get_effective_params", || {
+        if handle.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        match WHISPER_MANAGER.with_model(&handle_ref.model_id, |model| model.effective_params_json()) {
+            Ok(json) => match CString::new(json) {
+                Ok(c_string) => c_string.into_raw(),
+                Err(_) => std::ptr::null_mut(),
+            },
+            Err(e) => {
+                logging::warn(&e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Estimate how fast this model runs on this machine
+/// 
+/// Loads the model, then transcribes `seconds` of synthetic audio with the
+/// handle's settings and discards the text. Returns the real-time factor,
+/// inference time divided by audio time, so values below 1 are faster than
+/// real time. The run isn't recorded in the CPU usage history. Returns 0 if
+/// `seconds` is 0 or the model can't be loaded or run.
+#[no_mangle]
+pub extern "C" fn whisper_benchmark(handle: *mut WhisperHandle, seconds: u32) -> c_float {
+    ffi_guard("whisper_benchmark", || {
+        if handle.is_null() || seconds == 0 {
+            return 0.0;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        match WHISPER_MANAGER.benchmark(&handle_ref.model_id, seconds) {
+            Ok(factor) => factor,
+            Err(e) => {
+                logging::warn(&e.to_string());
+                0.0
+            }
+        }
+    })
+}
+
+/// Set the idle timeout after which a loaded model may be unloaded
+/// 
+/// A value of 0 disables automatic unloading for this model.
+#[no_mangle]
+pub extern "C" fn whisper_set_idle_timeout(handle: *mut WhisperHandle, seconds: u64) -> bool {
+    ffi_guard("whisper_set_idle_timeout", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.idle_timeout = Duration::from_secs(seconds);
+            })
+            .is_ok()
+    })
+}
+
+/// Set the default idle timeout applied to newly registered models
+/// 
+/// A value of 0 disables automatic unloading. Already registered models
+/// keep their current timeout.
+#[no_mangle]
+pub extern "C" fn whisper_set_idle_timeout_global(seconds: u64) {
+    ffi_guard("whisper_set_idle_timeout_global", || {
+        WHISPER_MANAGER.set_default_idle_timeout(Duration::from_secs(seconds));
+    })
+}
+
+/// Retry low-confidence transcriptions once with beam search
+/// 
+/// Confidence is the mean per-token probability of the greedy result's
+/// text tokens. When it falls below `threshold`, inference runs a second
+/// time using beam search with `beam_size` beams, so low-confidence clips
+/// pay for two inference passes. A `threshold` of 0 or less disables the retry.
+#[no_mangle]
+pub extern "C" fn whisper_set_confidence_retry(handle: *mut WhisperHandle, threshold: c_float, beam_size: i32) -> bool {
+    ffi_guard("whisper_set_confidence_retry", || {
+        if handle.is_null() || beam_size < 1 {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let retry = (threshold > 0.0).then_some(ConfidenceRetry { threshold, beam_size });
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.confidence_retry = retry;
+            })
+            .is_ok()
+    })
+}
+
+/// `whisper_set_sampling` mode: greedy decoding, `param` is best_of
+pub const WHISPER_SAMPLING_GREEDY: i32 = 0;
+/// `whisper_set_sampling` mode: beam search, `param` is the beam size
+pub const WHISPER_SAMPLING_BEAM_SEARCH: i32 = 1;
+
+/// Choose the decoding strategy for subsequent transcriptions
+/// 
+/// Tiny and English-only small models default to greedy decoding with
+/// best_of 1, the lowest-latency option; larger and multilingual models
+/// default to beam search, see `whisper_get_effective_params`. Beam search
+/// is slower but noticeably more accurate on small models for accented
+/// speech. `param` must be at least 1. When beam search
+/// is selected the confidence retry is skipped, as it would only repeat it.
+#[no_mangle]
+pub extern "C" fn whisper_set_sampling(handle: *mut WhisperHandle, mode: i32, param: i32) -> bool {
+    ffi_guard("whisper_set_sampling", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let Some(sampling) = SamplingMode::from_ffi(mode, param) else {
+            return false;
+        };
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.sampling = sampling;
+            })
+            .is_ok()
+    })
+}
+
+/// Most greedy candidates per pass; whisper.cpp runs at most 8 decoders
+pub const WHISPER_MAX_BEST_OF: i32 = 8;
+
+/// Keep the most probable of `n` greedy candidates
+/// 
+/// whisper samples the candidates wherever decoding runs above temperature
+/// 0: every pass when `whisper_set_temperature` is raised above 0, and
+/// otherwise only the fallback passes of unreliable segments. Each
+/// candidate costs roughly one more decoding pass, so this trades latency
+/// for quality short of beam search. `n` must be at least 1 and is capped
+/// at `WHISPER_MAX_BEST_OF`. Selects greedy decoding, so it replaces beam
+/// search, whether that's the model's tuned default or was chosen with
+/// `whisper_set_sampling`.
+#[no_mangle]
+pub extern "C" fn whisper_set_best_of(handle: *mut WhisperHandle, n: i32) -> bool {
+    ffi_guard("whisper_set_best_of", || {
+        if handle.is_null() || n < 1 {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.sampling = SamplingMode::greedy_best_of(n);
+            })
+            .is_ok()
+    })
+}
+
+/// Strip non-speech annotations such as `[BLANK_AUDIO]` from the output
+/// 
+/// Removes every `[...]` and `(...)` span from segment text, so sound
+/// descriptions like "(laughing)" are removed as well. Segments left empty
+/// are dropped. Word timestamps are unaffected. Off by default, since
+/// captioning users may want the annotations.
+#[no_mangle]
+pub extern "C" fn whisper_set_suppress_non_speech(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_suppress_non_speech", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.suppress_non_speech = enabled;
+            })
+            .is_ok()
+    })
+}
+
+/// Cap segments at roughly `chars` characters, e.g. for subtitles
+/// 
+/// Segments are split at word boundaries, so a single long word can still
+/// run past the cap. Token timestamps are enabled automatically, since
+/// whisper needs them to split segments. 0 restores whisper's default
+/// segment lengths; values above `i32::MAX` are rejected.
+#[no_mangle]
+pub extern "C" fn whisper_set_max_segment_len(handle: *mut WhisperHandle, chars: u32) -> bool {
+    ffi_guard("whisper_set_max_segment_len", || {
+        let Ok(max_len) = i32::try_from(chars) else {
+            return false;
+        };
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.max_segment_len = Some(max_len).filter(|&len| len > 0);
+            })
+            .is_ok()
+    })
+}
+
+/// Prepend `ms` of silence to each buffer before inference
+/// 
+/// Gives the encoder a lead-in when the audio starts right on the first
+/// word, which otherwise is sometimes clipped. Timestamps are shifted back,
+/// so they stay relative to the audio passed in. 0 (the default) adds
+/// nothing; values above 30000 are rejected.
+#[no_mangle]
+pub extern "C" fn whisper_set_lead_silence_ms(handle: *mut WhisperHandle, ms: u32) -> bool {
+    ffi_guard("whisper_set_lead_silence_ms", || {
+        if handle.is_null() || ms > MAX_LEAD_SILENCE_MS {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.lead_silence_ms = ms;
+            })
+            .is_ok()
+    })
+}
+
+/// Fail transcriptions with an unreadable segment instead of marking the gap
+/// 
+/// whisper can hand back segment text that isn't valid UTF-8. By default
+/// such a segment keeps its place and timing with U+FFFD as its text, so
+/// the gap is visible. When strict, the call fails with `ConversionFailed`
+/// and an error naming the segment's index.
+#[no_mangle]
+pub extern "C" fn whisper_set_strict_segments(handle: *mut WhisperHandle, strict: bool) -> bool {
+    ffi_guard("whisper_set_strict_segments", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.strict_segments = strict;
+            })
+            .is_ok()
+    })
+}
+
+/// Refuse single-call buffers longer than `secs`, 0 restoring the default of 600
+/// 
+/// Longer buffers fail with `AudioTooLong` before the model is loaded or any
+/// inference memory is allocated. `whisper_transcribe_long` applies the
+/// limit to each window rather than the whole buffer, and
+/// `whisper_transcribe_range` to the range it decodes.
+#[no_mangle]
+pub extern "C" fn whisper_set_max_audio_secs(handle: *mut WhisperHandle, secs: u32) -> bool {
+    ffi_guard("whisper_set_max_audio_secs", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.max_audio_secs = Some(secs).filter(|&secs| secs > 0);
+            })
+            .is_ok()
+    })
+}
+
+/// Reject audio lengths above `samples` in every call that takes a buffer, 0 restoring the default
+/// 
+/// A sanity check on the length a caller passes alongside its pointer,
+/// made before the samples are read: a wrong length, e.g. a byte count
+/// instead of a sample count, fails with `InvalidParams` instead of reading
+/// past the buffer. It can't catch a length that is wrong by less than the
+/// ceiling. The default is four hours of 16kHz audio, and the setting
+/// applies to every handle.
+#[no_mangle]
+pub extern "C" fn whisper_set_max_audio_len(samples: size_t) -> bool {
+    ffi_guard("whisper_set_max_audio_len", || {
+        let ceiling = if samples == 0 { DEFAULT_MAX_AUDIO_LEN } else { samples };
+        MAX_AUDIO_LEN.store(ceiling, Ordering::Relaxed);
+        true
+    })
+}
+
+/// Post-process transcription text with `WHISPER_TEXT_*` flags, 0 (the default) for none
+/// 
+/// Meant for dictating code, where whisper's sentence capitalization and
+/// final period get in the way. Flags combine and apply to the joined text
+/// of each result; segment texts are untouched. Unknown flags are rejected.
+#[no_mangle]
+pub extern "C" fn whisper_set_text_format(handle: *mut WhisperHandle, flags: u32) -> bool {
+    ffi_guard("whisper_set_text_format", || {
+        if handle.is_null() || flags & !WHISPER_TEXT_ALL != 0 {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.text_format = flags;
+            })
+            .is_ok()
+    })
+}
+
+/// `whisper_set_trim` policy: keep whisper's whitespace at both ends of the text
+pub const WHISPER_TRIM_NONE: i32 = 0;
+/// `whisper_set_trim` policy: trim both ends, the default
+pub const WHISPER_TRIM_BOTH: i32 = 1;
+/// `whisper_set_trim` policy: trim only the start
+pub const WHISPER_TRIM_LEADING: i32 = 2;
+/// `whisper_set_trim` policy: trim only the end, keeping the leading space
+pub const WHISPER_TRIM_TRAILING: i32 = 3;
+
+/// Choose which ends of each result's text are trimmed of whitespace
+/// 
+/// whisper starts segments with a space, which results normally drop.
+/// Dictating into the middle of a sentence wants it kept, so " word"
+/// lands after the cursor with a space; `WHISPER_TRIM_TRAILING` does that.
+/// The kept whitespace is whisper's own, around the text after
+/// `whisper_set_text_format` flags are applied. Segment texts and an empty
+/// result are unaffected. Unknown policies are rejected.
+#[no_mangle]
+pub extern "C" fn whisper_set_trim(handle: *mut WhisperHandle, policy: i32) -> bool {
+    ffi_guard("whisper_set_trim", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let Some(trim) = TrimPolicy::from_ffi(policy) else {
+            return false;
+        };
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.trim = trim;
+            })
+            .is_ok()
+    })
+}
+
+/// Never let the decoder emit the `len` token ids at `ids`
+/// 
+/// Unlike an initial prompt, which only biases decoding, suppressed tokens
+/// are ruled out at every step; suppressing the digit or punctuation tokens
+/// keeps them out of the text entirely. Replaces any earlier list, and
+/// `len` 0 clears it. Ids past the model's vocabulary are ignored. Returns
+/// false for a null `ids` with a non-zero `len` or a negative id.
+#[no_mangle]
+pub extern "C" fn whisper_set_suppress_tokens(handle: *mut WhisperHandle, ids: *const i32, len: size_t) -> bool {
+    ffi_guard("whisper_set_suppress_tokens", || {
+        if handle.is_null() || (ids.is_null() && len > 0) {
+            return false;
+        }
+        
+        let ids = if len == 0 { &[][..] } else { unsafe { std::slice::from_raw_parts(ids, len) } };
+        if ids.iter().any(|&id| id < 0) {
+            return false;
+        }
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.suppress_tokens = ids.to_vec();
+            })
+            .is_ok()
+    })
+}
+
+/// Mark segments that follow a pause longer than `gap_ms` as new speaker turns
+/// 
+/// A cheap stand-in for diarization: the segment after each long pause has
+/// `starts_new_turn` set. Only segment timestamps are used, no extra model.
+/// 0 (the default) turns marking off.
+#[no_mangle]
+pub extern "C" fn whisper_set_speaker_gap(handle: *mut WhisperHandle, gap_ms: u32) -> bool {
+    ffi_guard("whisper_set_speaker_gap", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.speaker_gap_ms = Some(gap_ms).filter(|&gap| gap > 0);
+            })
+            .is_ok()
+    })
+}
+
+/// Cut hallucinated repetition loops from the end of each segment
+/// 
+/// A word or phrase repeated more than three times in a row at the end of
+/// a segment is reduced to a single occurrence; shorter runs such as
+/// "no no no" are kept. Word timestamps are unaffected. Off by default.
+#[no_mangle]
+pub extern "C" fn whisper_set_collapse_repeats(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_collapse_repeats", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.collapse_repeats = enabled;
+            })
+            .is_ok()
+    })
+}
+
+/// Let the decoder start a segment with a blank or end-of-text token
+/// 
+/// whisper suppresses these by default, which stops it ending a window
+/// straight away; passing false allows it, so silence decodes to nothing
+/// rather than a guessed word.
+#[no_mangle]
+pub extern "C" fn whisper_set_suppress_blank(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_suppress_blank", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.suppress_blank = Some(enabled);
+            })
+            .is_ok()
+    })
+}
+
+/// Stop the decoder producing non-speech tokens such as music symbols
+/// 
+/// Unlike `whisper_set_suppress_non_speech`, which strips annotations from
+/// the finished text, this changes what is decoded: whisper picks the best
+/// speech token instead, which can shift the surrounding words. The two can
+/// be combined. Off by default.
+#[no_mangle]
+pub extern "C" fn whisper_set_suppress_nst(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_suppress_nst", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.suppress_nst = enabled;
+            })
+            .is_ok()
+    })
+}
+
+/// Normalize input levels before inference
+/// 
+/// Quiet recordings are boosted to a consistent RMS level, with the peak
+/// limited so nothing clips; silent audio is passed through unchanged. VAD
+/// runs on the original levels. Off by default.
+#[no_mangle]
+pub extern "C" fn whisper_set_auto_gain(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_auto_gain", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.auto_gain = enabled;
+            })
+            .is_ok()
+    })
+}
+
+/// Carry context from one transcription into the next
+/// 
+/// When enabled, the trailing text tokens of each result seed the prompt of
+/// the following call on this handle, so consecutive chunks of a long
+/// recording keep punctuation and names consistent. Any prompt set with
+/// `whisper_set_prompt` still comes first. Disabling drops the stored
+/// context. Off by default.
+#[no_mangle]
+pub extern "C" fn whisper_set_context_carryover(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_context_carryover", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.context_carryover = enabled;
+                if !enabled {
+                    model.carryover.clear();
+                }
+            })
+            .is_ok()
+    })
+}
+
+/// Set the initial sampling temperature, clamped to [0.0, 1.0]
+/// 
+/// Zero (the default) decodes deterministically. Higher temperatures and
+/// the fallbacks are reproducible too: whisper.cpp seeds its sampler with a
+/// fixed value in every decoding state and offers no seed parameter, and
+/// each call decodes in a fresh state. So the same audio and settings give
+/// the same text, unless context carryover changes the prompt between
+/// calls. Returns false for NaN.
+#[no_mangle]
+pub extern "C" fn whisper_set_temperature(handle: *mut WhisperHandle, temperature: c_float) -> bool {
+    ffi_guard("whisper_set_temperature", || {
+        if handle.is_null() || temperature.is_nan() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.temperature = temperature.clamp(0.0, 1.0);
+            })
+            .is_ok()
+    })
+}
+
+/// Set the temperature fallback step, clamped to [0.0, 1.0]
+/// 
+/// When a decode trips whisper's compression-ratio or log probability
+/// thresholds (typically a repetition loop like "the the the"), it is
+/// retried at a temperature raised by `increment`, up to 1.0. Whisper's
+/// default step is 0.2; 0 disables the fallback. Returns false for NaN.
+#[no_mangle]
+pub extern "C" fn whisper_set_temperature_fallback(handle: *mut WhisperHandle, increment: c_float) -> bool {
+    ffi_guard("whisper_set_temperature_fallback", || {
+        if handle.is_null() || increment.is_nan() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.temperature_inc = Some(increment.clamp(0.0, 1.0));
+            })
+            .is_ok()
+    })
+}
+
+/// Translate speech in any supported language into English text
+/// 
+/// Enabling translation also switches the source language to automatic
+/// detection. Only multilingual models can translate; English-only models
+/// (`*.en`) ignore the task. Off by default.
+#[no_mangle]
+pub extern "C" fn whisper_set_translate(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_translate", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.translate = enabled;
+            })
+            .is_ok()
+    })
+}
+
+/// Condition transcriptions on an initial prompt
+/// 
+/// The prompt is fed to whisper as preceding context, biasing recognition
+/// towards its vocabulary (domain jargon, proper names). It is stored on the
+/// model registration, so it survives idle unloads, and applies to every
+/// transcription until cleared with a null or empty string.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - text must be null or a valid null-terminated UTF-8 string
+#[no_mangle]
+pub extern "C" fn whisper_set_prompt(handle: *mut WhisperHandle, text: *const c_char) -> bool {
+    ffi_guard("whisper_set_prompt", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let prompt = if text.is_null() {
+            None
+        } else {
+            match unsafe { CStr::from_ptr(text) }.to_str() {
+                Ok(s) => (!s.is_empty()).then(|| s.to_string()),
+                Err(_) => return false,
+            }
+        };
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.initial_prompt = prompt;
+            })
+            .is_ok()
+    })
+}
+
+/// Drop segments whose mean token log probability is below `threshold`
+/// 
+/// Filters whisper's low-confidence output (typically hallucinations during
+/// silence) before segments are joined into the returned text. A typical
+/// threshold is around -1.0. Pass a NaN threshold to disable filtering.
+#[no_mangle]
+pub extern "C" fn whisper_set_min_confidence(handle: *mut WhisperHandle, threshold: c_float) -> bool {
+    ffi_guard("whisper_set_min_confidence", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let min_avg_logprob = (!threshold.is_nan()).then_some(threshold);
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.min_avg_logprob = min_avg_logprob;
+            })
+            .is_ok()
+    })
+}
+
+/// `whisper_set_audio_check` mode: skip the range check
+pub const WHISPER_AUDIO_CHECK_OFF: i32 = 0;
+/// `whisper_set_audio_check` mode: log a warning and transcribe anyway
+pub const WHISPER_AUDIO_CHECK_WARN: i32 = 1;
+/// `whisper_set_audio_check` mode: fail with `InvalidAudio`
+pub const WHISPER_AUDIO_CHECK_REJECT: i32 = 2;
+
+/// Choose how to handle input that doesn't look like normalized f32 samples
+/// 
+/// Audio is expected as 16kHz mono f32 within [-1, 1]. When more than 5% of
+/// the samples fall outside that range (or are NaN), the buffer is likely
+/// integer PCM passed as floats. By default a warning is logged and the
+/// transcription proceeds.
+#[no_mangle]
+pub extern "C" fn whisper_set_audio_check(handle: *mut WhisperHandle, mode: i32) -> bool {
+    ffi_guard("whisper_set_audio_check", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let Some(check) = AudioCheck::from_ffi(mode) else {
+            return false;
+        };
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.audio_check = check;
+            })
+            .is_ok()
+    })
+}
+
+/// `whisper_set_core_preference` value: performance cores for single calls, efficiency cores for batches
+pub const WHISPER_CORES_AUTO: i32 = 0;
+/// `whisper_set_core_preference` value: performance cores, for the lowest latency
+pub const WHISPER_CORES_PERFORMANCE: i32 = 1;
+/// `whisper_set_core_preference` value: efficiency cores, to save battery
+pub const WHISPER_CORES_EFFICIENCY: i32 = 2;
+
+/// Choose which kind of core inference runs on
+/// 
+/// The preference caps the model's tuned thread count at the number of
+/// cores of that kind, and on Apple platforms sets the QoS class of the
+/// calling thread for the duration of inference, which whisper's worker
+/// threads inherit. By default (`WHISPER_CORES_AUTO`) single transcriptions
+/// use the performance cores for dictation latency, while
+/// `whisper_transcribe_batch` and `whisper_transcribe_long` use the
+/// efficiency cores. A thread count passed to `whisper_transcribe_params`
+/// still takes precedence over the derived one.
+#[no_mangle]
+pub extern "C" fn whisper_set_core_preference(handle: *mut WhisperHandle, preference: i32) -> bool {
+    ffi_guard("whisper_set_core_preference", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let Some(preference) = CorePreference::from_ffi(preference) else {
+            return false;
+        };
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.core_preference = preference;
+            })
+            .is_ok()
+    })
+}
+
+/// Enable a best-effort check that input audio really is 16kHz
+/// 
+/// When enabled, each transcription inspects the spectrum for a cliff at the
+/// Nyquist frequency of a lower sample rate (e.g. 8kHz audio upsampled or
+/// mislabeled) and logs a warning. Transcription proceeds either way. Off by
+/// default because heavily filtered audio can trigger false positives.
+#[no_mangle]
+pub extern "C" fn whisper_set_sample_rate_check(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_sample_rate_check", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.check_sample_rate = enabled;
+            })
+            .is_ok()
+    })
+}
+
+/// Trim leading and trailing silence before transcription
+/// 
+/// Uses an energy-based detector and keeps 100ms of audio before the first
+/// speech frame so quiet word onsets aren't clipped. Returned timestamps
+/// stay relative to the untrimmed buffer. Audio with no detected speech
+/// yields an empty transcription without running inference.
+#[no_mangle]
+pub extern "C" fn whisper_set_vad(handle: *mut WhisperHandle, enabled: bool) -> bool {
+    ffi_guard("whisper_set_vad", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                model.settings.vad = enabled.then(VadConfig::default);
+            })
+            .is_ok()
+    })
+}
+
+/// Transcribe audio data into timed segments with confidence estimates
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - the returned array must be released with whisper_free_array
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_segments(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+) -> WhisperArray {
+    ffi_guard("whisper_transcribe_segments", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperArray::error(WHISPER_ARRAY_SEGMENTS, WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        match WHISPER_MANAGER.transcribe_segments_with(&handle_ref.model_id, audio_slice, |_| {}) {
+            Ok(decoded) => segment_array(decoded),
+            Err(e) => WhisperArray::error(WHISPER_ARRAY_SEGMENTS, e.code, &e.message),
+        }
+    })
+}
+
+/// Transcribe long audio as overlapping chunks, stitched into one segment list
+/// 
+/// The buffer is split into `chunk_secs` windows, each starting
+/// `overlap_secs` before the previous one ended, and transcribed in order.
+/// Segment times are relative to the start of the whole buffer, and words
+/// repeated across an overlap are kept only once. `overlap_secs` must be
+/// smaller than `chunk_secs`; whisper decodes at most 30s at a time, so
+/// chunks longer than that gain nothing.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - the returned array must be released with whisper_free_array
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_long(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    chunk_secs: u32,
+    overlap_secs: u32,
+) -> WhisperArray {
+    ffi_guard("whisper_transcribe_long", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || chunk_secs == 0 || overlap_secs >= chunk_secs || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperArray::error(WHISPER_ARRAY_SEGMENTS, WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let chunk_samples = chunk_secs as usize * WHISPER_SAMPLE_RATE;
+        let overlap_samples = overlap_secs as usize * WHISPER_SAMPLE_RATE;
+        match WHISPER_MANAGER.transcribe_long(&handle_ref.model_id, audio_slice, chunk_samples, overlap_samples) {
+            Ok(decoded) => segment_array(decoded),
+            Err(e) => WhisperArray::error(WHISPER_ARRAY_SEGMENTS, e.code, &e.message),
+        }
+    })
+}
+
+/// Convert decoded segments into an FFI array
+fn segment_array(decoded: Decoded) -> WhisperArray {
+    let (joined, ranges) = decoded.text_with_ranges();
+    let Ok(joined) = CString::new(joined) else {
+        return WhisperArray::error(WHISPER_ARRAY_SEGMENTS, WhisperError::ConversionFailed, "Failed to convert text");
+    };
+    let mut ffi_segments = Vec::with_capacity(decoded.segments.len());
+    for (segment, range) in decoded.segments.into_iter().zip(ranges) {
+        let Some((byte_start, byte_end)) = byte_range_u32(&range) else {
+            free_segment_texts(&ffi_segments);
+            return WhisperArray::error(WHISPER_ARRAY_SEGMENTS, WhisperError::ConversionFailed, "Segment text offset exceeds 4 GiB");
+        };
+        let text = match CString::new(segment.text) {
+            Ok(c_string) => c_string.into_raw(),
+            Err(_) => {
+                free_segment_texts(&ffi_segments);
+                return WhisperArray::error(WHISPER_ARRAY_SEGMENTS, WhisperError::ConversionFailed, "Failed to convert segment text");
+            }
+        };
+        ffi_segments.push(WhisperSegment {
+            text,
+            start_ms: segment.start_ms,
+            end_ms: segment.end_ms,
+            avg_logprob: segment.avg_logprob,
+            no_speech_prob: segment.no_speech_prob,
+            starts_new_turn: segment.starts_new_turn,
+            byte_start,
+            byte_end,
+            language: language_string(segment.language),
+        });
+    }
+    
+    WhisperArray {
+        detected_lang: language_string(decoded.language),
+        text: joined.into_raw(),
+        ..WhisperArray::new(ffi_segments)
+    }
+}
+
+/// A byte range as the header's `uint32_t` offsets, if both fit
+fn byte_range_u32(range: &Range<usize>) -> Option<(u32, u32)> {
+    Some((u32::try_from(range.start).ok()?, u32::try_from(range.end).ok()?))
+}
+
+fn free_segment_texts(segments: &[WhisperSegment]) {
+    for segment in segments {
+        whisper_free_string(segment.text);
+        whisper_free_string(segment.language);
+    }
+}
+
+/// Transcribe audio data into words with timestamps
+/// 
+/// Enables whisper's token timestamps and merges subword tokens into whole
+/// words. Times are relative to the start of the audio buffer.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - the returned array must be released with whisper_free_array
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_words(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+) -> WhisperArray {
+    ffi_guard("whisper_transcribe_words", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperArray::error(WHISPER_ARRAY_WORDS, WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let segments = match WHISPER_MANAGER.transcribe_segments_with(&handle_ref.model_id, audio_slice, |params| {
+            params.set_token_timestamps(true);
+        }) {
+            Ok(decoded) => decoded.segments,
+            Err(e) => return WhisperArray::error(WHISPER_ARRAY_WORDS, e.code, &e.message),
+        };
+        
+        match words_to_ffi(&segments) {
+            Some(ffi_words) => WhisperArray::new(ffi_words),
+            None => WhisperArray::error(WHISPER_ARRAY_WORDS, WhisperError::ConversionFailed, "Failed to convert word text"),
+        }
+    })
+}
+
+/// Merge every segment's tokens into FFI words, or None if a word's text can't be converted
+fn words_to_ffi(segments: &[DecodedSegment]) -> Option<Vec<WhisperWord>> {
+    let mut ffi_words = Vec::new();
+    for word in segments.iter().flat_map(|segment| merge_words(&segment.tokens)) {
+        let Ok(text) = CString::new(word.text) else {
+            free_word_texts(&ffi_words);
+            return None;
+        };
+        ffi_words.push(WhisperWord {
+            text: text.into_raw(),
+            start_ms: word.start_ms,
+            end_ms: word.end_ms,
+            probability: word.probability,
+            t_dtw: word.dtw_ms.unwrap_or(-1),
+        });
+    }
+    Some(ffi_words)
+}
+
+/// `whisper_set_dtw` preset: no DTW timestamps, the default
+pub const WHISPER_DTW_OFF: i32 = 0;
+/// `whisper_set_dtw` preset: the heads matching the model's file name
+pub const WHISPER_DTW_AUTO: i32 = 1;
+pub const WHISPER_DTW_TINY_EN: i32 = 2;
+pub const WHISPER_DTW_TINY: i32 = 3;
+pub const WHISPER_DTW_BASE_EN: i32 = 4;
+pub const WHISPER_DTW_BASE: i32 = 5;
+pub const WHISPER_DTW_SMALL_EN: i32 = 6;
+pub const WHISPER_DTW_SMALL: i32 = 7;
+pub const WHISPER_DTW_MEDIUM_EN: i32 = 8;
+pub const WHISPER_DTW_MEDIUM: i32 = 9;
+pub const WHISPER_DTW_LARGE_V1: i32 = 10;
+pub const WHISPER_DTW_LARGE_V2: i32 = 11;
+pub const WHISPER_DTW_LARGE_V3: i32 = 12;
+pub const WHISPER_DTW_LARGE_V3_TURBO: i32 = 13;
+
+/// Align tokens to the audio with DTW, filling `t_dtw` in tokens and words
+/// 
+/// DTW follows the cross-attention of a few alignment heads, which differ
+/// per released model: each `WHISPER_DTW_*` preset names the model it is
+/// for, `.en` presets being the English-only models. `WHISPER_DTW_AUTO`
+/// picks one from the file name: tiny, base, small and medium, with `.en`
+/// for English-only, and large-v1, -v2, -v3 and -v3-turbo. It fails for a
+/// plain "large" or an unrecognized name, since heads from another model
+/// give meaningless times. Fine-tuned models keep their base model's heads.
+/// 
+/// The heads are part of the loaded context, so a loaded model is reloaded
+/// on its next transcription, and DTW keeps about 128MB of extra working
+/// memory. Times are more precise than the default token timestamps, e.g.
+/// for karaoke-style highlighting. `WHISPER_DTW_OFF` turns it off again.
+#[no_mangle]
+pub extern "C" fn whisper_set_dtw(handle: *mut WhisperHandle, preset: i32) -> bool {
+    ffi_guard("whisper_set_dtw", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| {
+                let dtw = match preset {
+                    WHISPER_DTW_OFF => None,
+                    WHISPER_DTW_AUTO => match DtwPreset::for_model(&model.model_info) {
+                        Some(dtw) => Some(dtw),
+                        None => return false,
+                    },
+                    preset => match DtwPreset::from_ffi(preset) {
+                        Some(dtw) => Some(dtw),
+                        None => return false,
+                    },
+                };
+                model.settings.dtw = dtw;
+                true
+            })
+            .unwrap_or(false)
+    })
+}
+
+fn free_word_texts(words: &[WhisperWord]) {
+    for word in words {
+        whisper_free_string(word.text);
+    }
+}
+
+/// Transcribe audio data into the raw token stream of every segment
+/// 
+/// Special tokens are included and flagged with `is_special`, so callers
+/// doing their own alignment can keep or skip them.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - the returned array must be released with whisper_free_array
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_tokens(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+) -> WhisperArray {
+    ffi_guard("whisper_transcribe_tokens", || {
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperArray::error(WHISPER_ARRAY_TOKENS, WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let segments = match WHISPER_MANAGER.transcribe_segments_with(&handle_ref.model_id, audio_slice, |_| {}) {
+            Ok(decoded) => decoded.segments,
+            Err(e) => return WhisperArray::error(WHISPER_ARRAY_TOKENS, e.code, &e.message),
+        };
+        
+        match tokens_to_ffi(&segments) {
+            Some(tokens) => WhisperArray::new(tokens),
+            None => WhisperArray::error(WHISPER_ARRAY_TOKENS, WhisperError::ConversionFailed, "Failed to convert token text"),
+        }
+    })
+}
+
+/// Copy every segment's tokens into FFI form, or None if a token's text can't be converted
+fn tokens_to_ffi(segments: &[DecodedSegment]) -> Option<Vec<WhisperTokenInfo>> {
+    let mut ffi_tokens = Vec::new();
+    for token in segments.iter().flat_map(|segment| &segment.tokens) {
+        let Ok(text) = CString::new(token.bytes.clone()) else {
+            free_token_texts(&ffi_tokens);
+            return None;
+        };
+        ffi_tokens.push(WhisperTokenInfo {
+            id: token.id,
+            text: text.into_raw(),
+            p: token.p,
+            t_dtw: token.dtw_ms.unwrap_or(-1),
+            is_special: token.is_special,
+        });
+    }
+    Some(ffi_tokens)
+}
+
+fn free_token_texts(tokens: &[WhisperTokenInfo]) {
+    for token in tokens {
+        whisper_free_string(token.text);
+    }
+}
+
+/// Transcribe audio data, writing each segment to `fd` as a line of JSON
+/// 
+/// Every line is one object with the schema
+/// `{"text": string, "start_ms": int, "end_ms": int, "confidence": float}`,
+/// where `confidence` is the mean token probability (0-1). Lines are written
+/// once decoding finishes. The descriptor is not closed. The full text is
+/// still returned in the `WhisperResult`.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - fd must be an open, writable file descriptor
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_jsonl_to_fd(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    fd: i32,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_jsonl_to_fd", || {
+        use std::io::Write;
+        use std::os::unix::io::FromRawFd;
+        
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || fd < 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        let result = WHISPER_MANAGER
+            .transcribe_segments_with(&handle_ref.model_id, audio_slice, |_| {})
+            .and_then(|decoded| {
+                // The caller owns the descriptor, so never let the File close it
+                let mut output = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+                for segment in &decoded.segments {
+                    writeln!(output, "{}", segment_jsonl(segment)).map_err(jsonl_write_error)?;
+                }
+                output.flush().map_err(jsonl_write_error)?;
+                Ok(Transcript::from_decoded(&decoded))
+            });
+        
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Transcribe audio data into a single JSON document (caller must free result)
+/// 
+/// On success the document has the schema
+/// `{"version": 1, "text": string, "language": string|null,
+///   "segments": [{"start": int, "end": int, "text": string}], "inference_ms": int}`
+/// with segment times in milliseconds. On failure it is
+/// `{"version": 1, "error": {"code": int, "message": string}}`, where `code`
+/// is a `WhisperError`. `version` only changes when existing fields change
+/// meaning or are removed; new fields may appear without a bump. Returns
+/// NULL only if the document can't be allocated as a C string.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - the returned string must be released with whisper_free_string
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_json(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+) -> *mut c_char {
+    ffi_guard("whisper_transcribe_json", || {
+        let json = if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            error_json(WhisperError::InvalidParams, "Invalid parameters")
+        } else {
+            let start_time = Instant::now();
+            let handle_ref = unsafe { &*handle };
+            let audio_slice = unsafe {
+                std::slice::from_raw_parts(audio_data, audio_len)
+            };
+            
+            match WHISPER_MANAGER.transcribe_segments_with(&handle_ref.model_id, audio_slice, |_| {}) {
+                Ok(decoded) => transcript_json(&decoded, start_time.elapsed()),
+                Err(e) => error_json(e.code, &e.message),
+            }
+        };
+        
+        match CString::new(json) {
+            Ok(c_string) => c_string.into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+#[cfg(unix)]
+fn jsonl_write_error(e: std::io::Error) -> ManagerError {
+    ManagerError::new(WhisperError::ConversionFailed, format!("Failed to write JSONL output: {}", e))
+}
+
+/// Transcribe several audio buffers with one model lock acquisition
+/// 
+/// The model is loaded once and the buffers are decoded in order. Results
+/// line up with the input buffers; a buffer that fails carries its own
+/// error in its entry while the others remain valid. `success` is false only
+/// when the whole batch could not run (invalid parameters, unknown model).
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - buffers and lengths must each point to `count` elements
+/// - each buffer must point to valid f32 audio samples of the matching length
+/// - the returned array must be released with whisper_free_array
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_batch(
+    handle: *mut WhisperHandle,
+    buffers: *const *const c_float,
+    lengths: *const size_t,
+    count: size_t,
+) -> WhisperArray {
+    ffi_guard("whisper_transcribe_batch", || {
+        if handle.is_null() || buffers.is_null() || lengths.is_null() || count == 0 {
+            return WhisperArray::error(WHISPER_ARRAY_RESULTS, WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let (buffers, lengths) = unsafe {
+            (std::slice::from_raw_parts(buffers, count), std::slice::from_raw_parts(lengths, count))
+        };
+        
+        // Invalid buffers get an error entry rather than failing the batch
+        let valid: Vec<bool> = buffers
+            .iter()
+            .zip(lengths)
+            .map(|(buffer, &len)| !buffer.is_null() && len > 0 && !exceeds_audio_len_ceiling(len))
+            .collect();
+        let slices: Vec<&[f32]> = buffers
+            .iter()
+            .zip(lengths)
+            .zip(&valid)
+            .filter(|(_, &ok)| ok)
+            .map(|((&buffer, &len), _)| unsafe { std::slice::from_raw_parts(buffer, len) })
+            .collect();
+        
+        let mut outcomes = match WHISPER_MANAGER.transcribe_batch(&handle_ref.model_id, &slices) {
+            Ok(outcomes) => outcomes.into_iter(),
+            Err(e) => return WhisperArray::error(WHISPER_ARRAY_RESULTS, e.code, &e.message),
+        };
+        
+        let results: Vec<WhisperResult> = valid
+            .iter()
+            .zip(lengths)
+            .map(|(&ok, &len)| match ok.then(|| outcomes.next()).flatten() {
+                Some((result, inference)) => transcription_result(result, inference, len),
+                None => WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters"),
+            })
+            .collect();
+        WhisperArray::new(results)
+    })
+}
+
+fn free_result_strings(results: &[WhisperResult]) {
+    for result in results {
+        whisper_free_string(result.text);
+        whisper_free_string(result.error);
+        whisper_free_string(result.detected_lang);
+        whisper_free_string(result.warning);
+    }
+}
+
+/// Free an array returned by any call, with its elements and every string they and it own
+/// 
+/// Dispatches on `kind`, so each element type is freed as itself. An
+/// unknown kind is logged and its elements leaked rather than freed as the
+/// wrong type.
+/// 
+/// # Safety
+/// - array must come from this library and not be freed twice
+#[no_mangle]
+pub extern "C" fn whisper_free_array(array: WhisperArray) {
+    ffi_guard("whisper_free_array", || {
+        /// # Safety
+        /// `ptr` must come from `WhisperArray::new` with `len` `T`s that nothing else frees
+        unsafe fn free_elements<T: ArrayElement>(ptr: *mut c_void, len: size_t) {
+            let elements = Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr.cast::<T>(), len));
+            T::free_strings(&elements);
+        }
+        
+        if !array.ptr.is_null() {
+            match array.kind {
+                WHISPER_ARRAY_RESULTS => unsafe { free_elements::<WhisperResult>(array.ptr, array.len) },
+                WHISPER_ARRAY_SEGMENTS => unsafe { free_elements::<WhisperSegment>(array.ptr, array.len) },
+                WHISPER_ARRAY_WORDS => unsafe { free_elements::<WhisperWord>(array.ptr, array.len) },
+                WHISPER_ARRAY_TOKENS => unsafe { free_elements::<WhisperTokenInfo>(array.ptr, array.len) },
+                kind => logging::warn(&format!("whisper_free_array: unknown array kind {}, elements not freed", kind)),
+            }
+        }
+        whisper_free_string(array.error);
+        whisper_free_string(array.detected_lang);
+        whisper_free_string(array.text);
+    })
+}
+
+/// Callback receiving each new segment's text during streaming transcription
+pub type WhisperSegmentCallback = extern "C" fn(text: *const c_char, user_data: *mut c_void);
+
+/// Transcribe audio data, reporting each segment as whisper produces it
+/// 
+/// The callback runs on the calling thread before this function returns.
+/// The full text is still returned in the `WhisperResult`.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+/// - the text pointer passed to the callback is only valid for the duration
+///   of that callback invocation; copy it before returning
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_streaming(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    callback: Option<WhisperSegmentCallback>,
+    user_data: *mut c_void,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_streaming", || {
+        let callback = match callback {
+            Some(callback) => callback,
+            None => return whisper_transcribe(handle, audio_data, audio_len),
+        };
+        
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        // whisper-rs requires a 'static closure, so carry the pointer as an address
+        let user_data_addr = user_data as usize;
+        let result = WHISPER_MANAGER.transcribe_with(&handle_ref.model_id, audio_slice, |params| {
+            params.set_segment_callback_safe_lossy(move |segment: SegmentCallbackData| {
+                let Ok(text) = CString::new(segment.text) else {
+                    return;
+                };
+                
+                // The callback is invoked from inside whisper.cpp, never let a panic unwind into it
+                let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                    callback(text.as_ptr(), user_data_addr as *mut c_void);
+                }));
+                if outcome.is_err() {
+                    logging::warn("Segment callback panicked, ignoring");
+                }
+            });
+        });
+        
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Passes a word must read the same in before `whisper_stream_feed` commits it, by default
+const DEFAULT_STREAM_PASSES: usize = 2;
+
+/// Opaque live transcription session for FFI, see `whisper_stream_create`
+pub struct WhisperStream {
+    model_id: String,
+    session: Mutex<stream::StreamSession>,
+    callback: WhisperSegmentCallback,
+    user_data: usize, // caller's pointer, only handed back to the callback
+}
+
+/// Start a live session that reports words once they stop changing
+/// 
+/// Each `whisper_stream_feed` re-transcribes all audio fed so far. A word is
+/// committed, and passed to `callback` once, after reading the same in
+/// `passes` consecutive feeds (0 uses 2); words after it stay provisional
+/// and may still change. Committed words split on whitespace, so scripts
+/// written without spaces commit a whole run at a time. Returns NULL for a
+/// null handle or callback; release with `whisper_stream_free`.
+#[no_mangle]
+pub extern "C" fn whisper_stream_create(
+    handle: *mut WhisperHandle,
+    passes: u32,
+    callback: Option<WhisperSegmentCallback>,
+    user_data: *mut c_void,
+) -> *mut WhisperStream {
+    ffi_guard("whisper_stream_create", || {
+        let Some(callback) = callback.filter(|_| !handle.is_null()) else {
+            return std::ptr::null_mut();
+        };
+        
+        let handle_ref = unsafe { &*handle };
+        let passes = if passes == 0 { DEFAULT_STREAM_PASSES } else { passes as usize };
+        Box::into_raw(Box::new(WhisperStream {
+            model_id: handle_ref.model_id.clone(),
+            session: Mutex::new(stream::StreamSession::new(passes)),
+            callback,
+            user_data: user_data as usize,
+        }))
+    })
+}
+
+/// Append audio to a live session and re-transcribe everything fed so far
+/// 
+/// Words this pass commits are passed to the callback, space-separated,
+/// before this returns. The result holds the full current text, committed
+/// and provisional. A session holds at most ten minutes of audio; feeding
+/// past that fails with `WHISPER_ERROR_INVALID_PARAMS`.
+/// 
+/// # Safety
+/// - stream must be a valid pointer returned by whisper_stream_create
+/// - samples must point to `len` valid f32 samples
+/// - the text pointer passed to the callback is only valid for the duration
+///   of that callback invocation; copy it before returning
+#[no_mangle]
+pub extern "C" fn whisper_stream_feed(stream: *mut WhisperStream, samples: *const c_float, len: size_t) -> WhisperResult {
+    ffi_guard("whisper_stream_feed", || {
+        if stream.is_null() || samples.is_null() || len == 0 || exceeds_audio_len_ceiling(len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let stream_ref = unsafe { &*stream };
+        let samples = unsafe { std::slice::from_raw_parts(samples, len) };
+        let mut session = match stream_ref.session.lock() {
+            Ok(session) => session,
+            Err(_) => {
+                let e = ManagerError::lock("stream");
+                return WhisperResult::error(e.code, &e.message);
+            }
+        };
+        if session.is_finished() {
+            return WhisperResult::error(WhisperError::InvalidParams, "Stream is already finished");
+        }
+        if session.audio().len() + len > MAX_BUFFER_SAMPLES {
+            return WhisperResult::error(WhisperError::InvalidParams, "Stream holds more than ten minutes of audio");
+        }
+        session.push_audio(samples);
+        
+        let start_time = Instant::now();
+        let result = WHISPER_MANAGER.transcribe(&stream_ref.model_id, session.audio());
+        if let Ok(transcript) = &result {
+            stream_ref.emit(&session.update(&transcript.text));
+        }
+        transcription_result(result, start_time.elapsed(), session.audio().len())
+    })
+}
+
+impl WhisperStream {
+    /// Pass newly committed words to the callback, space-separated, if there are any
+    fn emit(&self, committed: &[String]) {
+        if committed.is_empty() {
+            return;
+        }
+        if let Ok(text) = CString::new(committed.join(" ")) {
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                (self.callback)(text.as_ptr(), self.user_data as *mut c_void);
+            }));
+            if outcome.is_err() {
+                logging::warn("Stream callback panicked, ignoring");
+            }
+        }
+    }
+}
+
+/// Transcribe all audio of a live session once more and end it
+/// 
+/// For when the speaker stops, e.g. on push-to-talk release. The final
+/// pass sees the complete audio, so it may read differently from the last
+/// feed's provisional words; committed words stand and the final pass
+/// supplies the rest, which are passed to the callback as newly committed.
+/// The result's text is the whole utterance, committed plus final words.
+/// 
+/// The session's audio is then released, and feeding or finishing it again
+/// fails with `WHISPER_ERROR_INVALID_PARAMS`; it still needs
+/// `whisper_stream_free`. A session that was never fed finishes with empty
+/// text. If the final pass fails, the session is left as it was so
+/// finishing can be retried.
+/// 
+/// # Safety
+/// - stream must be a valid pointer returned by whisper_stream_create
+#[no_mangle]
+pub extern "C" fn whisper_stream_finish(stream: *mut WhisperStream) -> WhisperResult {
+    ffi_guard("whisper_stream_finish", || {
+        if stream.is_null() {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let stream_ref = unsafe { &*stream };
+        let mut session = match stream_ref.session.lock() {
+            Ok(session) => session,
+            Err(_) => {
+                let e = ManagerError::lock("stream");
+                return WhisperResult::error(e.code, &e.message);
+            }
+        };
+        if session.is_finished() {
+            return WhisperResult::error(WhisperError::InvalidParams, "Stream is already finished");
+        }
+        
+        let start_time = Instant::now();
+        let audio_len = session.audio().len();
+        let result = if audio_len == 0 {
+            Ok(Transcript {
+                text: String::new(),
+                segment_count: 0,
+                language: None,
+                non_finite: 0,
+                fallbacks: Fallbacks::default(),
+                no_speech: true,
+            })
+        } else {
+            WHISPER_MANAGER.transcribe(&stream_ref.model_id, session.audio())
+        };
+        let result = result.map(|transcript| {
+            stream_ref.emit(&session.finish(&transcript.text));
+            let no_speech = transcript.no_speech && session.committed().is_empty();
+            Transcript { text: session.committed().join(" "), no_speech, ..transcript }
+        });
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Free a session created with `whisper_stream_create`, without emitting further words
+#[no_mangle]
+pub extern "C" fn whisper_stream_free(stream: *mut WhisperStream) {
+    ffi_guard("whisper_stream_free", || {
+        if !stream.is_null() {
+            unsafe {
+                drop(Box::from_raw(stream));
+            }
+        }
+    })
+}
+
+/// Callback receiving log messages; `msg` is only valid during the call
+pub type WhisperLogCallback = logging::LogCallback;
+
+/// Route library and whisper.cpp log messages to a callback
+/// 
+/// `level` is one of the `WHISPER_LOG_LEVEL_*` values. Messages go to
+/// stderr while no callback is installed; pass null to restore that. The
+/// callback may be invoked from any thread, including whisper.cpp's
+/// worker threads, so it must be thread-safe.
+#[no_mangle]
+pub extern "C" fn whisper_set_log_callback(callback: Option<WhisperLogCallback>, user_data: *mut c_void) {
+    ffi_guard("whisper_set_log_callback", || {
+        logging::set_callback(callback, user_data);
+    })
+}
+
+/// Remove the handle's model from the manager and release its context
+/// 
+/// Handles created from the same model path share a registration, so this
+/// affects all of them. The handle itself must still be released with
+/// whisper_free. Blocks until any in-flight transcription on the model ends.
+#[no_mangle]
+pub extern "C" fn whisper_unregister(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_unregister", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER.unregister(&handle_ref.model_id).is_ok()
+    })
+}
+
+/// Cancel the transcription currently running on the handle's model
+/// 
+/// The running call returns promptly with `WHISPER_ERROR_CANCELLED`. Safe to
+/// call from any thread and while no transcription is running, in which
+/// case it does nothing: the next transcription starts with the flag cleared.
+/// Returns false if the handle is null or its model isn't registered.
+#[no_mangle]
+pub extern "C" fn whisper_abort(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_abort", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER.abort(&handle_ref.model_id).is_ok()
+    })
+}
+
+/// Callback receiving transcription progress in percent (0-100)
+pub type WhisperProgressCallback = extern "C" fn(progress: i32, user_data: *mut c_void);
+
+/// Transcribe audio data, reporting whisper's progress through a callback
+/// 
+/// The callback runs on the calling thread before this function returns.
+/// Progress restarts from 0 if a low-confidence beam search retry runs.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - audio_data must point to valid f32 audio samples
+/// - audio_len must accurately represent the length of audio_data
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_with_progress(
+    handle: *mut WhisperHandle,
+    audio_data: *const c_float,
+    audio_len: size_t,
+    progress_cb: Option<WhisperProgressCallback>,
+    user_data: *mut c_void,
+) -> WhisperResult {
+    ffi_guard("whisper_transcribe_with_progress", || {
+        let progress_cb = match progress_cb {
+            Some(progress_cb) => progress_cb,
+            None => return whisper_transcribe(handle, audio_data, audio_len),
+        };
+        
+        if handle.is_null() || audio_data.is_null() || audio_len == 0 || exceeds_audio_len_ceiling(audio_len) {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let start_time = Instant::now();
+        let handle_ref = unsafe { &*handle };
+        let audio_slice = unsafe {
+            std::slice::from_raw_parts(audio_data, audio_len)
+        };
+        
+        // whisper-rs requires a 'static closure, so carry the pointer as an address
+        let user_data_addr = user_data as usize;
+        let result = WHISPER_MANAGER.transcribe_with(&handle_ref.model_id, audio_slice, |params| {
+            params.set_progress_callback_safe(move |progress: i32| {
+                // The callback is invoked from inside whisper.cpp, never let a panic unwind into it
+                let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                    progress_cb(progress.clamp(0, 100), user_data_addr as *mut c_void);
+                }));
+                if outcome.is_err() {
+                    logging::warn("Progress callback panicked, ignoring");
+                }
+            });
+        });
+        
+        transcription_result(result, start_time.elapsed(), audio_len)
+    })
+}
+
+/// Create a buffer holding the last `capacity_samples` of 16kHz mono audio
+/// 
+/// Returns NULL for a zero capacity or one above ten minutes of audio.
+/// Release with `whisper_buffer_free`.
+#[no_mangle]
+pub extern "C" fn whisper_buffer_create(capacity_samples: size_t) -> *mut WhisperBuffer {
+    ffi_guard("whisper_buffer_create", || {
+        if capacity_samples == 0 || capacity_samples > MAX_BUFFER_SAMPLES {
+            return std::ptr::null_mut();
+        }
+        Box::into_raw(Box::new(WhisperBuffer {
+            ring: Mutex::new(ring::RingBuffer::new(capacity_samples)),
+        }))
+    })
+}
+
+/// Append samples to a buffer, overwriting the oldest once it's full
+/// 
+/// Safe to call while another thread transcribes the same buffer.
+/// 
+/// # Safety
+/// - buffer must be a valid pointer returned by whisper_buffer_create
+/// - samples must point to `len` valid f32 samples
+#[no_mangle]
+pub extern "C" fn whisper_buffer_push(buffer: *mut WhisperBuffer, samples: *const c_float, len: size_t) -> bool {
+    ffi_guard("whisper_buffer_push", || {
+        if buffer.is_null() || samples.is_null() || exceeds_audio_len_ceiling(len) {
+            return false;
+        }
+        
+        let buffer_ref = unsafe { &*buffer };
+        let samples = unsafe { std::slice::from_raw_parts(samples, len) };
+        match buffer_ref.ring.lock() {
+            Ok(mut ring) => {
+                ring.push(samples);
+                true
+            }
+            Err(_) => false,
+        }
+    })
+}
+
+/// Transcribe a buffer's current contents, oldest sample first
+/// 
+/// The contents are copied out before inference, so pushes carry on while
+/// it runs and only affect the next call. An empty buffer is
+/// `WHISPER_ERROR_INVALID_PARAMS`.
+/// 
+/// # Safety
+/// - handle must be a valid pointer returned by whisper_init
+/// - buffer must be a valid pointer returned by whisper_buffer_create
+#[no_mangle]
+pub extern "C" fn whisper_transcribe_buffer(handle: *mut WhisperHandle, buffer: *mut WhisperBuffer) -> WhisperResult {
+    ffi_guard("whisper_transcribe_buffer", || {
+        if handle.is_null() || buffer.is_null() {
+            return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters");
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        let buffer_ref = unsafe { &*buffer };
+        let window = match buffer_ref.ring.lock() {
+            Ok(ring) if ring.is_empty() => return WhisperResult::error(WhisperError::InvalidParams, "Invalid parameters"),
+            Ok(ring) => ring.to_vec(),
+            Err(_) => {
+                let e = ManagerError::lock("buffer");
+                return WhisperResult::error(e.code, &e.message);
+            }
+        };
+        
+        let start_time = Instant::now();
+        let result = WHISPER_MANAGER.transcribe(&handle_ref.model_id, &window);
+        transcription_result(result, start_time.elapsed(), window.len())
+    })
+}
+
+/// Free a buffer created with `whisper_buffer_create`
+#[no_mangle]
+pub extern "C" fn whisper_buffer_free(buffer: *mut WhisperBuffer) {
+    ffi_guard("whisper_buffer_free", || {
+        if !buffer.is_null() {
+            unsafe {
+                drop(Box::from_raw(buffer));
+            }
+        }
+    })
+}
+
+/// Free whisper context
+#[no_mangle]
+pub extern "C" fn whisper_free(handle: *mut WhisperHandle) {
+    ffi_guard("whisper_free", || {
+        if !handle.is_null() {
+            unsafe {
+                drop(Box::from_raw(handle));
+            }
+        }
+    })
+}
+
+/// Free result strings
+#[no_mangle]
+pub extern "C" fn whisper_free_string(ptr: *mut c_char) {
+    ffi_guard("whisper_free_string", || {
+        if !ptr.is_null() {
+            unsafe {
+                drop(CString::from_raw(ptr));
+            }
+        }
+    })
+}
+
+/// Get current memory usage of all loaded models
+/// 
+/// This sums per-model estimates, which is what the memory limit is
+/// enforced against. For the memory the process actually uses, see
+/// `whisper_get_process_rss`.
+#[no_mangle]
+pub extern "C" fn whisper_get_memory_usage() -> u64 {
+    ffi_guard("whisper_get_memory_usage", || {
+        WHISPER_MANAGER.current_memory_usage()
+    })
+}
+
+/// Get the resident memory of the whole process in bytes
+/// 
+/// Unlike `whisper_get_memory_usage` this is measured, so it includes
+/// whisper's working buffers and everything else the host allocates.
+/// Returns 0 on platforms other than macOS and Linux.
+#[no_mangle]
+pub extern "C" fn whisper_get_process_rss() -> u64 {
+    ffi_guard("whisper_get_process_rss", || {
+        process_resident_bytes().unwrap_or(0)
+    })
+}
+
+/// Describe the CPU features and backends whisper.cpp was built with (caller must free result)
+/// 
+/// Returns whisper.cpp's own capability string, e.g. "NEON = 1 | ...", for
+/// bug reports. It describes the library rather than a model; whether a
+/// loaded model actually runs on the GPU is logged when it falls back.
+#[no_mangle]
+pub extern "C" fn whisper_system_info() -> *mut c_char {
+    ffi_guard("whisper_system_info", || {
+        match CString::new(whisper_rs::print_system_info().trim()) {
+            Ok(c_string) => c_string.into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Get the estimated memory footprint of a loaded model (0 if unloaded)
+/// 
+/// Doesn't wait for a running transcription; see `whisper_get_actual_memory`.
+#[no_mangle]
+pub extern "C" fn whisper_get_estimated_memory(handle: *mut WhisperHandle) -> u64 {
+    ffi_guard("whisper_get_estimated_memory", || {
+        if handle.is_null() {
+            return 0;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .model_memory(&handle_ref.model_id)
+            .map_or(0, |(estimated, _)| estimated)
+    })
+}
+
+/// Get the measured memory footprint of a loaded model (0 if unloaded)
+/// 
+/// Measured as resident memory growth while the context loaded. Falls back
+/// to the estimate when the platform doesn't expose resident memory. While
+/// a transcription holds the model, the figure from its last load is
+/// returned rather than waiting.
+#[no_mangle]
+pub extern "C" fn whisper_get_actual_memory(handle: *mut WhisperHandle) -> u64 {
+    ffi_guard("whisper_get_actual_memory", || {
+        if handle.is_null() {
+            return 0;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .model_memory(&handle_ref.model_id)
+            .map_or(0, |(_, actual)| actual)
+    })
+}
+
+/// Load the model now so the first transcription doesn't pay for it
+/// 
+/// Returns false if the model could not be loaded, e.g. a missing or
+/// corrupt model file, so callers can surface the error up front;
+/// `whisper_get_last_error` then has the reason.
+#[no_mangle]
+pub extern "C" fn whisper_preload(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_preload", || {
+        let Some(handle_ref) = (unsafe { handle.as_ref() }) else {
+            return record_outcome("preload model", Err(null_handle_error()));
+        };
+        record_outcome("preload model", WHISPER_MANAGER.preload(&handle_ref.model_id))
+    })
+}
+
+/// `whisper_warm_in_background` priority: below normal work, still making steady progress
+pub const WHISPER_PRIORITY_UTILITY: i32 = 0;
+/// `whisper_warm_in_background` priority: only run when the system is otherwise idle
+pub const WHISPER_PRIORITY_BACKGROUND: i32 = 1;
+
+/// Scheduling priority for background model warming
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WarmPriority {
+    Utility,
+    Background,
+}
+
+impl WarmPriority {
+    fn from_ffi(priority: i32) -> Option<Self> {
+        match priority {
+            WHISPER_PRIORITY_UTILITY => Some(WarmPriority::Utility),
+            WHISPER_PRIORITY_BACKGROUND => Some(WarmPriority::Background),
+            _ => None,
+        }
+    }
+    
+    /// Lower the calling thread to this priority via its QoS class
+    #[cfg(target_vendor = "apple")]
+    fn apply_to_current_thread(self) {
+        let qos = match self {
+            WarmPriority::Utility => libc::qos_class_t::QOS_CLASS_UTILITY,
+            WarmPriority::Background => libc::qos_class_t::QOS_CLASS_BACKGROUND,
+        };
+        if unsafe { libc::pthread_set_qos_class_self_np(qos, 0) } != 0 {
+            logging::warn("Could not lower the warming thread's QoS class");
+        }
+    }
+    
+    /// Lower the calling thread to this priority via its nice value
+    /// 
+    /// Linux keeps a nice value per thread, and `who` 0 is the caller.
+    #[cfg(target_os = "linux")]
+    fn apply_to_current_thread(self) {
+        let nice = match self {
+            WarmPriority::Utility => 10,
+            WarmPriority::Background => 19,
+        };
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+            logging::warn("Could not lower the warming thread's priority");
+        }
+    }
+    
+    #[cfg(not(any(target_vendor = "apple", target_os = "linux")))]
+    fn apply_to_current_thread(self) {}
+}
+
+/// Load the model on a low-priority thread if it fits in memory
+/// 
+/// For models that may be needed soon but aren't selected yet. Unlike
+/// `whisper_preload` this returns immediately, and the model is skipped
+/// rather than loaded when it would only fit by evicting another, so the
+/// model in use stays resident. `priority` is one of the
+/// `WHISPER_PRIORITY_*` constants. Returns false for a null handle, an
+/// unknown priority, or if the thread couldn't be started; whether the
+/// model was warmed is logged, and `whisper_is_loaded` reports it.
+#[no_mangle]
+pub extern "C" fn whisper_warm_in_background(handle: *mut WhisperHandle, priority: i32) -> bool {
+    ffi_guard("whisper_warm_in_background", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let Some(priority) = WarmPriority::from_ffi(priority) else {
+            return false;
+        };
+        
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let spawned = std::thread::Builder::new()
+            .name("whisper-warm".to_string())
+            .spawn(move || {
+                priority.apply_to_current_thread();
+                match WHISPER_MANAGER.warm(&model_id) {
+                    Ok(()) => logging::info(&format!("Warmed model '{}' in the background", model_id)),
+                    Err(e) => logging::info(&format!("Skipped warming model '{}': {}", model_id, e)),
+                }
+            });
+        spawned.is_ok()
+    })
+}
+
+/// Replace the model's context with a freshly loaded one
+/// 
+/// A recovery path when transcriptions keep failing with
+/// `WHISPER_ERROR_INFERENCE_FAILED` and the context may be corrupt, without
+/// unregistering and re-initializing. Transcriptions on the model wait until
+/// the reload finishes. Returns false if the model can't be loaded again,
+/// with the reason in `whisper_get_last_error`; it is then left unloaded
+/// and the next transcription retries the load.
+#[no_mangle]
+pub extern "C" fn whisper_reload(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_reload", || {
+        let Some(handle_ref) = (unsafe { handle.as_ref() }) else {
+            return record_outcome("reload model", Err(null_handle_error()));
+        };
+        record_outcome("reload model", WHISPER_MANAGER.reload(&handle_ref.model_id))
+    })
+}
+
+/// Serve the handle's model from a pool of up to `n` contexts
+/// 
+/// Independent transcriptions on the same model then run in parallel
+/// instead of queueing behind one another. Every context is loaded up
+/// front and counts against the memory limit in full; the pool stops
+/// growing at the first context that doesn't fit. Settings changes apply
+/// to every context. The default is a single context, and 1 restores it.
+/// 
+/// Returns the number of contexts now available, or 0 if `n` is outside
+/// 1-8 or the model itself couldn't be loaded.
+#[no_mangle]
+pub extern "C" fn whisper_set_concurrency(handle: *mut WhisperHandle, n: u32) -> u32 {
+    ffi_guard("whisper_set_concurrency", || {
+        if handle.is_null() {
+            return 0;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        match WHISPER_MANAGER.set_concurrency(&handle_ref.model_id, n as usize) {
+            Ok(size) => size as u32,
+            Err(e) => {
+                logging::warn(&format!("Failed to set concurrency: {}", e));
+                0
+            }
+        }
+    })
+}
+
+/// Whether the model's context is currently loaded in memory
+#[no_mangle]
+pub extern "C" fn whisper_is_loaded(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_is_loaded", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| model.ctx.is_some())
+            .unwrap_or(false)
+    })
+}
+
+/// Whether the model's loaded context runs on the GPU
+/// 
+/// False while the model isn't loaded, and when initializing the GPU
+/// failed and the model fell back to the slower CPU backend.
+#[no_mangle]
+pub extern "C" fn whisper_is_gpu_enabled(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_is_gpu_enabled", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| model.ctx.is_some() && model.gpu_enabled)
+            .unwrap_or(false)
+    })
+}
+
+/// Get the memory cap for the model's size class in bytes (0 if unknown)
+/// 
+/// Loads are refused when a model's estimated footprint is well beyond
+/// this cap, which usually means the model file is mislabeled.
+#[no_mangle]
+pub extern "C" fn whisper_get_model_limit(handle: *mut WhisperHandle) -> u64 {
+    ffi_guard("whisper_get_model_limit", || {
+        if handle.is_null() {
+            return 0;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER
+            .with_model(&handle_ref.model_id, |model| model.model_info.size.memory_limit())
+            .unwrap_or(0)
+    })
+}
+
+/// Force memory cleanup by unloading idle models
+/// 
+/// On false, `whisper_get_last_error` says why.
+#[no_mangle]
+pub extern "C" fn whisper_cleanup_memory() -> bool {
+    ffi_guard("whisper_cleanup_memory", || {
+        record_outcome("clean up memory", WHISPER_MANAGER.manage_memory())
+    })
+}
+
+/// Start a background thread that unloads idle models every `interval_secs`
+/// 
+/// Models past their idle timeout are unloaded even when memory isn't
+/// tight; models in use are skipped until the next sweep. Starting again
+/// replaces the running sweeper. Returns false for a zero interval or if
+/// the thread can't be spawned.
+#[no_mangle]
+pub extern "C" fn whisper_start_idle_sweeper(interval_secs: u64) -> bool {
+    ffi_guard("whisper_start_idle_sweeper", || {
+        if interval_secs == 0 {
+            return false;
+        }
+        
+        whisper_stop_idle_sweeper();
+        let sweeper = match sweeper::IdleSweeper::start(Duration::from_secs(interval_secs), || WHISPER_MANAGER.sweep_idle()) {
+            Ok(sweeper) => sweeper,
+            Err(e) => {
+                logging::warn(&format!("Failed to start idle sweeper: {}", e));
+                return false;
+            }
+        };
+        
+        match IDLE_SWEEPER.lock() {
+            Ok(mut slot) => {
+                // A concurrent start may have won the race; keep only one sweeper
+                if let Some(previous) = slot.replace(sweeper) {
+                    previous.stop();
+                }
+                true
+            }
+            Err(_) => {
+                sweeper.stop();
+                false
+            }
+        }
+    })
+}
+
+/// Mark the start of a recording, pausing the idle sweeper
+/// 
+/// Until `whisper_mark_idle()`, the sweeper leaves every model loaded so
+/// the next transcription doesn't wait for a reload. Memory pressure can
+/// still unload models.
+#[no_mangle]
+pub extern "C" fn whisper_mark_active() {
+    ffi_guard("whisper_mark_active", || {
+        WHISPER_MANAGER.set_recording(true);
+    })
+}
+
+/// Mark the end of a recording, letting the idle sweeper resume
+/// 
+/// Idle timeouts count from this call at the earliest.
+#[no_mangle]
+pub extern "C" fn whisper_mark_idle() {
+    ffi_guard("whisper_mark_idle", || {
+        WHISPER_MANAGER.set_recording(false);
+    })
+}
+
+/// Stop the idle sweeper and wait for its thread to exit (no-op if not running)
+#[no_mangle]
+pub extern "C" fn whisper_stop_idle_sweeper() {
+    ffi_guard("whisper_stop_idle_sweeper", || {
+        let running = IDLE_SWEEPER.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(sweeper) = running {
+            sweeper.stop();
+        }
+    })
+}
+
+/// Set the peak memory budget for loaded models in bytes
+/// 
+/// Returns false for values below the smallest model's footprint (39MB).
+/// Raising the limit lets more models stay resident at once; lowering it
+/// takes effect at the next load or transcription.
+#[no_mangle]
+pub extern "C" fn whisper_set_memory_limit(bytes: u64) -> bool {
+    ffi_guard("whisper_set_memory_limit", || {
+        WHISPER_MANAGER.set_memory_limit(bytes).is_ok()
+    })
+}
+
+/// Get average CPU usage for performance monitoring
+#[no_mangle]
+pub extern "C" fn whisper_get_avg_cpu_usage() -> c_float {
+    ffi_guard("whisper_get_avg_cpu_usage", || {
+        if let Ok(monitor) = WHISPER_MANAGER.cpu_monitor.lock() {
+            monitor.average_cpu_usage()
+        } else {
+            0.0
+        }
+    })
+}
+
+/// Get the text of the most recent transcription (caller must free result)
+/// 
+/// Returns NULL when nothing has been transcribed since startup or the last
+/// `whisper_clear_history()`.
+#[no_mangle]
+pub extern "C" fn whisper_get_last_result() -> *mut c_char {
+    ffi_guard("whisper_get_last_result", || {
+        match WHISPER_MANAGER.recent_history(1).pop() {
+            Some(entry) => match CString::new(entry.text) {
+                Ok(c_string) => c_string.into_raw(),
+                Err(_) => std::ptr::null_mut(),
+            },
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Get up to `n` recent transcriptions as a JSON array, newest first (caller must free result)
+/// 
+/// Each entry is `{"model_id", "text", "timestamp_ms"}`; at most 32 are kept.
+/// A long transcription is one entry, and a batch one entry per buffer.
+#[no_mangle]
+pub extern "C" fn whisper_get_history_json(n: u32) -> *mut c_char {
+    ffi_guard("whisper_get_history_json", || {
+        let entries: Vec<String> = WHISPER_MANAGER.recent_history(n as usize)
+            .iter()
+            .map(HistoryEntry::to_json)
+            .collect();
+        match CString::new(format!("[{}]", entries.join(","))) {
+            Ok(c_string) => c_string.into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Forget the transcription history
+#[no_mangle]
+pub extern "C" fn whisper_clear_history() {
+    ffi_guard("whisper_clear_history", || {
+        WHISPER_MANAGER.clear_history();
+    })
+}
+
+/// Clear the CPU usage history, e.g. between recording sessions
+/// 
+/// Until new transcriptions run, the average reads 0 and no downgrade is
+/// suggested.
+#[no_mangle]
+pub extern "C" fn whisper_reset_cpu_stats() {
+    ffi_guard("whisper_reset_cpu_stats", || {
+        if let Ok(mut monitor) = WHISPER_MANAGER.cpu_monitor.lock() {
+            monitor.reset();
+        }
+    })
+}
+
+/// Get loaded model count, memory and CPU figures in one consistent snapshot
+/// 
+/// All fields are zero if the manager state can't be read.
+#[no_mangle]
+pub extern "C" fn whisper_get_stats() -> WhisperStats {
+    ffi_guard("whisper_get_stats", || {
+        WHISPER_MANAGER.stats().unwrap_or(WhisperStats {
+            loaded_models: 0,
+            total_memory: 0,
+            avg_cpu: 0.0,
+            memory_limit: 0,
+        })
+    })
+}
+
+/// Check if model downgrade is recommended for given model
+#[no_mangle]
+pub extern "C" fn whisper_check_downgrade_needed(handle: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_check_downgrade_needed", || {
+        if handle.is_null() {
+            return false;
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        WHISPER_MANAGER.suggest_model_downgrade(&handle_ref.model_id).is_some()
+    })
+}
+
+/// Suggest another model in place of `from` when CPU usage is high
+/// 
+/// `whisper_get_suggested_model` then returns the path `to` was registered
+/// with. Passing NULL for `to` restores the default large -> medium ->
+/// small -> tiny chain for `from`. Both models must be registered.
+#[no_mangle]
+pub extern "C" fn whisper_set_downgrade_target(from: *mut WhisperHandle, to: *mut WhisperHandle) -> bool {
+    ffi_guard("whisper_set_downgrade_target", || {
+        if from.is_null() {
+            return false;
+        }
+        
+        let from_ref = unsafe { &*from };
+        let to_id = if to.is_null() {
+            None
+        } else {
+            Some(unsafe { &*to }.model_id.as_str())
+        };
+        WHISPER_MANAGER.set_downgrade_target(&from_ref.model_id, to_id).is_ok()
+    })
+}
+
+/// Set the average CPU usage (0-100%) above which a downgrade is suggested
+/// 
+/// Defaults to 80%. Returns false for values outside that range or NaN.
+#[no_mangle]
+pub extern "C" fn whisper_set_downgrade_threshold(percent: c_float) -> bool {
+    ffi_guard("whisper_set_downgrade_threshold", || {
+        WHISPER_MANAGER.set_downgrade_threshold(percent).is_ok()
+    })
+}
+
+/// Get suggested downgrade model name (caller must free result)
+#[no_mangle]
+pub extern "C" fn whisper_get_suggested_model(handle: *mut WhisperHandle) -> *mut c_char {
+    ffi_guard("whisper_get_suggested_model", || {
+        if handle.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        let handle_ref = unsafe { &*handle };
+        if let Some(suggested) = WHISPER_MANAGER.suggest_model_downgrade(&handle_ref.model_id) {
+            match CString::new(suggested) {
+                Ok(c_string) => c_string.into_raw(),
+                Err(_) => std::ptr::null_mut(),
+            }
+        } else {
+            std::ptr::null_mut()
+        }
+    })
+}
+
+/// Quote and escape a string as a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Serialize a segment as one JSONL record
+fn segment_jsonl(segment: &DecodedSegment) -> String {
+    format!(
+        "{{\"text\":{},\"start_ms\":{},\"end_ms\":{},\"confidence\":{}}}",
+        json_string(&segment.text),
+        segment.start_ms,
+        segment.end_ms,
+        segment.confidence,
+    )
+}
+
+/// Architecture read from a loaded model, as opposed to the size guessed from its name
+#[derive(Debug, Clone, PartialEq)]
+struct ModelMetadata {
+    model_type: String, // "tiny", "base", "small", "medium" or "large"
+    multilingual: bool,
+    n_vocab: i32,
+    n_audio_ctx: i32,
+    n_text_ctx: i32,
+    n_mels: i32,
+}
+
+impl ModelMetadata {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"type\":{},\"is_multilingual\":{},\"n_vocab\":{},\"n_audio_ctx\":{},\"n_text_ctx\":{},\"n_mels\":{}}}",
+            json_string(&self.model_type),
+            self.multilingual,
+            self.n_vocab,
+            self.n_audio_ctx,
+            self.n_text_ctx,
+            self.n_mels,
+        )
+    }
+}
+
+/// Version of the `whisper_transcribe_json` schema, bumped on incompatible changes
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Serialize a whole transcription for `whisper_transcribe_json`
+fn transcript_json(decoded: &Decoded, inference: Duration) -> String {
+    let segments: Vec<String> = decoded.segments.iter()
+        .map(|segment| format!(
+            "{{\"start\":{},\"end\":{},\"text\":{}}}",
+            segment.start_ms,
+            segment.end_ms,
+            json_string(&segment.text),
+        ))
+        .collect();
+    format!(
+        "{{\"version\":{},\"text\":{},\"language\":{},\"segments\":[{}],\"inference_ms\":{}}}",
+        JSON_SCHEMA_VERSION,
+        json_string(&decoded.text()),
+        decoded.language.map_or_else(|| "null".to_string(), json_string),
+        segments.join(","),
+        inference.as_millis(),
+    )
+}
+
+/// Serialize a failed transcription for `whisper_transcribe_json`
+fn error_json(code: WhisperError, message: &str) -> String {
+    format!(
+        "{{\"version\":{},\"error\":{{\"code\":{},\"message\":{}}}}}",
+        JSON_SCHEMA_VERSION,
+        code as i32,
+        json_string(message),
+    )
+}
+
+/// Convert a manager transcription outcome into an FFI result
+/// 
+/// Timing and segment fields are only populated on success.
+fn transcription_result(result: Result<Transcript, ManagerError>, inference: Duration, audio_len: usize) -> WhisperResult {
+    match result {
+        Ok(transcript) => match CString::new(transcript.text) {
+            Ok(c_string) => WhisperResult {
+                success: true,
+                has_text: !c_string.as_bytes().is_empty(),
+                text: c_string.into_raw(),
+                error: std::ptr::null_mut(),
+                error_code: WhisperError::None as i32,
+                inference_ms: inference.as_millis() as u64,
+                audio_duration_ms: (audio_len * 1000 / WHISPER_SAMPLE_RATE) as u64,
+                segment_count: transcript.segment_count as u32,
+                detected_lang: language_string(transcript.language),
+                non_finite_samples: transcript.non_finite as u64,
+                warning: non_finite_warning(transcript.non_finite, audio_len),
+                fallback_count: transcript.fallbacks.count,
+                final_temperature: transcript.fallbacks.final_temperature,
+                no_speech: transcript.no_speech,
+            },
+            Err(_) => WhisperResult::error(WhisperError::ConversionFailed, "Failed to convert transcription result"),
+        },
+        Err(e) => WhisperResult::error(e.code, &e.message),
+    }
+}
+
+/// Share of non-finite samples above which a successful result carries a warning
+const NON_FINITE_WARN_FRACTION: f32 = 0.01;
+
+/// Warning for a result whose input was noticeably non-finite, or null
+fn non_finite_warning(non_finite: usize, audio_len: usize) -> *mut c_char {
+    if audio_len == 0 || (non_finite as f32 / audio_len as f32) <= NON_FINITE_WARN_FRACTION {
+        return std::ptr::null_mut();
+    }
+    let message = format!(
+        "{} of {} samples were NaN or infinite and were transcribed as silence; check the audio pipeline",
+        non_finite, audio_len
+    );
+    logging::warn(&message);
+    create_error_string(&message)
+}
+
+/// Language code as an owned C string, or null when unknown
+fn language_string(language: Option<&str>) -> *mut c_char {
+    language
+        .and_then(|code| CString::new(code).ok())
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Value an FFI entry point returns when its body panicked
+trait PanicFallback {
+    fn panicked(message: &str) -> Self;
+}
+
+impl PanicFallback for WhisperResult {
+    fn panicked(message: &str) -> Self {
+        Self::error(WhisperError::Internal, message)
+    }
+}
+
+impl PanicFallback for WhisperArray {
+    fn panicked(message: &str) -> Self {
+        Self::error(WHISPER_ARRAY_NONE, WhisperError::Internal, message)
+    }
+}
+
+impl PanicFallback for WhisperStats {
+    fn panicked(_: &str) -> Self {
+        WhisperStats { loaded_models: 0, total_memory: 0, avg_cpu: 0.0, memory_limit: 0 }
+    }
+}
+
+impl<T> PanicFallback for *mut T {
+    fn panicked(_: &str) -> Self {
+        std::ptr::null_mut()
+    }
+}
+
+/// `i32` results are `WhisperError` codes
+impl PanicFallback for i32 {
+    fn panicked(_: &str) -> Self {
+        WhisperError::Internal as i32
+    }
+}
+
+impl PanicFallback for bool {
+    fn panicked(_: &str) -> Self {
+        false
+    }
+}
+
+impl PanicFallback for u32 {
+    fn panicked(_: &str) -> Self {
+        0
+    }
+}
+
+impl PanicFallback for u64 {
+    fn panicked(_: &str) -> Self {
+        0
+    }
+}
+
+impl PanicFallback for c_float {
+    fn panicked(_: &str) -> Self {
+        0.0
+    }
+}
+
+impl PanicFallback for () {
+    fn panicked(_: &str) -> Self {}
+}
+
+/// Run an FFI entry point's body, turning a panic into its error value
+/// 
+/// Unwinding out of an `extern "C"` function aborts the host process, so
+/// every entry point goes through this. Locks held by the panicking call
+/// stay poisoned, so later calls on the same model fail cleanly rather than
+/// seeing half-updated state.
+fn ffi_guard<T: PanicFallback>(name: &str, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let detail = payload.downcast_ref::<&str>().copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        let message = format!("Internal error in {}: {}", name, detail);
+        logging::log(logging::LOG_LEVEL_ERROR, &message);
+        T::panicked(&message)
+    })
+}
+
+/// Helper function to create error C strings
+fn create_error_string(msg: &str) -> *mut c_char {
+    match CString::new(msg) {
+        Ok(c_string) => c_string.into_raw(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+thread_local! {
+    /// Why the last bool-returning call on this thread failed, see `whisper_get_last_error`
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn null_handle_error() -> ManagerError {
+    ManagerError::new(WhisperError::InvalidParams, "Handle is null")
+}
+
+/// Turn an operation's outcome into the bool an FFI call returns
+/// 
+/// Replaces this thread's last error: cleared on success, set to the
+/// message on failure, which is also logged.
+fn record_outcome(operation: &str, outcome: Result<(), ManagerError>) -> bool {
+    let error = outcome.err().map(|e| {
+        logging::warn(&format!("Failed to {}: {}", operation, e));
+        e.message
+    });
+    let succeeded = error.is_none();
+    LAST_ERROR.with(|last| *last.borrow_mut() = error);
+    succeeded
+}
+
+/// Why the last `whisper_preload`, `whisper_reload` or `whisper_cleanup_memory`
+/// call on this thread returned false
+/// 
+/// Errors are kept per thread, like `errno`, so concurrent callers each see
+/// their own; every call to those functions replaces it. Returns null if
+/// the last call succeeded or none was made. The caller frees the string
+/// with `whisper_free_string`.
+#[no_mangle]
+pub extern "C" fn whisper_get_last_error() -> *mut c_char {
+    ffi_guard("whisper_get_last_error", || {
+        LAST_ERROR.with(|last| last.borrow().as_deref().map_or(std::ptr::null_mut(), create_error_string))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    
+    /// The elements of an array from this library, checking they're of kind `T`
+    fn elements<T: ArrayElement>(array: &WhisperArray) -> &[T] {
+        assert_eq!(array.kind, T::KIND);
+        if array.ptr.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(array.ptr.cast(), array.len) }
+    }
+    
+    #[test]
+    fn test_panics_become_ffi_errors() {
+        let result: WhisperResult = ffi_guard("whisper_transcribe", || panic!("malformed input"));
+        assert!(!result.success);
+        assert_eq!(result.error_code, WhisperError::Internal as i32);
+        let message = unsafe { CStr::from_ptr(result.error) }.to_str().unwrap().to_string();
+        assert_eq!(message, "Internal error in whisper_transcribe: malformed input");
+        whisper_free_string(result.error);
+        
+        let array: WhisperArray = ffi_guard("whisper_transcribe_segments", || panic!("{} segments", 3));
+        assert_eq!(array.error_code, WhisperError::Internal as i32);
+        whisper_free_array(array);
+        
+        assert!(!ffi_guard("whisper_preload", || -> bool { panic!("load") }));
+        assert!(ffi_guard("whisper_init", || -> *mut WhisperHandle { panic!("init") }).is_null());
+        assert_eq!(ffi_guard("whisper_validate_model", || -> i32 { panic!("read") }), WhisperError::Internal as i32);
+        
+        // Bodies that return normally are untouched
+        assert_eq!(ffi_guard("whisper_get_memory_usage", || 42u64), 42);
+    }
+    
+    #[test]
+    fn test_ffi_safety() {
+        // Test null pointer handling
+        let result = whisper_transcribe(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            0
+        );
+        assert!(!result.success);
+        
+        // Test context creation with invalid path
+        let invalid_path = CString::new("invalid_path").unwrap();
+        let handle = whisper_init(invalid_path.as_ptr());
+        assert!(handle.is_null());
+    }
+    
+    #[test]
+    fn test_model_info_creation() {
+        let tiny_info = ModelInfo {
+            name: "tiny.en".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        
+        assert_eq!(tiny_info.size, ModelSize::Tiny);
+        assert_eq!(tiny_info.size.memory_limit(), 100 * 1024 * 1024);
+        assert_eq!(tiny_info.size.cpu_threshold(), 80.0);
+    }
+    
+    #[test]
+    fn test_model_size_from_name() {
+        assert_eq!(ModelSize::from_name("tiny.en"), ModelSize::Tiny);
+        assert_eq!(ModelSize::from_name("small.en"), ModelSize::Small);
+        assert_eq!(ModelSize::from_name("medium.en"), ModelSize::Medium);
+        assert_eq!(ModelSize::from_name("ggml-large.bin"), ModelSize::Large);
+        assert_eq!(ModelSize::from_name("ggml-large-v3.bin"), ModelSize::Large);
+        assert_eq!(ModelSize::from_name("unknown"), ModelSize::Medium); // Default
+    }
+    
+    #[test]
+    fn test_large_model_accounting() {
+        let info = model_info_for_path("/nonexistent/ggml-large-v3.bin");
+        assert_eq!((info.size, info.memory_usage, info.cpu_factor), (ModelSize::Large, 3 * 1024 * 1024 * 1024, 8.0));
+        assert!(!info.exceeds_size_limit(info.memory_usage));
+        assert_eq!(Tuning::for_model(&info).sampling, SamplingMode::BeamSearch { beam_size: 5, patience: -1.0 });
+        
+        // The estimate is what gets reserved, so next to a loaded tiny model
+        // the default budget turns it away
+        let manager = WhisperManager::new();
+        manager.register_model("large_id".to_string(), info.name.clone(), info).unwrap();
+        let tiny = 39 * 1024 * 1024;
+        assert!(manager.try_reserve_memory(tiny));
+        let error = manager.preload("large_id").unwrap_err();
+        assert!(error.message.contains("(3072MB) would exceed the 700MB memory limit"), "{}", error);
+        
+        manager.set_memory_limit(4 * 1024 * 1024 * 1024).unwrap();
+        let error = manager.preload("large_id").unwrap_err();
+        assert!(!error.message.contains("memory limit"), "{}", error);
+        assert_eq!(manager.committed_memory.load(Ordering::SeqCst), tiny);
+    }
+    
+    #[test]
+    fn test_background_warm_skips_when_memory_is_short() {
+        let manager = WhisperManager::new();
+        let info = model_info_for_path("/nonexistent/ggml-small-warm.bin");
+        let small = info.memory_usage;
+        manager.register_model("warm_id".to_string(), info.name.clone(), info).unwrap();
+        
+        // With the active model's reservation in place, the second one only
+        // fits by evicting, so warming refuses where preload would evict
+        let active = manager.memory_limit() - small / 2;
+        assert!(manager.try_reserve_memory(active));
+        let error = manager.warm("warm_id").unwrap_err();
+        assert!(error.message.contains("would exceed the 700MB memory limit"), "{}", error);
+        assert_eq!(manager.committed_memory.load(Ordering::SeqCst), active);
+        
+        // With room to spare it gets as far as reading the file
+        manager.release_memory(active);
+        let error = manager.warm("warm_id").unwrap_err();
+        assert!(!error.message.contains("memory limit"), "{}", error);
+        assert_eq!(manager.committed_memory.load(Ordering::SeqCst), 0);
+        
+        assert!(!whisper_warm_in_background(std::ptr::null_mut(), WHISPER_PRIORITY_UTILITY));
+        let path = CString::new("/nonexistent/warm-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(!whisper_warm_in_background(handle, 7));
+        assert!(whisper_warm_in_background(handle, WHISPER_PRIORITY_BACKGROUND));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_tuning_depends_on_size_and_languages() {
+        assert!(!model_info_for_path("/models/ggml-tiny.en.bin").is_multilingual());
+        assert!(model_info_for_path("/models.en/ggml-medium.bin").is_multilingual());
+        
+        let tiny = Tuning::for_model(&model_info_for_path("/models/ggml-tiny.en.bin"));
+        let medium = Tuning::for_model(&model_info_for_path("/models/ggml-medium.bin"));
+        assert_eq!(tiny.sampling, SamplingMode::Greedy { best_of: 1 });
+        assert_eq!(medium.sampling, SamplingMode::BeamSearch { beam_size: 5, patience: -1.0 });
+        assert!(medium.n_threads >= tiny.n_threads);
+        
+        // Registration applies the table, and setters still override it
+        let path = CString::new("/nonexistent/ggml-medium.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let effective = || {
+            let raw = whisper_get_effective_params(handle);
+            let json = unsafe { CStr::from_ptr(raw) }.to_str().unwrap().to_string();
+            whisper_free_string(raw);
+            parse_json(&json).unwrap()
+        };
+        let json = effective();
+        assert_eq!(json.get("sampling"), Some(&Json::String("beam_search".to_string())));
+        assert_eq!(json.get("sampling_param"), Some(&Json::Number(5.0)));
+        assert_eq!(json.get("n_threads"), Some(&Json::Number(medium.n_threads as f64)));
+        assert!(whisper_set_sampling(handle, WHISPER_SAMPLING_GREEDY, 2));
+        assert_eq!(effective().get("sampling"), Some(&Json::String("greedy".to_string())));
+        
+        assert!(whisper_get_effective_params(std::ptr::null_mut()).is_null());
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_cpu_monitor() {
+        let mut monitor = CpuMonitor::new();
+        
+        // Test initial state
+        assert_eq!(monitor.average_cpu_usage(), 0.0);
+        assert!(!monitor.should_downgrade(80.0));
+        
+        // Add some CPU usage samples
+        monitor.record_cpu_usage(50.0);
+        monitor.record_cpu_usage(60.0);
+        monitor.record_cpu_usage(70.0);
+        
+        assert_eq!(monitor.average_cpu_usage(), 60.0);
+        assert!(!monitor.should_downgrade(80.0));
+        
+        // Add high CPU usage
+        monitor.record_cpu_usage(90.0);
+        monitor.record_cpu_usage(95.0);
+        
+        assert!(monitor.average_cpu_usage() > 70.0);
+        assert!(monitor.should_downgrade(80.0));
+    }
+    
+    #[test]
+    fn test_reset_cpu_stats() {
+        let mut monitor = CpuMonitor::new();
+        for sample in 0..25 {
+            monitor.record_cpu_usage(sample as f32);
+        }
+        // Only the last 10 samples (15..25) count
+        assert_eq!(monitor.average_cpu_usage(), 19.5);
+        
+        monitor.reset();
+        assert_eq!(monitor.average_cpu_usage(), 0.0);
+        assert!(!monitor.should_downgrade(0.0));
+        
+        monitor.record_cpu_usage(40.0);
+        assert_eq!(monitor.average_cpu_usage(), 40.0);
+        
+        whisper_reset_cpu_stats();
+    }
+    
+    #[test]
+    fn test_custom_downgrade_target() {
+        let init = |path: &str| whisper_init(CString::new(path).unwrap().as_ptr());
+        let (slow, fast) = (init("/tmp/downgrade-custom-slow.bin"), init("/tmp/downgrade-custom-fast.bin"));
+        let medium = init("/tmp/downgrade-medium.bin");
+        let large_v3 = init("/tmp/downgrade-large-v3.bin");
+        let id = |handle: *mut WhisperHandle| unsafe { &*handle }.model_id.clone();
+        
+        // Custom names match nothing in the default chain, so only a registered target applies
+        assert_eq!(WHISPER_MANAGER.downgrade_target(&id(slow)), None);
+        assert!(whisper_set_downgrade_target(slow, fast));
+        assert_eq!(WHISPER_MANAGER.downgrade_target(&id(slow)).as_deref(), Some("/tmp/downgrade-custom-fast.bin"));
+        
+        // Other models keep the default chain
+        assert_eq!(WHISPER_MANAGER.downgrade_target(&id(medium)).as_deref(), Some("small"));
+        assert_eq!(WHISPER_MANAGER.downgrade_target(&id(large_v3)).as_deref(), Some("medium"));
+        
+        // Clearing restores the default; an unregistered target suggests nothing
+        assert!(whisper_set_downgrade_target(slow, std::ptr::null_mut()));
+        assert_eq!(WHISPER_MANAGER.downgrade_target(&id(slow)), None);
+        assert!(whisper_set_downgrade_target(medium, fast));
+        whisper_unregister(fast);
+        assert_eq!(WHISPER_MANAGER.downgrade_target(&id(medium)), None);
+        assert!(!whisper_set_downgrade_target(slow, fast));
+        assert!(!whisper_set_downgrade_target(std::ptr::null_mut(), slow));
+        
+        assert!(!whisper_set_downgrade_threshold(f32::NAN));
+        assert!(!whisper_set_downgrade_threshold(120.0));
+        
+        for handle in [slow, fast, medium, large_v3] {
+            whisper_unregister(handle);
+            whisper_free(handle);
+        }
+    }
+    
+    #[test]
+    fn test_whisper_model_lifecycle() {
+        let model_info = ModelInfo {
+            name: "test.en".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 50 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        
+        let mut model = WhisperModel::new("/tmp/test".to_string(), model_info);
+        
+        // Initially unloaded
+        assert_eq!(model.memory_usage(), 0);
+        assert!(model.ctx.is_none());
+        
+        // Should unload after timeout (simulated)
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        model.idle_timeout = std::time::Duration::from_millis(5);
+        assert!(model.should_unload());
+    }
+    
+    #[test]
+    fn test_idle_timeout_zero_never_unloads() {
+        let model_info = ModelInfo {
+            name: "test.en".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 50 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        
+        let mut model = WhisperModel::new("/tmp/test".to_string(), model_info);
+        model.idle_timeout = Duration::from_millis(5);
+        std::thread::sleep(Duration::from_millis(10));
+        
+        // Normal branch: idle past the timeout
+        assert!(model.idle_expired());
+        
+        // Zero branch: never expires no matter how long it idles
+        model.idle_timeout = Duration::ZERO;
+        assert!(!model.idle_expired());
+        assert!(!model.should_unload());
+    }
+    
+    #[test]
+    fn test_activity_defers_idle_sweep() {
+        let model_info = ModelInfo {
+            name: "test.en".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 50 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        let manager = WhisperManager::new();
+        let mut model = WhisperModel::new("/tmp/test".to_string(), model_info);
+        model.idle_timeout = Duration::from_millis(5);
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(manager.sweep_due(&model));
+        
+        // An active recording holds off an otherwise due unload
+        manager.set_recording(true);
+        assert!(model.idle_expired());
+        assert!(!manager.sweep_due(&model));
+        
+        // Once idle again, the countdown restarts from the mark
+        manager.set_recording(false);
+        assert!(!manager.sweep_due(&model));
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(manager.sweep_due(&model));
+        
+        manager.touch_activity();
+        assert!(!manager.sweep_due(&model));
+    }
+    
+    #[test]
+    fn test_idle_timeout_ffi() {
+        assert!(!whisper_set_idle_timeout(std::ptr::null_mut(), 60));
+        
+        let manager = WhisperManager::new();
+        manager.set_default_idle_timeout(Duration::from_secs(120));
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("idle_id".to_string(), "/tmp/idle.bin".to_string(), model_info).unwrap();
+        
+        let timeout = manager.with_model("idle_id", |model| model.idle_timeout).unwrap();
+        assert_eq!(timeout, Duration::from_secs(120));
+        assert!(manager.with_model("missing", |_| ()).is_err());
+    }
+    
+    #[test]
+    fn test_failed_load_is_retried() {
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        let mut model = WhisperModel::new("/nonexistent/retry.bin".to_string(), model_info);
+        
+        // Every attempt must hit the loader again rather than a stale "not loaded" state
+        for _ in 0..2 {
+            let error = model.ensure_loaded().err().unwrap();
+            assert_eq!(error.code, WhisperError::LoadFailed);
+            assert!(error.message.starts_with("Failed to load model"), "{}", error);
+            assert!(model.ctx.is_none());
+        }
+        
+        // Once the file loads, the same instance recovers
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-retry-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            model.model_path = path.to_str().unwrap().to_string();
+            assert!(model.ensure_loaded().is_ok());
+            assert!(model.ctx.is_some());
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_idle_sweeper_start_stop() {
+        assert!(!whisper_start_idle_sweeper(0));
+        whisper_stop_idle_sweeper();
+        
+        assert!(whisper_start_idle_sweeper(3600));
+        assert!(whisper_start_idle_sweeper(1)); // replaces the running sweeper
+        whisper_stop_idle_sweeper();
+        whisper_stop_idle_sweeper();
+        assert!(IDLE_SWEEPER.lock().unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_context_pool() {
+        let manager = WhisperManager::new();
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("pool_id".to_string(), "/nonexistent/pool.bin".to_string(), model_info.clone()).unwrap();
+        
+        // Defaults to the model alone
+        assert!(replicas(&manager.models.read().unwrap(), "pool_id").is_empty());
+        assert_eq!(manager.resize_pool("pool_id", 0).unwrap_err().code, WhisperError::InvalidParams);
+        assert_eq!(manager.resize_pool("pool_id", MAX_CONCURRENCY + 1).unwrap_err().code, WhisperError::InvalidParams);
+        
+        // Replicas follow the model's settings and are aborted with it
+        manager.resize_pool("pool_id", 3).unwrap();
+        manager.with_model("pool_id", |model| model.settings.translate = true).unwrap();
+        for index in 1..3 {
+            assert!(manager.with_model(&replica_id("pool_id", index), |model| model.settings.translate).unwrap());
+        }
+        manager.abort("pool_id").unwrap();
+        assert!(manager.with_model(&replica_id("pool_id", 2), |model| model.abort.load(Ordering::Relaxed)).unwrap());
+        
+        manager.resize_pool("pool_id", 2).unwrap();
+        assert_eq!(replicas(&manager.models.read().unwrap(), "pool_id").len(), 1);
+        
+        // A model that can't load leaves the pool at its default
+        assert_eq!(manager.set_concurrency("pool_id", 2).unwrap_err().code, WhisperError::LoadFailed);
+        assert!(replicas(&manager.models.read().unwrap(), "pool_id").is_empty());
+        
+        manager.resize_pool("pool_id", 2).unwrap();
+        manager.unregister("pool_id").unwrap();
+        assert!(manager.models.read().unwrap().is_empty());
+        assert_eq!(whisper_set_concurrency(std::ptr::null_mut(), 2), 0);
+        
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-pool-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            manager.register_model("pool_id".to_string(), path.to_str().unwrap().to_string(), model_info).unwrap();
+            assert_eq!(manager.set_concurrency("pool_id", 2).unwrap(), 2);
+            assert_eq!(manager.current_memory_usage(), 2 * 39 * 1024 * 1024);
+            
+            // A busy model hands out its loaded replica instead of waiting
+            let models = manager.models.read().unwrap();
+            let _busy = models["pool_id"].lock().unwrap();
+            assert!(manager.select_slot(&models, "pool_id").unwrap().ctx.is_some());
+            drop(_busy);
+            drop(models);
+            
+            // Only as many contexts as the memory limit allows
+            manager.set_memory_limit(MIN_MEMORY_LIMIT).unwrap();
+            manager.resize_pool("pool_id", 1).unwrap();
+            assert_eq!(manager.set_concurrency("pool_id", 2).unwrap(), 1);
+            assert_eq!(manager.current_memory_usage(), 39 * 1024 * 1024);
+            manager.unregister("pool_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_raised_memory_limit_avoids_eviction() {
+        let manager = WhisperManager::new();
+        let medium = 769 * 1024 * 1024;
+        
+        // A resident medium model leaves no room for a second one under the default limit,
+        // so the second load would have to evict
+        assert!(manager.try_reserve_memory(medium));
+        assert!(!manager.try_reserve_memory(medium));
+        
+        assert!(manager.set_memory_limit(4 * medium).is_ok());
+        assert!(manager.try_reserve_memory(medium));
+        assert_eq!(manager.current_memory_usage(), 2 * medium);
+        assert_eq!(manager.stats().unwrap().memory_limit, 4 * medium);
+        
+        assert_eq!(manager.set_memory_limit(1024).unwrap_err().code, WhisperError::InvalidParams);
+        assert!(!whisper_set_memory_limit(0));
+        assert_eq!(manager.memory_limit(), 4 * medium);
+    }
+    
+    #[test]
+    fn test_stats_reflect_load_state() {
+        let manager = WhisperManager::new();
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        // The mock backend loads any model header; nothing loads from a missing file
+        let path = if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-stats.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            path
+        } else {
+            std::path::PathBuf::from("/nonexistent/stats.bin")
+        };
+        manager.register_model("stats_id".to_string(), path.to_str().unwrap().to_string(), model_info).unwrap();
+        
+        let stats = manager.stats().unwrap();
+        assert_eq!(stats.loaded_models, 0);
+        assert_eq!(stats.total_memory, 0);
+        assert_eq!(stats.memory_limit, 700 * 1024 * 1024);
+        
+        // A busy model is reported as loaded instead of blocking the snapshot
+        {
+            let models = manager.models.read().unwrap();
+            let _busy = models["stats_id"].lock().unwrap();
+            assert_eq!(manager.stats().unwrap().loaded_models, 1);
+        }
+        
+        assert_eq!(manager.preload("stats_id").is_ok(), cfg!(feature = "mock"));
+        let stats = manager.stats().unwrap();
+        if cfg!(feature = "mock") {
+            assert_eq!((stats.loaded_models, stats.total_memory), (1, 39 * 1024 * 1024));
+            manager.unregister("stats_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        } else {
+            assert_eq!((stats.loaded_models, stats.total_memory), (0, 0));
+        }
+    }
+    
+    #[test]
+    fn test_gpu_load_failure_falls_back_to_cpu() {
+        let mut attempts = Vec::new();
+        let result = load_with_cpu_fallback(true, |use_gpu| {
+            attempts.push(use_gpu);
+            if use_gpu { Err("metal init failed") } else { Ok("context") }
+        });
+        assert_eq!(result, Ok(("context", false)));
+        assert_eq!(attempts, [true, false]);
+        
+        // The CPU error is reported when both attempts fail
+        let mut attempts = Vec::new();
+        let result: Result<((), bool), &str> = load_with_cpu_fallback(true, |use_gpu| {
+            attempts.push(use_gpu);
+            Err(if use_gpu { "gpu" } else { "cpu" })
+        });
+        assert_eq!(result, Err("cpu"));
+        assert_eq!(attempts, [true, false]);
+        
+        // Without a GPU there's nothing to fall back from
+        let mut attempts = Vec::new();
+        let result = load_with_cpu_fallback(false, |use_gpu| {
+            attempts.push(use_gpu);
+            Ok::<_, &str>(())
+        });
+        assert_eq!(result, Ok(((), false)));
+        assert_eq!(attempts, [false]);
+        
+        assert!(!whisper_is_gpu_enabled(std::ptr::null_mut()));
+    }
+    
+    #[test]
+    fn test_actual_memory_falls_back_to_estimate() {
+        let model_info = ModelInfo {
+            name: "test.en".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        
+        let mut model = WhisperModel::new("/tmp/test".to_string(), model_info);
+        
+        // Unloaded models report nothing, measured or not
+        model.actual_memory = Some(12 * 1024 * 1024);
+        assert_eq!(model.actual_memory_usage(), 0);
+        
+        model.unload();
+        assert!(model.actual_memory.is_none());
+        
+        assert_eq!(whisper_get_actual_memory(std::ptr::null_mut()), 0);
+        assert_eq!(whisper_get_estimated_memory(std::ptr::null_mut()), 0);
+    }
+    
+    #[test]
+    fn test_preload_reports_failure() {
+        assert!(!whisper_preload(std::ptr::null_mut()));
+        assert!(!whisper_is_loaded(std::ptr::null_mut()));
+        
+        let path = CString::new("/nonexistent/preload_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(!handle.is_null());
+        assert!(!whisper_preload(handle));
+        assert!(!whisper_is_loaded(handle));
+        
+        // The reason stays retrievable until the next such call on this thread
+        let last_error = || {
+            let ptr = whisper_get_last_error();
+            let message = (!ptr.is_null()).then(|| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string());
+            whisper_free_string(ptr);
+            message
+        };
+        let message = last_error().expect("failed preload leaves an error");
+        assert!(message.contains("load"), "{}", message);
+        assert_eq!(last_error().as_deref(), Some(message.as_str()));
+        assert!(std::thread::spawn(|| whisper_get_last_error().is_null()).join().unwrap());
+        
+        assert!(whisper_cleanup_memory());
+        assert_eq!(last_error(), None);
+        assert!(!whisper_reload(std::ptr::null_mut()));
+        assert_eq!(last_error().as_deref(), Some("Handle is null"));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    fn test_process_resident_bytes() {
+        assert!(process_resident_bytes().unwrap_or(0) > 0);
+        
+        // A running test binary is well past a megabyte resident
+        assert!(whisper_get_process_rss() > 1024 * 1024);
+    }
+    
+    extern "C" fn count_segments(_text: *const c_char, user_data: *mut c_void) {
+        let count = unsafe { &mut *(user_data as *mut usize) };
+        *count += 1;
+    }
+    
+    #[test]
+    fn test_streaming_invalid_params() {
+        let mut count = 0usize;
+        let result = whisper_transcribe_streaming(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            0,
+            Some(count_segments),
+            &mut count as *mut usize as *mut c_void,
+        );
+        assert!(!result.success);
+        assert!(result.text.is_null());
+        assert_eq!(result.inference_ms, 0);
+        assert_eq!(result.audio_duration_ms, 0);
+        assert_eq!(count, 0);
+        whisper_free_string(result.error);
+    }
+    
+    #[test]
+    fn test_error_codes() {
+        let audio = vec![0.0f32; 1600];
+        let result = whisper_transcribe(std::ptr::null_mut(), audio.as_ptr(), audio.len());
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        
+        let handle = Box::into_raw(Box::new(WhisperHandle {
+            model_id: "unregistered_model".to_string(),
+        }));
+        let result = whisper_transcribe(handle, audio.as_ptr(), audio.len());
+        assert_eq!(result.error_code, WhisperError::ModelNotFound as i32);
+        whisper_free_string(result.error);
+        whisper_free(handle);
+        
+        let path = CString::new("/nonexistent/error_code_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let array = whisper_transcribe_segments(handle, audio.as_ptr(), audio.len());
+        assert_eq!(array.error_code, WhisperError::LoadFailed as i32);
+        whisper_free_array(array);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_transcription_result_timing() {
+        let inference = Duration::from_millis(250);
+        let transcript = Transcript { text: "hello".to_string(), segment_count: 1, language: Some("en"), non_finite: 0, fallbacks: Fallbacks::default(), no_speech: false };
+        let result = transcription_result(Ok(transcript), inference, 48000);
+        assert!(result.success);
+        assert_eq!(result.error_code, WhisperError::None as i32);
+        assert_eq!(result.inference_ms, 250);
+        assert_eq!(result.audio_duration_ms, 3000);
+        assert_eq!(result.segment_count, 1);
+        assert!(result.has_text);
+        assert_eq!(unsafe { CStr::from_ptr(result.detected_lang) }.to_str().unwrap(), "en");
+        whisper_free_string(result.detected_lang);
+        whisper_free_string(result.text);
+        
+        let result = transcription_result(Err(ManagerError::new(WhisperError::InferenceFailed, "boom")), inference, 48000);
+        assert!(!result.success);
+        assert_eq!(result.error_code, WhisperError::InferenceFailed as i32);
+        assert_eq!(result.inference_ms, 0);
+        assert_eq!(result.audio_duration_ms, 0);
+        whisper_free_string(result.error);
+    }
+    
+    #[test]
+    fn test_silent_buffer_flags_no_speech() {
+        let silent = vec![0.0f32; 32000];
+        let speech: Vec<f32> = (0..32000).map(|i| 0.5 * (i as f32 * 0.05).sin()).collect();
+        let decoded = |audio: &[f32]| Decoded {
+            // whisper often hallucinates a phrase over silence
+            segments: vec![DecodedSegment {
+                end_ms: 2000,
+                no_speech_prob: silent_fraction(audio, 0, 2000),
+                ..segment(" Thank you.", 0.5, -0.7)
+            }],
+            language: Some("en"),
+            non_finite: 0,
+            text_format: 0,
+            trim: TrimPolicy::Both,
+            fallbacks: Fallbacks::default(),
+        };
+        
+        assert!(Transcript::from_decoded(&decoded(&silent)).no_speech);
+        assert!(!Transcript::from_decoded(&decoded(&speech)).no_speech);
+        let nothing = Decoded { segments: Vec::new(), ..decoded(&silent) };
+        assert!(Transcript::from_decoded(&nothing).no_speech);
+        
+        let result = transcription_result(Ok(Transcript::from_decoded(&decoded(&silent))), Duration::ZERO, silent.len());
+        assert!(result.success && result.has_text && result.no_speech);
+        whisper_free_string(result.text);
+        whisper_free_string(result.detected_lang);
+        
+        // A failed call is not silence
+        let result = transcription_result(Err(ManagerError::new(WhisperError::InferenceFailed, "boom")), Duration::ZERO, silent.len());
+        assert!(!result.no_speech);
+        whisper_free_string(result.error);
+    }
+    
+    #[test]
+    fn test_batch_invalid_params() {
+        let array = whisper_transcribe_batch(std::ptr::null_mut(), std::ptr::null(), std::ptr::null(), 0);
+        assert!(!array.success);
+        assert!(array.ptr.is_null());
+        whisper_free_array(array);
+    }
+    
+    #[test]
+    fn test_batch_reports_per_buffer_errors() {
+        let path = CString::new("/nonexistent/batch_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let audio = vec![0.0f32; 1600];
+        let buffers = [audio.as_ptr(), std::ptr::null(), audio.as_ptr()];
+        let lengths = [audio.len(), 0, audio.len()];
+        
+        let array = whisper_transcribe_batch(handle, buffers.as_ptr(), lengths.as_ptr(), buffers.len());
+        assert!(array.success);
+        assert_eq!(array.len, 3);
+        
+        let results = elements::<WhisperResult>(&array);
+        let error = |result: &WhisperResult| unsafe { CStr::from_ptr(result.error) }.to_str().unwrap().to_string();
+        assert!(results.iter().all(|result| !result.success));
+        assert!(error(&results[0]).starts_with("Failed to load model"));
+        assert_eq!(error(&results[1]), "Invalid parameters");
+        assert!(error(&results[2]).starts_with("Failed to load model"), "{}", error(&results[2]));
+        
+        whisper_free_array(array);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    extern "C" fn record_progress(progress: i32, user_data: *mut c_void) {
+        let last = unsafe { &mut *(user_data as *mut i32) };
+        *last = progress;
+    }
+    
+    #[test]
+    fn test_progress_missing_model_fails_gracefully() {
+        let mut last = -1i32;
+        let audio = vec![0.0f32; 1600];
+        
+        let result = whisper_transcribe_with_progress(
+            std::ptr::null_mut(),
+            audio.as_ptr(),
+            audio.len(),
+            Some(record_progress),
+            &mut last as *mut i32 as *mut c_void,
+        );
+        assert!(!result.success);
+        whisper_free_string(result.error);
+        
+        let handle = Box::into_raw(Box::new(WhisperHandle {
+            model_id: "unregistered_model".to_string(),
+        }));
+        let result = whisper_transcribe_with_progress(
+            handle,
+            audio.as_ptr(),
+            audio.len(),
+            Some(record_progress),
+            &mut last as *mut i32 as *mut c_void,
+        );
+        assert!(!result.success);
+        assert_eq!(last, -1);
+        
+        whisper_free_string(result.error);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_resampled_invalid_params() {
+        let audio = vec![0.0f32; 4800];
+        let result = whisper_transcribe_resampled(std::ptr::null_mut(), audio.as_ptr(), audio.len(), 48000);
+        assert!(!result.success);
+        whisper_free_string(result.error);
+        
+        let handle = Box::into_raw(Box::new(WhisperHandle {
+            model_id: "unregistered_model".to_string(),
+        }));
+        let result = whisper_transcribe_resampled(handle, audio.as_ptr(), audio.len(), 0);
+        assert!(!result.success);
+        whisper_free_string(result.error);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_multichannel_invalid_params() {
+        let audio = vec![0.0f32; 3200];
+        let handle = Box::into_raw(Box::new(WhisperHandle {
+            model_id: "unregistered_model".to_string(),
+        }));
+        
+        for (len, channels) in [(audio.len(), 0), (1, 2)] {
+            let result = whisper_transcribe_multichannel(handle, audio.as_ptr(), len, channels);
+            assert!(!result.success);
+            assert_eq!(unsafe { CStr::from_ptr(result.error) }.to_str().unwrap(), "Invalid parameters");
+            whisper_free_string(result.error);
+        }
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_detect_language_failures_return_null() {
+        let audio = vec![0.0f32; 1600];
+        let mut confidence = -1.0f32;
+        assert!(whisper_detect_language(std::ptr::null_mut(), audio.as_ptr(), audio.len(), &mut confidence).is_null());
+        
+        let path = CString::new("/nonexistent/language_small.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_detect_language(handle, audio.as_ptr(), audio.len(), &mut confidence).is_null());
+        assert_eq!(confidence, -1.0);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_streaming_missing_model_fails_gracefully() {
+        let mut count = 0usize;
+        let handle = Box::into_raw(Box::new(WhisperHandle {
+            model_id: "unregistered_model".to_string(),
+        }));
+        let audio = vec![0.0f32; 1600];
+        
+        let result = whisper_transcribe_streaming(
+            handle,
+            audio.as_ptr(),
+            audio.len(),
+            Some(count_segments),
+            &mut count as *mut usize as *mut c_void,
+        );
+        assert!(!result.success);
+        assert!(!result.error.is_null());
+        assert_eq!(count, 0);
+        
+        whisper_free_string(result.error);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_stream_session_lifecycle() {
+        let mut count = 0usize;
+        let user_data = &mut count as *mut usize as *mut c_void;
+        let path = CString::new("/nonexistent/stream-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_stream_create(std::ptr::null_mut(), 2, Some(count_segments), user_data).is_null());
+        assert!(whisper_stream_create(handle, 2, None, user_data).is_null());
+        
+        let stream = whisper_stream_create(handle, 0, Some(count_segments), user_data);
+        assert_eq!(unsafe { &*stream }.session.lock().unwrap().committed().len(), 0);
+        let audio = vec![0.0f32; 1600];
+        for _ in 0..3 {
+            let result = whisper_stream_feed(stream, audio.as_ptr(), audio.len());
+            assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+            whisper_free_string(result.error);
+        }
+        // Audio accumulates even when passes fail, and nothing is committed
+        assert_eq!(unsafe { &*stream }.session.lock().unwrap().audio().len(), 3 * 1600);
+        assert_eq!(count, 0);
+        
+        let result = whisper_stream_feed(stream, std::ptr::null(), 0);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        let too_long = vec![0.0f32; MAX_BUFFER_SAMPLES];
+        let result = whisper_stream_feed(stream, too_long.as_ptr(), too_long.len());
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        
+        whisper_stream_free(stream);
+        whisper_stream_free(std::ptr::null_mut());
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_stream_finish_ends_the_session() {
+        let _global = GLOBAL_MODELS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut count = 0usize;
+        let user_data = &mut count as *mut usize as *mut c_void;
+        let result = whisper_stream_finish(std::ptr::null_mut());
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        
+        // The mock backend decodes each second with sound as " Second N."; a
+        // real one would abort loading a bare header, so it gets no file
+        let path = if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-stream-finish-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            path
+        } else {
+            std::path::PathBuf::from("/nonexistent/stream-finish-tiny.bin")
+        };
+        let model_path = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = whisper_init(model_path.as_ptr());
+        
+        // Never fed: nothing to transcribe, and the session still ends
+        let stream = whisper_stream_create(handle, 2, Some(count_segments), user_data);
+        let result = whisper_stream_finish(stream);
+        assert!(result.success);
+        assert_eq!(unsafe { CStr::from_ptr(result.text) }.to_str().unwrap(), "");
+        whisper_free_string(result.text);
+        whisper_stream_free(stream);
+        
+        let stream = whisper_stream_create(handle, 2, Some(count_segments), user_data);
+        let audio = vec![0.1f32; 16000];
+        for _ in 0..2 {
+            let result = whisper_stream_feed(stream, audio.as_ptr(), audio.len());
+            whisper_free_string(result.text);
+            whisper_free_string(result.error);
+            whisper_free_string(result.detected_lang);
+        }
+        let result = whisper_stream_finish(stream);
+        if cfg!(feature = "mock") {
+            assert!(result.success);
+            assert_eq!(unsafe { CStr::from_ptr(result.text) }.to_str().unwrap(), "Second 1. Second 2.");
+            assert_eq!(result.audio_duration_ms, 2000);
+            assert_eq!(count, 2); // "Second 1." on the second feed, the rest on finishing
+            whisper_free_string(result.text);
+            whisper_free_string(result.detected_lang);
+            
+            let session = unsafe { &*stream }.session.lock().unwrap();
+            assert!(session.is_finished() && session.audio().is_empty());
+            assert_eq!(session.committed(), ["Second", "1.", "Second", "2."]);
+            drop(session);
+            
+            for result in [whisper_stream_feed(stream, audio.as_ptr(), audio.len()), whisper_stream_finish(stream)] {
+                assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+                whisper_free_string(result.error);
+            }
+        } else {
+            // A failed final pass leaves the session open for a retry
+            assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+            whisper_free_string(result.error);
+            let session = unsafe { &*stream }.session.lock().unwrap();
+            assert!(!session.is_finished());
+            assert_eq!(session.audio().len(), 2 * 16000);
+        }
+        
+        whisper_stream_free(stream);
+        whisper_unregister(handle);
+        whisper_free(handle);
+        if cfg!(feature = "mock") {
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    fn segment(text: &str, confidence: f32, avg_logprob: f32) -> DecodedSegment {
+        DecodedSegment {
+            text: text.to_string(),
+            start_ms: 0,
+            end_ms: 0,
+            confidence,
+            avg_logprob,
+            no_speech_prob: 0.0,
+            starts_new_turn: false,
+            language: None,
+            tokens: Vec::new(),
+        }
+    }
+    
+    fn token(text: &str, start_ms: i64, end_ms: i64, is_special: bool) -> DecodedToken {
+        DecodedToken {
+            id: 0,
+            bytes: text.as_bytes().to_vec(),
+            p: 0.5,
+            start_ms,
+            end_ms,
+            dtw_ms: None,
+            is_special,
+        }
+    }
+    
+    #[test]
+    fn test_merge_words() {
+        let tokens = vec![
+            token("[_BEG_]", 0, 0, true),
+            token(" Kub", 1230, 1400, false),
+            token("ern", 1400, 1550, false),
+            token("etes", 1550, 1700, false),
+            token(" rocks", 1710, 2000, false),
+            token(".", 2000, 2050, false),
+            token("[_TT_105]", 2050, 2050, true),
+        ];
+        
+        let words = merge_words(&tokens);
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "Kubernetes");
+        // Start comes from the first token's buffer-relative time, not the segment's
+        assert_eq!(words[0].start_ms, 1230);
+        assert_eq!(words[0].end_ms, 1700);
+        assert_eq!(words[1].text, "rocks.");
+        assert_eq!(words[1].end_ms, 2050);
+        assert!((words[1].probability - 0.5).abs() < f32::EPSILON);
+    }
+    
+    #[test]
+    fn test_dtw_preset_threads_into_context_params() {
+        let preset = |name: &str| DtwPreset::for_model(&model_info_for_path(name));
+        assert_eq!(preset("/models/ggml-base.en.bin"), Some(DtwPreset::BaseEn));
+        assert_eq!(preset("/models/ggml-small.bin"), Some(DtwPreset::Small));
+        assert_eq!(preset("/models/ggml-large-v3-turbo.bin"), Some(DtwPreset::LargeV3Turbo));
+        assert_eq!(preset("/models/ggml-large-v2.bin"), Some(DtwPreset::LargeV2));
+        assert_eq!(preset("/models/ggml-large.bin"), None);
+        assert_eq!(preset("/models/custom.bin"), None);
+        
+        let path = CString::new("/nonexistent/dtw-tiny.en.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let dtw = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.dtw).unwrap();
+        assert_eq!(dtw(), None);
+        assert!(matches!(context_params(false, dtw()).dtw_parameters.mode, DtwMode::None));
+        
+        assert!(whisper_set_dtw(handle, WHISPER_DTW_AUTO));
+        assert_eq!(dtw(), Some(DtwPreset::TinyEn));
+        let params = context_params(true, dtw());
+        assert!(params.use_gpu);
+        assert!(matches!(params.dtw_parameters.mode, DtwMode::ModelPreset { model_preset: DtwModelPreset::TinyEn }));
+        assert!(whisper_set_dtw(handle, WHISPER_DTW_LARGE_V3));
+        assert!(matches!(context_params(false, dtw()).dtw_parameters.mode, DtwMode::ModelPreset { model_preset: DtwModelPreset::LargeV3 }));
+        
+        assert!(!whisper_set_dtw(handle, 14));
+        assert!(!whisper_set_dtw(std::ptr::null_mut(), WHISPER_DTW_AUTO));
+        assert_eq!(dtw(), Some(DtwPreset::LargeV3));
+        assert!(whisper_set_dtw(handle, WHISPER_DTW_OFF));
+        assert_eq!(dtw(), None);
+        whisper_unregister(handle);
+        whisper_free(handle);
+        
+        // Words take the DTW time of their first token
+        let tokens = [
+            DecodedToken { dtw_ms: Some(1250), ..token(" Kub", 1230, 1400, false) },
+            DecodedToken { dtw_ms: Some(1420), ..token("ernetes", 1400, 1700, false) },
+        ];
+        assert_eq!(merge_words(&tokens)[0].dtw_ms, Some(1250));
+    }
+    
+    #[test]
+    fn test_merge_words_rejoins_split_characters() {
+        // "é" split across two tokens must not turn into replacement characters
+        let mut first = token(" caf", 0, 100, false);
+        first.bytes.push(0xC3);
+        let second = DecodedToken { bytes: vec![0xA9], ..token("", 100, 200, false) };
+        
+        let words = merge_words(&[first, second]);
+        assert_eq!(words[0].text, "café");
+    }
+    
+    /// Counts the bytes each thread has allocated and not yet freed, for leak checks
+    struct CountingAllocator;
+    
+    thread_local! {
+        static NET_ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    }
+    
+    fn track_allocation(bytes: isize) {
+        // Allocations during thread teardown, after the counter is gone, aren't tracked
+        let _ = NET_ALLOCATED.try_with(|net| net.set(net.get() + bytes));
+    }
+    
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let ptr = std::alloc::System.alloc(layout);
+            if !ptr.is_null() {
+                track_allocation(layout.size() as isize);
+            }
+            ptr
+        }
+        
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout);
+            track_allocation(-(layout.size() as isize));
+        }
+    }
+    
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+    
+    fn net_allocated() -> isize {
+        NET_ALLOCATED.with(Cell::get)
+    }
+    
+    #[test]
+    fn test_free_array_releases_every_kind() {
+        let decoded = || Decoded {
+            segments: vec![DecodedSegment {
+                tokens: vec![token("[_BEG_]", 0, 0, true), token(" Hello", 0, 500, false), token(" there.", 500, 900, false)],
+                language: Some("en"),
+                ..segment(" Hello there.", 0.9, -0.1)
+            }],
+            language: Some("en"),
+            non_finite: 0,
+            text_format: 0,
+            trim: TrimPolicy::Both,
+            fallbacks: Fallbacks::default(),
+        };
+        let round_trip = || {
+            // Segments own the joined text and language besides their elements' strings
+            let segments = segment_array(decoded());
+            assert_eq!((segments.kind, segments.len), (WHISPER_ARRAY_SEGMENTS, 1));
+            assert!(!segments.text.is_null() && !segments.detected_lang.is_null());
+            assert_eq!(elements::<WhisperSegment>(&segments)[0].byte_end, 12);
+            whisper_free_array(segments);
+            
+            let words = WhisperArray::new(words_to_ffi(&decoded().segments).unwrap());
+            assert_eq!((words.kind, words.len), (WHISPER_ARRAY_WORDS, 2));
+            whisper_free_array(words);
+            
+            let tokens = WhisperArray::new(tokens_to_ffi(&decoded().segments).unwrap());
+            assert_eq!((tokens.kind, tokens.len), (WHISPER_ARRAY_TOKENS, 3));
+            whisper_free_array(tokens);
+            
+            let results = WhisperArray::new(
+                (0..2)
+                    .map(|_| transcription_result(Ok(Transcript::from_decoded(&decoded())), Duration::ZERO, 16000))
+                    .collect(),
+            );
+            assert_eq!((results.kind, results.len), (WHISPER_ARRAY_RESULTS, 2));
+            whisper_free_array(results);
+            
+            // Failed calls and panics own only their error string
+            whisper_free_array(WhisperArray::error(WHISPER_ARRAY_WORDS, WhisperError::InvalidParams, "Invalid parameters"));
+            whisper_free_array(WhisperArray::panicked("Internal error in whisper_transcribe_tokens"));
+            whisper_free_array(WhisperArray { kind: 99, ..WhisperArray::error(WHISPER_ARRAY_NONE, WhisperError::Internal, "unknown") });
+        };
+        
+        // The first round may set up per-thread state that outlives it
+        round_trip();
+        let before = net_allocated();
+        round_trip();
+        assert_eq!(net_allocated(), before);
+        
+        // The harness does see a leak
+        let leaked = CString::new("leak").unwrap().into_raw();
+        assert!(net_allocated() > before);
+        whisper_free_string(leaked);
+        assert_eq!(net_allocated(), before);
+    }
+    
+    #[test]
+    fn test_words_invalid_params() {
+        let array = whisper_transcribe_words(std::ptr::null_mut(), std::ptr::null(), 0);
+        assert!(!array.success);
+        assert!(array.ptr.is_null());
+        whisper_free_array(array);
+    }
+    
+    #[test]
+    fn test_tokens_keep_special_flag() {
+        let timestamp = DecodedToken { id: 50364, ..token("[_TT_0]", 0, 0, true) };
+        let text = DecodedToken { id: 2504, dtw_ms: Some(120), ..token(" Hello", 0, 300, false) };
+        let segments = [DecodedSegment { tokens: vec![timestamp, text], ..segment(" Hello", 0.5, -0.5) }];
+        
+        let tokens = tokens_to_ffi(&segments).unwrap();
+        let summary: Vec<_> = tokens.iter()
+            .map(|token| (token.id, token.is_special, token.t_dtw))
+            .collect();
+        assert_eq!(summary, vec![(50364, true, -1), (2504, false, 120)]);
+        assert_eq!(unsafe { CStr::from_ptr(tokens[1].text) }.to_str(), Ok(" Hello"));
+        free_token_texts(&tokens);
+        
+        let array = whisper_transcribe_tokens(std::ptr::null_mut(), std::ptr::null(), 0);
+        assert!(!array.success);
+        assert_eq!(array.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_array(array);
+    }
+    
+    #[test]
+    fn test_min_confidence_filters_segments() {
+        let segments = vec![
+            segment(" Deploy the service.", 0.91, -0.2),
+            segment(" Thank you for watching!", 0.12, -2.4),
+            segment(" Then restart it.", 0.85, -0.4),
+        ];
+        
+        let kept = filter_low_confidence(segments, -1.0);
+        let texts: Vec<&str> = kept.iter().map(|segment| segment.text.as_str()).collect();
+        assert_eq!(texts, vec![" Deploy the service.", " Then restart it."]);
+        
+        assert!(!whisper_set_min_confidence(std::ptr::null_mut(), -1.0));
+    }
+    
+    #[test]
+    fn test_lead_silence_is_removed_from_timestamps() {
+        let audio = vec![0.3f32; 1600];
+        assert!(matches!(prepend_silence(&audio, 0), Cow::Borrowed(_)));
+        let padded = prepend_silence(&audio, 250);
+        assert_eq!(padded.len(), 4000 + 1600);
+        assert!(padded[..4000].iter().all(|&sample| sample == 0.0));
+        assert_eq!(&padded[4000..], &audio[..]);
+        
+        // Decoded over 250ms of lead-in plus 100ms of audio that started 2s into the buffer
+        let mut segments = vec![
+            DecodedSegment { start_ms: 0, end_ms: 300, ..segment(" Hi", 0.9, -0.1) },
+            DecodedSegment { start_ms: 300, end_ms: 400, ..segment(" there", 0.9, -0.1) },
+        ];
+        segments[0].tokens.push(token(" Hi", 240, 300, false));
+        restore_timeline(&mut segments, 250, 100, 2000);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (2000, 2050));
+        assert_eq!((segments[0].tokens[0].start_ms, segments[0].tokens[0].end_ms), (2000, 2050));
+        assert_eq!((segments[1].start_ms, segments[1].end_ms), (2050, 2100));
+        
+        assert_eq!(TranscribeSettings::default().lead_silence_ms, 0);
+        assert!(!whisper_set_lead_silence_ms(std::ptr::null_mut(), 200));
+        let path = CString::new("/nonexistent/lead-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_set_lead_silence_ms(handle, 200));
+        assert!(!whisper_set_lead_silence_ms(handle, MAX_LEAD_SILENCE_MS + 1));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_speaker_turns_follow_long_gaps() {
+        let timed = |start_ms: i64, end_ms: i64| DecodedSegment { start_ms, end_ms, ..segment(" Hi", 0.9, -0.1) };
+        
+        // Gaps of 0, 800 (at the threshold), 801 and 200ms
+        let mut segments = vec![timed(0, 1000), timed(1000, 2000), timed(2800, 3000), timed(3801, 4000), timed(4200, 5000)];
+        mark_speaker_turns(&mut segments, 800);
+        let turns: Vec<bool> = segments.iter().map(|segment| segment.starts_new_turn).collect();
+        assert_eq!(turns, [false, false, false, true, false]);
+        
+        // Marks are recomputed, not accumulated
+        mark_speaker_turns(&mut segments, 100);
+        let turns: Vec<bool> = segments.iter().map(|segment| segment.starts_new_turn).collect();
+        assert_eq!(turns, [false, false, true, true, true]);
+        
+        assert_eq!(TranscribeSettings::default().speaker_gap_ms, None);
+        assert!(!whisper_set_speaker_gap(std::ptr::null_mut(), 800));
+        let path = CString::new("/nonexistent/turns-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_set_speaker_gap(handle, 800));
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert_eq!(WHISPER_MANAGER.with_model(&model_id, |model| model.settings.speaker_gap_ms).unwrap(), Some(800));
+        assert!(whisper_set_speaker_gap(handle, 0));
+        assert_eq!(WHISPER_MANAGER.with_model(&model_id, |model| model.settings.speaker_gap_ms).unwrap(), None);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    /// Stands in for a finished state; `None` texts fail to read
+    struct FakeSegments(Vec<Option<&'static str>>);
+    
+    impl SegmentSource for FakeSegments {
+        fn full_n_segments(&self) -> Result<i32, whisper_rs::WhisperError> {
+            Ok(self.0.len() as i32)
+        }
+        
+        fn full_get_segment_text(&self, segment: i32) -> Result<String, whisper_rs::WhisperError> {
+            self.0[segment as usize].map(str::to_string).ok_or(whisper_rs::WhisperError::NullPointer)
+        }
+        
+        fn full_get_segment_t0(&self, segment: i32) -> Result<i64, whisper_rs::WhisperError> {
+            Ok(segment as i64 * 100)
+        }
+        
+        fn full_get_segment_t1(&self, segment: i32) -> Result<i64, whisper_rs::WhisperError> {
+            Ok(segment as i64 * 100 + 90)
+        }
+        
+        fn full_n_tokens(&self, _segment: i32) -> Result<i32, whisper_rs::WhisperError> {
+            Ok(0)
+        }
+        
+        fn full_get_token_data(&self, _segment: i32, _token: i32) -> Result<WhisperTokenData, whisper_rs::WhisperError> {
+            Err(whisper_rs::WhisperError::NullPointer)
+        }
+        
+        fn full_get_token_bytes(&self, _segment: i32, _token: i32) -> Result<Vec<u8>, whisper_rs::WhisperError> {
+            Err(whisper_rs::WhisperError::NullPointer)
+        }
+    }
+    
+    #[test]
+    fn test_unreadable_segment_follows_strictness() {
+        let state = FakeSegments(vec![Some(" Open the door."), None, Some(" Thanks.")]);
+        let audio = vec![0.0f32; 3 * WHISPER_SAMPLE_RATE];
+        
+        // Lenient: the segment keeps its place and timing, marked in the text
+        let segments = collect_segments(&state, 0, &audio, false).unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1].text, UNREADABLE_SEGMENT_TEXT);
+        assert_eq!((segments[1].start_ms, segments[1].end_ms), (1000, 1900));
+        assert_eq!(join_segments(&segments, Some("en")), "Open the door. \u{FFFD} Thanks.");
+        assert_eq!(suppress_non_speech(segments).len(), 3);
+        
+        let error = collect_segments(&state, 0, &audio, true).unwrap_err();
+        assert_eq!(error.code, WhisperError::ConversionFailed);
+        assert!(error.message.contains("segment 1"), "{}", error.message);
+        
+        let readable = FakeSegments(vec![Some(" Fine.")]);
+        assert_eq!(collect_segments(&readable, 0, &audio, true).unwrap()[0].text, " Fine.");
+        
+        assert!(!TranscribeSettings::default().strict_segments);
+        assert!(!whisper_set_strict_segments(std::ptr::null_mut(), true));
+        let path = CString::new("/nonexistent/strict-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_set_strict_segments(handle, true));
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert!(WHISPER_MANAGER.with_model(&model_id, |model| model.settings.strict_segments).unwrap());
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_over_length_audio_is_refused_before_loading() {
+        assert!(!whisper_set_max_audio_secs(std::ptr::null_mut(), 1));
+        let path = CString::new("/nonexistent/max-audio-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        
+        // Within the default limit the call gets as far as loading, which fails
+        let audio = vec![0.0f32; 2 * WHISPER_SAMPLE_RATE];
+        let result = whisper_transcribe(handle, audio.as_ptr(), audio.len());
+        assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+        whisper_free_string(result.error);
+        assert_eq!(DEFAULT_MAX_AUDIO_SECS as usize * WHISPER_SAMPLE_RATE, MAX_BUFFER_SAMPLES);
+        
+        assert!(whisper_set_max_audio_secs(handle, 1));
+        let result = whisper_transcribe(handle, audio.as_ptr(), audio.len());
+        assert!(!result.success);
+        assert_eq!(result.error_code, WhisperError::AudioTooLong as i32);
+        let message = unsafe { CStr::from_ptr(result.error) }.to_str().unwrap().to_string();
+        assert!(message.contains("whisper_transcribe_long"), "{}", message);
+        whisper_free_string(result.error);
+        
+        // Exactly at the limit is allowed through
+        let result = whisper_transcribe(handle, audio.as_ptr(), WHISPER_SAMPLE_RATE);
+        assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+        whisper_free_string(result.error);
+        
+        // A range is measured by its window, not the buffer it's cut from
+        let result = whisper_transcribe_range(handle, audio.as_ptr(), audio.len(), 500, 1000);
+        assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+        whisper_free_string(result.error);
+        let result = whisper_transcribe_range(handle, audio.as_ptr(), audio.len(), 500, 0);
+        assert_eq!(result.error_code, WhisperError::AudioTooLong as i32);
+        whisper_free_string(result.error);
+        let result = whisper_transcribe_range(handle, audio.as_ptr(), audio.len(), 1000, 0);
+        assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+        whisper_free_string(result.error);
+        assert!(whisper_set_max_audio_secs(handle, 0));
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert_eq!(WHISPER_MANAGER.with_model(&model_id, |model| model.settings.max_audio_secs).unwrap(), None);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_fallbacks_counted_from_decode_callbacks() {
+        let tracker = DecodeTracker::new(Vec::new());
+        let user_data = &tracker as *const DecodeTracker as *mut c_void;
+        let mut logits = vec![0.0f32; 4];
+        let step = |logits: &mut Vec<f32>, n_tokens| unsafe {
+            filter_logits(std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null(), n_tokens, logits.as_mut_ptr(), user_data);
+        };
+        let window = || assert!(unsafe { encoder_starting(std::ptr::null_mut(), std::ptr::null_mut(), user_data) });
+        
+        // A clean run decodes each window once
+        for _ in 0..2 {
+            window();
+            (0..5).for_each(|n_tokens| step(&mut logits, n_tokens));
+        }
+        assert_eq!(tracker.fallbacks(0.0, 0.2), Fallbacks { count: 0, final_temperature: 0.0 });
+        
+        // The second window here only succeeded on its third attempt
+        tracker.reset();
+        window();
+        step(&mut logits, 0);
+        window();
+        for _ in 0..3 {
+            (0..4).for_each(|n_tokens| step(&mut logits, n_tokens));
+        }
+        let fallbacks = tracker.fallbacks(0.0, 0.2);
+        assert_eq!(fallbacks.count, 2);
+        assert!((fallbacks.final_temperature - 0.4).abs() < 1e-6);
+        
+        // Beam search and best-of filter logits from whisper.cpp's worker threads
+        tracker.reset();
+        window();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| tracker.attempt_started());
+            }
+        });
+        assert_eq!(tracker.fallbacks(0.0, 0.2).count, 3);
+        
+        let failed = WhisperResult::error(WhisperError::InferenceFailed, "failed");
+        assert_eq!((failed.fallback_count, failed.final_temperature), (0, 0.0));
+        whisper_free_string(failed.error);
+        
+        // A clean decode of every window reports no fallbacks
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-fallbacks-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            let manager = WhisperManager::new();
+            let info = model_info_for_path(path.to_str().unwrap());
+            manager.register_model("fallbacks_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+            let audio = vec![0.1f32; 45 * WHISPER_SAMPLE_RATE];
+            let fallbacks = manager.transcribe("fallbacks_id", &audio).unwrap().fallbacks;
+            assert_eq!(fallbacks, Fallbacks { count: 0, final_temperature: 0.0 });
+            manager.with_model("fallbacks_id", |model| model.settings.temperature = 0.4).unwrap();
+            let fallbacks = manager.transcribe("fallbacks_id", &audio).unwrap().fallbacks;
+            assert_eq!(fallbacks, Fallbacks { count: 0, final_temperature: 0.4 });
+            
+            let result = transcription_result(manager.transcribe("fallbacks_id", &audio), Duration::ZERO, audio.len());
+            assert_eq!((result.fallback_count, result.final_temperature), (0, 0.4));
+            for string in [result.text, result.error, result.detected_lang, result.warning] {
+                whisper_free_string(string);
+            }
+            manager.unregister("fallbacks_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_suppress_tokens_rule_out_logits() {
+        let tracker = DecodeTracker::new(vec![1, 3]);
+        let mut logits = vec![0.5f32; 5];
+        unsafe {
+            filter_logits(
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                0,
+                logits.as_mut_ptr(),
+                &tracker as *const DecodeTracker as *mut c_void,
+            );
+        }
+        assert_eq!(logits, [0.5, f32::NEG_INFINITY, 0.5, f32::NEG_INFINITY, 0.5]);
+        
+        assert!(TranscribeSettings::default().suppress_tokens.is_empty());
+        let ids = [220, 13, 11];
+        assert!(!whisper_set_suppress_tokens(std::ptr::null_mut(), ids.as_ptr(), ids.len()));
+        let path = CString::new("/nonexistent/suppress-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let stored = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.suppress_tokens.clone()).unwrap();
+        assert!(whisper_set_suppress_tokens(handle, ids.as_ptr(), ids.len()));
+        assert_eq!(stored(), ids);
+        assert!(!whisper_set_suppress_tokens(handle, std::ptr::null(), 2));
+        assert!(!whisper_set_suppress_tokens(handle, [5, -1].as_ptr(), 2));
+        assert_eq!(stored(), ids);
+        
+        // An empty list clears it, leaving decoding unfiltered
+        assert!(whisper_set_suppress_tokens(handle, std::ptr::null(), 0));
+        assert!(stored().is_empty());
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_short_input_is_padded_at_the_end() {
+        let word = vec![0.3f32; 800]; // 50ms
+        let padded = pad_short_input(Cow::Borrowed(&word));
+        assert_eq!(padded.len(), MIN_INPUT_SAMPLES);
+        assert_eq!(&padded[..800], &word[..]);
+        assert!(padded[800..].iter().all(|&sample| sample == 0.0));
+        
+        let long = vec![0.3f32; MIN_INPUT_SAMPLES];
+        assert!(matches!(pad_short_input(Cow::Borrowed(&long)), Cow::Borrowed(_)));
+        
+        // Times decoded over the padding are pulled back to the real end
+        let mut segments = vec![DecodedSegment { start_ms: 0, end_ms: 1100, ..segment(" Yes.", 0.9, -0.1) }];
+        segments[0].tokens.push(token(" Yes", 20, 1100, false));
+        clamp_segments(&mut segments, 50);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (0, 50));
+        assert_eq!((segments[0].tokens[0].start_ms, segments[0].tokens[0].end_ms), (20, 50));
+        
+        // One short word transcribes, ending where the word does
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-short-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            let manager = WhisperManager::new();
+            let info = model_info_for_path(path.to_str().unwrap());
+            manager.register_model("short_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+            let decoded = manager.transcribe_segments_with("short_id", &word, |_| {}).unwrap();
+            assert_eq!(decoded.text(), "Second 1.");
+            let spans: Vec<(i64, i64)> = decoded.segments.iter().map(|segment| (segment.start_ms, segment.end_ms)).collect();
+            assert_eq!(spans, [(0, 50)]);
+            manager.unregister("short_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_silent_fraction() {
+        // 200ms of silence followed by 200ms of a loud tone
+        let mut audio = vec![0.0f32; 3200];
+        audio.extend((0..3200).map(|i| (i as f32 * 0.1).sin() * 0.5));
+        
+        assert_eq!(silent_fraction(&audio, 0, 200), 1.0);
+        assert_eq!(silent_fraction(&audio, 200, 400), 0.0);
+        assert!((silent_fraction(&audio, 0, 400) - 0.5).abs() < 0.01);
+        
+        // Windows past the end of the audio count as silent
+        assert_eq!(silent_fraction(&audio, 1000, 2000), 1.0);
+    }
+    
+    #[test]
+    fn test_wav_errors_are_reported() {
+        let handle = Box::into_raw(Box::new(WhisperHandle { model_id: "unregistered_model".into() }));
+        let path = CString::new("/nonexistent/clip.wav").unwrap();
+        let result = whisper_transcribe_wav(handle, path.as_ptr());
+        assert!(!result.success);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        let message = unsafe { CStr::from_ptr(result.error) }.to_string_lossy().into_owned();
+        assert!(message.contains("/nonexistent/clip.wav"), "{}", message);
+        whisper_free_string(result.error);
+        
+        let result = whisper_transcribe_wav(std::ptr::null_mut(), path.as_ptr());
+        assert!(!result.success);
+        whisper_free_string(result.error);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_chunk_windows_cover_buffer() {
+        assert_eq!(chunk_windows(100, 40, 10), vec![(0, 40), (30, 70), (60, 100)]);
+        assert_eq!(chunk_windows(95, 40, 10), vec![(0, 40), (30, 70), (60, 95)]);
+        assert_eq!(chunk_windows(30, 40, 10), vec![(0, 30)]);
+        assert_eq!(chunk_windows(40, 40, 0), vec![(0, 40)]);
+    }
+    
+    #[test]
+    fn test_stitch_drops_overlap_repeats() {
+        let timed = |text: &str, start_ms: i64, end_ms: i64| DecodedSegment {
+            start_ms,
+            end_ms,
+            ..segment(text, 0.9, -0.1)
+        };
+        
+        // Two 10s chunks overlapping by 2s; the second was decoded from 8s
+        let mut stitched = Vec::new();
+        stitch_chunk(&mut stitched, vec![
+            timed(" The quick brown fox", 0, 5000),
+            timed(" jumps over the", 5000, 9600),
+        ]);
+        let mut second_chunk = vec![
+            timed(" the", 0, 400),
+            timed(" Over the lazy dog.", 400, 4000),
+        ];
+        offset_segments(&mut second_chunk, 8000);
+        stitch_chunk(&mut stitched, second_chunk);
+        
+        let summary: Vec<_> = stitched.iter()
+            .map(|segment| (segment.text.as_str(), segment.start_ms, segment.end_ms))
+            .collect();
+        assert_eq!(summary, vec![
+            (" The quick brown fox", 0, 5000),
+            (" jumps over the", 5000, 9600),
+            (" lazy dog.", 9600, 12000),
+        ]);
+        
+        // A chunk that only repeats the overlap adds nothing
+        stitch_chunk(&mut stitched, vec![timed(" lazy dog.", 11000, 12500)]);
+        assert_eq!(stitched.len(), 3);
+        
+        let array = whisper_transcribe_long(std::ptr::null_mut(), std::ptr::null(), 0, 30, 2);
+        assert!(!array.success);
+        whisper_free_array(array);
+    }
+    
+    #[test]
+    fn test_segments_keep_their_window_language() {
+        let spoken = |text: &str, start_ms: i64, end_ms: i64, language| DecodedSegment {
+            start_ms,
+            end_ms,
+            language,
+            ..segment(text, 0.9, -0.1)
+        };
+        
+        // A meeting switching from English to French between two windows
+        let mut stitched = Vec::new();
+        stitch_chunk(&mut stitched, vec![spoken(" Let's start.", 0, 4000, Some("en"))]);
+        stitch_chunk(&mut stitched, vec![spoken(" Bonjour à tous.", 5000, 9000, Some("fr"))]);
+        stitch_chunk(&mut stitched, vec![spoken(" Unknown.", 9000, 9500, None)]);
+        let decoded = Decoded { segments: stitched, language: Some("en"), non_finite: 0, text_format: 0, trim: TrimPolicy::Both, fallbacks: Fallbacks::default() };
+        
+        let array = segment_array(decoded);
+        assert!(array.success);
+        let segments = elements::<WhisperSegment>(&array);
+        let languages: Vec<Option<&str>> = segments.iter()
+            .map(|segment| (!segment.language.is_null()).then(|| unsafe { CStr::from_ptr(segment.language) }.to_str().unwrap()))
+            .collect();
+        assert_eq!(languages, [Some("en"), Some("fr"), None]);
+        assert_eq!(unsafe { CStr::from_ptr(array.detected_lang) }.to_str().unwrap(), "en");
+        whisper_free_array(array);
+    }
+    
+    #[test]
+    fn test_segments_invalid_params() {
+        let array = whisper_transcribe_segments(std::ptr::null_mut(), std::ptr::null(), 0);
+        assert!(!array.success);
+        assert!(array.ptr.is_null());
+        assert_eq!(array.len, 0);
+        whisper_free_array(array);
+    }
+    
+    #[test]
+    fn test_segment_jsonl() {
+        let mut record = segment(" He said \"hi\"\n", 0.5, -0.7);
+        record.start_ms = 1200;
+        record.end_ms = 2500;
+        
+        let line = segment_jsonl(&record);
+        assert_eq!(
+            line,
+            r#"{"text":" He said \"hi\"\n","start_ms":1200,"end_ms":2500,"confidence":0.5}"#
+        );
+        assert!(!line.contains('\n'));
+        assert_eq!(json_string("tab\there\u{1}"), r#""tab\there\u0001""#);
+    }
+    
+    /// Minimal JSON value for checking serializer output
+    #[derive(Debug, PartialEq)]
+    enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+    
+    impl Json {
+        fn get(&self, key: &str) -> Option<&Json> {
+            match self {
+                Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+                _ => None,
+            }
+        }
+    }
+    
+    /// Parse a complete JSON document, or None if it isn't valid
+    fn parse_json(input: &str) -> Option<Json> {
+        fn value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Json> {
+            match *chars.peek()? {
+                '{' => {
+                    chars.next();
+                    let mut fields = Vec::new();
+                    if chars.next_if_eq(&'}').is_some() {
+                        return Some(Json::Object(fields));
+                    }
+                    loop {
+                        let Json::String(key) = value(chars)? else { return None };
+                        chars.next_if_eq(&':')?;
+                        fields.push((key, value(chars)?));
+                        match chars.next()? {
+                            ',' => continue,
+                            '}' => return Some(Json::Object(fields)),
+                            _ => return None,
+                        }
+                    }
+                }
+                '[' => {
+                    chars.next();
+                    let mut items = Vec::new();
+                    if chars.next_if_eq(&']').is_some() {
+                        return Some(Json::Array(items));
+                    }
+                    loop {
+                        items.push(value(chars)?);
+                        match chars.next()? {
+                            ',' => continue,
+                            ']' => return Some(Json::Array(items)),
+                            _ => return None,
+                        }
+                    }
+                }
+                '"' => {
+                    chars.next();
+                    let mut text = String::new();
+                    loop {
+                        match chars.next()? {
+                            '"' => return Some(Json::String(text)),
+                            '\\' => match chars.next()? {
+                                'n' => text.push('\n'),
+                                't' => text.push('\t'),
+                                'r' => text.push('\r'),
+                                'u' => {
+                                    let hex: String = (0..4).filter_map(|_| chars.next()).collect();
+                                    text.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                                }
+                                escaped => text.push(escaped),
+                            },
+                            c if (c as u32) < 0x20 => return None,
+                            c => text.push(c),
+                        }
+                    }
+                }
+                'n' => (chars.by_ref().take(4).collect::<String>() == "null").then_some(Json::Null),
+                't' => (chars.by_ref().take(4).collect::<String>() == "true").then_some(Json::Bool(true)),
+                'f' => (chars.by_ref().take(5).collect::<String>() == "false").then_some(Json::Bool(false)),
+                _ => {
+                    let mut number = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+                        number.push(c);
+                    }
+                    number.parse().ok().map(Json::Number)
+                }
+            }
+        }
+        
+        let mut chars = input.chars().peekable();
+        let parsed = value(&mut chars)?;
+        chars.next().is_none().then_some(parsed)
+    }
+    
+    #[test]
+    fn test_transcript_json_round_trips() {
+        let mut first = segment(" Deploy \"it\"", 0.9, -0.1);
+        first.end_ms = 1200;
+        let mut second = segment(" now.\n", 0.9, -0.1);
+        (second.start_ms, second.end_ms) = (1200, 2000);
+        let decoded = Decoded { segments: vec![first, second], language: Some("en"), non_finite: 0, text_format: 0, trim: TrimPolicy::Both, fallbacks: Fallbacks::default() };
+        
+        let json = parse_json(&transcript_json(&decoded, Duration::from_millis(345))).unwrap();
+        assert_eq!(json.get("version"), Some(&Json::Number(JSON_SCHEMA_VERSION as f64)));
+        assert_eq!(json.get("text"), Some(&Json::String("Deploy \"it\" now.".to_string())));
+        assert_eq!(json.get("language"), Some(&Json::String("en".to_string())));
+        assert_eq!(json.get("inference_ms"), Some(&Json::Number(345.0)));
+        let Some(Json::Array(segments)) = json.get("segments") else { panic!("segments missing") };
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].get("start"), Some(&Json::Number(1200.0)));
+        assert_eq!(segments[1].get("end"), Some(&Json::Number(2000.0)));
+        assert_eq!(segments[1].get("text"), Some(&Json::String(" now.\n".to_string())));
+        
+        let empty = Decoded { segments: Vec::new(), language: None, non_finite: 0, text_format: 0, trim: TrimPolicy::Both, fallbacks: Fallbacks::default() };
+        let json = parse_json(&transcript_json(&empty, Duration::ZERO)).unwrap();
+        assert_eq!(json.get("language"), Some(&Json::Null));
+        assert_eq!(json.get("segments"), Some(&Json::Array(Vec::new())));
+        
+        let raw = whisper_transcribe_json(std::ptr::null_mut(), std::ptr::null(), 0);
+        let text = unsafe { CStr::from_ptr(raw) }.to_str().unwrap().to_string();
+        whisper_free_string(raw);
+        let error = parse_json(&text).unwrap();
+        let error = error.get("error").unwrap();
+        assert_eq!(error.get("code"), Some(&Json::Number(WhisperError::InvalidParams as i32 as f64)));
+    }
+    
+    #[test]
+    fn test_benchmark_real_time_factor() {
+        // Stand-in inference taking 20ms for one second of audio
+        let factor = real_time_factor(1000, || {
+            std::thread::sleep(Duration::from_millis(20));
+            Ok::<(), ManagerError>(())
+        }).unwrap();
+        assert!((0.02..1.0).contains(&factor), "{}", factor);
+        assert!(real_time_factor(1000, || Err("failed")).is_err());
+        
+        let audio = benchmark_audio(WHISPER_SAMPLE_RATE);
+        assert_eq!(audio.len(), WHISPER_SAMPLE_RATE);
+        assert!(audio.iter().any(|&s| s > 0.09) && audio.iter().all(|&s| s.abs() <= 0.1));
+        
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-bench-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            let manager = WhisperManager::new();
+            let info = model_info_for_path(path.to_str().unwrap());
+            manager.register_model("bench_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+            let factor = manager.benchmark("bench_id", 2).unwrap();
+            assert!(factor > 0.0, "{}", factor);
+            
+            // Only real transcriptions feed the CPU monitor and the history
+            assert_eq!(manager.cpu_monitor.lock().unwrap().cpu_samples.len(), 0);
+            assert!(manager.recent_history(1).is_empty());
+            manager.transcribe("bench_id", &audio).unwrap();
+            assert_eq!(manager.cpu_monitor.lock().unwrap().cpu_samples.len(), 1);
+            assert_eq!(manager.recent_history(1).len(), 1);
+            manager.unregister("bench_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+        assert_eq!(whisper_benchmark(std::ptr::null_mut(), 2), 0.0);
+    }
+    
+    #[test]
+    fn test_model_info_json() {
+        let metadata = ModelMetadata {
+            model_type: "medium".to_string(),
+            multilingual: true,
+            n_vocab: 51865,
+            n_audio_ctx: 1500,
+            n_text_ctx: 448,
+            n_mels: 80,
+        };
+        let json = parse_json(&metadata.to_json()).unwrap();
+        assert_eq!(json.get("type"), Some(&Json::String("medium".to_string())));
+        assert_eq!(json.get("is_multilingual"), Some(&Json::Bool(true)));
+        assert_eq!(json.get("n_vocab"), Some(&Json::Number(51865.0)));
+        assert_eq!(json.get("n_mels"), Some(&Json::Number(80.0)));
+        
+        let path = CString::new("/nonexistent/info-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_get_model_info(handle).is_null());
+        whisper_unregister(handle);
+        whisper_free(handle);
+        assert!(whisper_get_model_info(std::ptr::null_mut()).is_null());
+        
+        // Read from the loaded context, which the manager loads on demand
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-info-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            let manager = WhisperManager::new();
+            let info = model_info_for_path(path.to_str().unwrap());
+            manager.register_model("info_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+            let json = parse_json(&manager.model_metadata("info_id").unwrap().to_json()).unwrap();
+            assert_eq!(json.get("type"), Some(&Json::String("mock".to_string())));
+            assert_eq!(json.get("is_multilingual"), Some(&Json::Bool(true)));
+            assert_eq!(json.get("n_text_ctx"), Some(&Json::Number(448.0)));
+            assert_eq!(json.get("n_mels"), Some(&Json::Number(80.0)));
+            assert!(manager.with_model("info_id", |model| model.ctx.is_some()).unwrap());
+            manager.unregister("info_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    #[cfg(unix)]
+    fn test_jsonl_invalid_params() {
+        let result = whisper_transcribe_jsonl_to_fd(std::ptr::null_mut(), std::ptr::null(), 0, 1);
+        assert!(!result.success);
+        whisper_free_string(result.error);
+    }
+    
+    #[test]
+    fn test_confidence_retry_fires_on_low_confidence() {
+        let retry = ConfidenceRetry { threshold: 0.6, beam_size: 5 };
+        
+        // A crafted result where most tokens were barely above chance
+        let low = vec![segment(" uh the", 0.31, -1.2), segment(" sing", 0.42, -0.9)];
+        let high = vec![segment(" Hello world.", 0.93, -0.07)];
+        
+        assert!(retry.should_retry(mean_confidence(&low).unwrap()));
+        assert!(!retry.should_retry(mean_confidence(&high).unwrap()));
+        assert_eq!(mean_confidence(&[]), None);
+    }
+    
+    #[test]
+    fn test_confidence_retry_ffi() {
+        assert!(!whisper_set_confidence_retry(std::ptr::null_mut(), 0.5, 5));
+        
+        let handle = Box::into_raw(Box::new(WhisperHandle {
+            model_id: "unregistered_model".to_string(),
+        }));
+        assert!(!whisper_set_confidence_retry(handle, 0.5, 5));
+        assert!(!whisper_set_confidence_retry(handle, 0.5, 0));
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_sampling_mode_from_ffi() {
+        assert_eq!(SamplingMode::default(), SamplingMode::Greedy { best_of: 1 });
+        assert_eq!(
+            SamplingMode::from_ffi(WHISPER_SAMPLING_BEAM_SEARCH, 5),
+            Some(SamplingMode::BeamSearch { beam_size: 5, patience: -1.0 })
+        );
+        assert_eq!(SamplingMode::from_ffi(WHISPER_SAMPLING_GREEDY, 0), None);
+        assert_eq!(SamplingMode::from_ffi(7, 5), None);
+    }
+    
+    #[test]
+    fn test_best_of_is_clamped_and_applied() {
+        let path = CString::new("/nonexistent/best-of-tiny.en.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let sampling = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.sampling).unwrap();
+        assert_eq!(sampling(), SamplingMode::Greedy { best_of: 1 });
+        
+        assert!(whisper_set_best_of(handle, 3));
+        assert!(matches!(sampling().strategy(), SamplingStrategy::Greedy { best_of: 3 }));
+        assert!(whisper_set_best_of(handle, 50));
+        assert_eq!(sampling(), SamplingMode::Greedy { best_of: WHISPER_MAX_BEST_OF });
+        assert!(!whisper_set_best_of(handle, 0));
+        assert!(!whisper_set_best_of(std::ptr::null_mut(), 3));
+        assert_eq!(sampling(), SamplingMode::Greedy { best_of: WHISPER_MAX_BEST_OF });
+        
+        // A chosen beam search gives way to greedy candidates
+        assert!(whisper_set_sampling(handle, WHISPER_SAMPLING_BEAM_SEARCH, 5));
+        assert!(whisper_set_best_of(handle, 3));
+        assert_eq!(sampling(), SamplingMode::Greedy { best_of: 3 });
+        whisper_unregister(handle);
+        whisper_free(handle);
+        
+        // So does a tuned one
+        let path = CString::new("/nonexistent/best-of-medium.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let sampling = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.sampling).unwrap();
+        assert_eq!(sampling(), SamplingMode::BeamSearch { beam_size: 5, patience: -1.0 });
+        assert!(whisper_set_best_of(handle, 4));
+        assert_eq!(sampling(), SamplingMode::Greedy { best_of: 4 });
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    #[cfg(feature = "mock")] // reads back the mock's parameters
+    fn test_best_of_reaches_the_decoder() {
+        let path = std::env::temp_dir().join("whisper-mock-best-of-medium.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("best_of_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        let strategy = || {
+            manager.transcribe("best_of_id", &[0.1; 16000]).unwrap();
+            mock::last_params(|params| params.strategy.clone()).unwrap()
+        };
+        assert!(matches!(strategy(), SamplingStrategy::BeamSearch { beam_size: 5, .. }));
+        manager.with_model("best_of_id", |model| model.settings.sampling = SamplingMode::greedy_best_of(3)).unwrap();
+        assert!(matches!(strategy(), SamplingStrategy::Greedy { best_of: 3 }));
+        manager.unregister("best_of_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_beam_search_missing_model_fails_gracefully() {
+        let manager = WhisperManager::new();
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("beam_id".to_string(), "/nonexistent/beam.bin".to_string(), model_info).unwrap();
+        manager
+            .with_model("beam_id", |model| {
+                model.settings.sampling = SamplingMode::from_ffi(WHISPER_SAMPLING_BEAM_SEARCH, 5).unwrap();
+            })
+            .unwrap();
+        
+        assert!(manager.transcribe("beam_id", &[0.0; 1600]).is_err());
+        assert!(!whisper_set_sampling(std::ptr::null_mut(), WHISPER_SAMPLING_BEAM_SEARCH, 5));
+    }
+    
+    #[test]
+    fn test_carryover_takes_trailing_text_tokens() {
+        let with_ids = |ids: &[WhisperToken]| DecodedSegment {
+            tokens: ids.iter()
+                .map(|&id| DecodedToken { id, is_special: id >= 50000, ..token("", 0, 0, false) })
+                .collect(),
+            ..segment("", 0.5, -0.5)
+        };
+        let segments = [with_ids(&[50257, 10, 11, 50364]), with_ids(&[12, 13, 14, 50257])];
+        
+        // Special tokens are skipped and order is kept across segments
+        assert_eq!(carryover_tokens(&segments, 3), Some(vec![12, 13, 14]));
+        assert_eq!(carryover_tokens(&segments, 4), Some(vec![11, 12, 13, 14]));
+        assert_eq!(carryover_tokens(&segments, 64), Some(vec![10, 11, 12, 13, 14]));
+        assert_eq!(carryover_tokens(&[with_ids(&[50257])], 64), None);
+        assert_eq!(carryover_tokens(&[], 64), None);
+        
+        // The stored tail follows any initial prompt on the next call
+        assert_eq!(carryover_prompt(&[1, 2], &[12, 13], 224), vec![1, 2, 12, 13]);
+        assert_eq!(carryover_prompt(&[], &[12, 13], 224), vec![12, 13]);
+        
+        // Over budget, the carried context gives way first, then the prompt's start
+        assert_eq!(carryover_prompt(&[1, 2], &[12, 13, 14], 4), vec![1, 2, 13, 14]);
+        assert_eq!(carryover_prompt(&[1, 2, 3, 4], &[12, 13], 4), vec![1, 2, 3, 4]);
+        assert_eq!(carryover_prompt(&[1, 2, 3, 4, 5], &[12, 13], 4), vec![2, 3, 4, 5]);
+    }
+    
+    #[test]
+    fn test_context_carryover_ffi() {
+        let path = CString::new("/tmp/carryover_test_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(!handle.is_null());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let state = || WHISPER_MANAGER
+            .with_model(&model_id, |model| (model.settings.context_carryover, model.carryover.clone()))
+            .unwrap();
+        
+        assert!(whisper_set_context_carryover(handle, true));
+        WHISPER_MANAGER.with_model(&model_id, |model| model.carryover = vec![10, 11]).unwrap();
+        assert_eq!(state(), (true, vec![10, 11]));
+        
+        // Disabling forgets the stored context
+        assert!(whisper_set_context_carryover(handle, false));
+        assert_eq!(state(), (false, Vec::new()));
+        
+        assert!(!whisper_set_context_carryover(std::ptr::null_mut(), true));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_prompt_survives_unload() {
+        let path = CString::new("/tmp/prompt_test_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(!handle.is_null());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let prompt_of = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.initial_prompt.clone()).unwrap();
+        
+        let prompt = CString::new("Kubernetes, Supabase").unwrap();
+        assert!(whisper_set_prompt(handle, prompt.as_ptr()));
+        
+        // Unload and attempt a reload; the prompt belongs to the registration
+        WHISPER_MANAGER
+            .with_model(&model_id, |model| {
+                model.unload();
+                assert!(model.ensure_loaded().is_err());
+            })
+            .unwrap();
+        assert_eq!(prompt_of().as_deref(), Some("Kubernetes, Supabase"));
+        
+        let empty = CString::new("").unwrap();
+        assert!(whisper_set_prompt(handle, empty.as_ptr()));
+        assert_eq!(prompt_of(), None);
+        assert!(whisper_set_prompt(handle, prompt.as_ptr()));
+        assert!(whisper_set_prompt(handle, std::ptr::null()));
+        assert_eq!(prompt_of(), None);
+        
+        assert!(!whisper_set_prompt(std::ptr::null_mut(), prompt.as_ptr()));
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_vad_skips_silent_audio() {
+        let manager = WhisperManager::new();
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("vad_id".to_string(), "/nonexistent/vad.bin".to_string(), model_info).unwrap();
+        
+        // Without VAD the missing model is hit; with it, silence never reaches inference
+        assert!(manager.transcribe("vad_id", &[0.0; 16000]).is_err());
+        manager.with_model("vad_id", |model| model.settings.vad = Some(VadConfig::default())).unwrap();
+        let transcript = manager.transcribe("vad_id", &[0.0; 16000]).unwrap();
+        assert_eq!(transcript, Transcript { text: String::new(), segment_count: 0, language: Some("en"), non_finite: 0, fallbacks: Fallbacks::default(), no_speech: true });
+        
+        assert!(!whisper_set_vad(std::ptr::null_mut(), true));
+    }
+    
+    #[test]
+    fn test_abort_idle_model_is_noop() {
+        let manager = WhisperManager::new();
+        let model_info = ModelInfo {
+            name: "test_model".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("abort_id".to_string(), "/nonexistent/abort.bin".to_string(), model_info).unwrap();
+        
+        assert!(manager.abort("abort_id").is_ok());
+        assert_eq!(manager.abort("missing").unwrap_err().code, WhisperError::ModelNotFound);
+        
+        // The pending abort is cleared rather than cancelling the next transcription
+        let error = manager.transcribe("abort_id", &[0.0; 1600]).unwrap_err();
+        assert_eq!(error.code, WhisperError::LoadFailed);
+        assert!(!manager.with_model("abort_id", |model| model.abort.load(Ordering::Relaxed)).unwrap());
+        
+        manager.unregister("abort_id").unwrap();
+        assert!(manager.abort("abort_id").is_err());
+        assert!(!whisper_abort(std::ptr::null_mut()));
+    }
+    
+    #[test]
+    fn test_range_window_bounds() {
+        // 2.5s buffer
+        let len = 40000;
+        assert_eq!(range_window(len, 0, 0), Some((0, 0, 40000)));
+        assert_eq!(range_window(len, 500, 1000), Some((500, 1000, 16000)));
+        assert_eq!(range_window(len, 1500, 0), Some((1500, 0, 16000)));
+        assert_eq!(range_window(len, 1500, 1000), Some((1500, 1000, 16000)));
+        assert_eq!(range_window(len, 1500, 1001), None);
+        assert_eq!(range_window(len, 2500, 0), None);
+        assert_eq!(range_window(len, u32::MAX, u32::MAX), None);
+        
+        let audio = vec![0.0f32; len];
+        let handle = Box::into_raw(Box::new(WhisperHandle { model_id: "unregistered_model".to_string() }));
+        let result = whisper_transcribe_range(handle, audio.as_ptr(), audio.len(), 2000, 1000);
+        assert!(!result.success);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_range_window_moves_with_lead_silence() {
+        assert_eq!(padded_window((2000, 1000), 0), (2000, 1000));
+        assert_eq!(padded_window((2000, 0), 250), (2250, 0));
+        
+        // The moved window starts on the same sample of the caller's audio
+        let audio: Vec<f32> = (0..3 * WHISPER_SAMPLE_RATE).map(|i| i as f32).collect();
+        let padded = prepend_silence(&audio, 250);
+        let (offset, duration) = padded_window((2000, 1000), 250);
+        assert_eq!(padded[offset as usize * WHISPER_SAMPLE_RATE / 1000], audio[2 * WHISPER_SAMPLE_RATE]);
+        
+        // Whisper's times are from the start of the padded buffer, and map back onto the window
+        let end_ms = (offset + duration) as i64;
+        let mut segments = vec![DecodedSegment { start_ms: offset as i64, end_ms, ..segment(" Hi", 0.9, -0.1) }];
+        restore_timeline(&mut segments, 250, 3000, 0);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (2000, 3000));
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)] // needs a real model without the mock backend
+    fn test_range_points_at_the_same_audio_with_vad() {
+        let path = std::env::temp_dir().join("whisper-mock-range-vad-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("range_vad_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        
+        // Two seconds of silence, then two of sound; the range is the last second
+        let mut audio = vec![0.0f32; 2 * WHISPER_SAMPLE_RATE];
+        audio.extend(vec![0.1f32; 2 * WHISPER_SAMPLE_RATE]);
+        let spans = |vad: Option<VadConfig>| {
+            manager.with_model("range_vad_id", |model| model.settings.vad = vad).unwrap();
+            let decoded = manager
+                .transcribe_overriding("range_vad_id", &audio, |settings| settings.range = Some((3000, 1000)), |_| {})
+                .unwrap();
+            decoded.segments.iter().map(|segment| (segment.start_ms, segment.end_ms)).collect::<Vec<_>>()
+        };
+        assert_eq!(spans(None), [(3000, 4000)]);
+        assert_eq!(spans(Some(VadConfig::default())), [(3000, 4000)]);
+        
+        // Silence inside the window is trimmed, leaving nothing to decode
+        let decoded = manager
+            .transcribe_overriding("range_vad_id", &audio, |settings| settings.range = Some((0, 2000)), |_| {})
+            .unwrap();
+        assert!(decoded.segments.is_empty());
+        manager.unregister("range_vad_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_fixed_language_is_echoed() {
+        let path = CString::new("/nonexistent/lang-echo-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_set_vad(handle, true));
+        let silence = vec![0.0f32; 16000];
+        
+        // Silence never reaches whisper, so only the requested language can be reported
+        let array = whisper_transcribe_segments(handle, silence.as_ptr(), silence.len());
+        assert!(array.success);
+        assert_eq!(unsafe { CStr::from_ptr(array.detected_lang) }.to_str().unwrap(), "en");
+        whisper_free_array(array);
+        
+        // Translation auto-detects, and nothing was detected
+        assert!(whisper_set_translate(handle, true));
+        let result = whisper_transcribe(handle, silence.as_ptr(), silence.len());
+        assert!(result.success);
+        assert!(result.detected_lang.is_null());
+        whisper_free_string(result.text);
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_init_verified_checks_the_digest() {
+        let path = std::env::temp_dir().join("whisper-verified-tiny.bin");
+        std::fs::write(&path, b"abc").unwrap();
+        let model_path = CString::new(path.to_str().unwrap()).unwrap();
+        let init = |digest: &str, handle: &mut *mut WhisperHandle| {
+            whisper_init_verified(model_path.as_ptr(), CString::new(digest).unwrap().as_ptr(), handle)
+        };
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        
+        let mut handle = std::ptr::null_mut();
+        let wrong = "0000000000000000000000000000000000000000000000000000000000000000";
+        assert_eq!(init(wrong, &mut handle), WhisperError::ChecksumMismatch as i32);
+        assert!(handle.is_null());
+        assert_eq!(init(&abc[1..], &mut handle), WhisperError::InvalidParams as i32);
+        assert_eq!(init(&abc.replace('b', "x"), &mut handle), WhisperError::InvalidParams as i32);
+        assert!(handle.is_null());
+        
+        assert_eq!(init(&abc.to_uppercase(), &mut handle), WhisperError::None as i32);
+        assert!(!handle.is_null());
+        assert!(whisper_unregister(handle));
+        whisper_free(handle);
+        
+        std::fs::remove_file(&path).unwrap();
+        let mut handle = std::ptr::null_mut();
+        assert_eq!(init(abc, &mut handle), WhisperError::FileNotFound as i32);
+        assert_eq!(whisper_init_verified(model_path.as_ptr(), std::ptr::null(), &mut handle), WhisperError::InvalidParams as i32);
+        assert!(handle.is_null());
+    }
+    
+    #[test]
+    fn test_validate_model_file() {
+        let validate = |path: &std::path::Path| {
+            whisper_validate_model(CString::new(path.to_str().unwrap()).unwrap().as_ptr())
+        };
+        let dir = std::env::temp_dir();
+        
+        assert_eq!(validate(&dir.join("whisper-validate-missing.bin")), WhisperError::FileNotFound as i32);
+        assert_eq!(validate(&dir), WhisperError::InvalidModel as i32);
+        
+        let bogus = dir.join("whisper-validate-bogus.bin");
+        std::fs::write(&bogus, b"<html>404 Not Found</html>").unwrap();
+        assert_eq!(validate(&bogus), WhisperError::InvalidModel as i32);
+        std::fs::write(&bogus, b"lm").unwrap();
+        assert_eq!(validate(&bogus), WhisperError::InvalidModel as i32);
+        
+        let ggml = dir.join("whisper-validate-ggml.bin");
+        std::fs::write(&ggml, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        assert_eq!(validate(&ggml), WhisperError::None as i32);
+        std::fs::remove_file(&bogus).unwrap();
+        std::fs::remove_file(&ggml).unwrap();
+        
+        // Validation registers nothing
+        assert!(WHISPER_MANAGER.with_model(&model_id_for_path(ggml.to_str().unwrap()).0, |_| ()).is_err());
+        assert_eq!(whisper_validate_model(std::ptr::null()), WhisperError::InvalidParams as i32);
+    }
+    
+    #[test]
+    fn test_model_ids_are_distinct_per_path() {
+        // Both mangled to "model__models_tiny_en_bin" under the old scheme
+        let (dotted, _) = model_id_for_path("/models/tiny.en.bin");
+        let (underscored, _) = model_id_for_path("/models/tiny_en_bin");
+        assert_ne!(dotted, underscored);
+        assert_eq!(model_id_for_path("/models/tiny.en.bin").0, dotted);
+        assert_eq!(model_id_for_path("/models/./tiny.en.bin").0, dotted);
+        
+        // Re-registering the same path keeps the existing entry and its settings
+        let path = CString::new("/nonexistent/reinit-tiny.bin").unwrap();
+        let first = whisper_init(path.as_ptr());
+        assert!(whisper_set_translate(first, true));
+        let second = whisper_init(path.as_ptr());
+        let translate = WHISPER_MANAGER.with_model(&unsafe { &*second }.model_id, |model| model.settings.translate);
+        assert!(translate.unwrap());
+        
+        whisper_unregister(first);
+        whisper_free(first);
+        whisper_free(second);
+    }
+    
+    #[test]
+    fn test_silent_clip_succeeds_without_segments() {
+        let path = CString::new("/nonexistent/silent-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_set_vad(handle, true));
+        
+        let silence = vec![0.0f32; 16000];
+        let result = whisper_transcribe(handle, silence.as_ptr(), silence.len());
+        assert!(result.success);
+        assert_eq!(result.segment_count, 0);
+        assert!(!result.has_text);
+        assert_eq!(result.audio_duration_ms, 1000);
+        whisper_free_string(result.text);
+        whisper_free_string(result.detected_lang);
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_offset_segments() {
+        let mut segments = vec![segment(" Hi", 0.9, -0.1)];
+        segments[0].end_ms = 500;
+        segments[0].tokens.push(token(" Hi", 0, 500, false));
+        offset_segments(&mut segments, 1200);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (1200, 1700));
+        assert_eq!((segments[0].tokens[0].start_ms, segments[0].tokens[0].end_ms), (1200, 1700));
+    }
+    
+    #[test]
+    fn test_translate_setting() {
+        let settings = TranscribeSettings::default();
+        assert!(!settings.translate);
+        assert_eq!(settings.language(), "en");
+        
+        let path = CString::new("/nonexistent/translate_small.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert!(whisper_set_translate(handle, true));
+        
+        let settings = WHISPER_MANAGER.with_model(&model_id, |model| model.settings.clone()).unwrap();
+        assert!(settings.translate);
+        assert_eq!(settings.language(), "auto");
+        
+        assert!(!whisper_set_translate(std::ptr::null_mut(), true));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    fn params(language: Option<&CStr>, sampling_mode: i32, sampling_param: i32) -> WhisperParams {
+        WhisperParams {
+            language: language.map_or(std::ptr::null(), CStr::as_ptr),
+            temperature: 0.4,
+            sampling_mode,
+            sampling_param,
+            translate: false,
+            n_threads: 0,
+        }
+    }
+    
+    #[test]
+    fn test_params_override_one_call() {
+        let beam = unsafe { ParamOverrides::from_ffi(&params(Some(c"de"), WHISPER_SAMPLING_BEAM_SEARCH, 5)) }.unwrap();
+        let mut settings = TranscribeSettings::default();
+        beam.apply(&mut settings);
+        assert_eq!(settings.sampling, SamplingMode::BeamSearch { beam_size: 5, patience: -1.0 });
+        assert_eq!(settings.fixed_language(), Some("de"));
+        assert_eq!(settings.temperature, 0.4);
+        
+        // Detection is requested explicitly, or implied by translating without a language
+        let auto = unsafe { ParamOverrides::from_ffi(&params(Some(c"auto"), WHISPER_SAMPLING_GREEDY, 1)) }.unwrap();
+        auto.apply(&mut settings);
+        assert_eq!(settings.fixed_language(), None);
+        let translate = WhisperParams { translate: true, ..params(None, WHISPER_SAMPLING_GREEDY, 1) };
+        unsafe { ParamOverrides::from_ffi(&translate) }.unwrap().apply(&mut settings);
+        assert_eq!(settings.language(), "auto");
+        
+        for bad in [
+            params(Some(c"klingon"), WHISPER_SAMPLING_GREEDY, 1),
+            params(None, WHISPER_SAMPLING_BEAM_SEARCH, 0),
+            WhisperParams { temperature: f32::NAN, ..params(None, WHISPER_SAMPLING_GREEDY, 1) },
+            WhisperParams { n_threads: -2, ..params(None, WHISPER_SAMPLING_GREEDY, 1) },
+        ] {
+            assert_eq!(unsafe { ParamOverrides::from_ffi(&bad) }, None);
+        }
+    }
+    
+    #[test]
+    fn test_params_leave_handle_settings_alone() {
+        let path = CString::new("/nonexistent/params-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let settings = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.clone()).unwrap();
+        assert!(whisper_set_sampling(handle, WHISPER_SAMPLING_GREEDY, 2));
+        let before = settings();
+        
+        // Both the override and the NULL fallback reach the model, which fails to load
+        let audio = vec![0.0f32; 1600];
+        let beam = params(None, WHISPER_SAMPLING_BEAM_SEARCH, 5);
+        for call_params in [&beam as *const WhisperParams, std::ptr::null()] {
+            let result = whisper_transcribe_params(handle, audio.as_ptr(), audio.len(), call_params);
+            assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+            whisper_free_string(result.error);
+        }
+        assert_eq!(settings(), before);
+        
+        let bad = params(None, 7, 1);
+        let result = whisper_transcribe_params(handle, audio.as_ptr(), audio.len(), &bad);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_history_keeps_most_recent() {
+        let manager = WhisperManager::new();
+        assert!(manager.recent_history(1).is_empty());
+        
+        manager.record_history("tiny", String::new());
+        for i in 0..HISTORY_CAPACITY + 3 {
+            manager.record_history("tiny", format!("take {}", i));
+        }
+        let latest = manager.recent_history(1);
+        assert_eq!(latest[0].text, format!("take {}", HISTORY_CAPACITY + 2));
+        assert!(latest[0].timestamp_ms > 0);
+        
+        // The three oldest were evicted at the cap
+        let all = manager.recent_history(usize::MAX);
+        assert_eq!(all.len(), HISTORY_CAPACITY);
+        assert_eq!(all.last().unwrap().text, "take 3");
+        
+        let json = parse_json(&latest[0].to_json()).unwrap();
+        assert_eq!(json.get("text"), Some(&Json::String(latest[0].text.clone())));
+        assert_eq!(json.get("model_id"), Some(&Json::String("tiny".to_string())));
+        
+        manager.clear_history();
+        assert!(manager.recent_history(usize::MAX).is_empty());
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)] // needs a real model without the mock backend
+    fn test_long_and_batch_transcriptions_are_recorded() {
+        let path = std::env::temp_dir().join("whisper-mock-history-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("history_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        let texts = || manager.recent_history(usize::MAX).into_iter().map(|entry| entry.text).collect::<Vec<_>>();
+        
+        // The stitched text of every window is one entry; the mock counts seconds per window
+        let audio = vec![0.1f32; 3 * WHISPER_SAMPLE_RATE];
+        manager.transcribe_long("history_id", &audio, 2 * WHISPER_SAMPLE_RATE, 0).unwrap();
+        let long = "Second 1. Second 2. Second 1.";
+        assert_eq!(texts(), [long]);
+        
+        // Each buffer with text is an entry of its own, newest first
+        let silence = vec![0.0f32; WHISPER_SAMPLE_RATE];
+        manager.transcribe_batch("history_id", &[&audio[..WHISPER_SAMPLE_RATE], &silence, &audio]).unwrap();
+        assert_eq!(texts(), ["Second 1. Second 2. Second 3.", "Second 1.", long]);
+        manager.unregister("history_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[cfg(feature = "header")]
+    #[test]
+    fn test_generated_header_matches_exports() {
+        let header = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/whisper_node.h")).unwrap();
+        for declaration in [
+            "} WhisperResult;",
+            "int32_t error_code;",
+            "} WhisperTokenInfo;",
+            "WHISPER_ERROR_TIMED_OUT",
+            "WhisperResult whisper_transcribe_with_timeout(",
+            "char *whisper_get_history_json(",
+            "#define WHISPER_LOG_LEVEL_ERROR",
+        ] {
+            assert!(header.contains(declaration), "whisper_node.h is missing {:?}", declaration);
+        }
+    }
+    
+    #[test]
+    fn test_absurd_audio_len_is_rejected_before_slicing() {
+        // Slicing this length would be undefined behavior, caught by debug
+        // builds as an abort rather than a test failure
+        let audio = [0.0f32; 16];
+        let absurd = usize::MAX / 2;
+        let path = CString::new("/nonexistent/ceiling_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        
+        let result = whisper_transcribe(handle, audio.as_ptr(), absurd);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        let array = whisper_transcribe_long(handle, audio.as_ptr(), absurd, 30, 5);
+        assert_eq!(array.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_array(array);
+        assert!(whisper_detect_language(handle, audio.as_ptr(), absurd, std::ptr::null_mut()).is_null());
+        assert_eq!(whisper_audio_rms(audio.as_ptr(), absurd), 0.0);
+        
+        let buffers = [audio.as_ptr(), audio.as_ptr()];
+        let lengths = [absurd, audio.len()];
+        let array = whisper_transcribe_batch(handle, buffers.as_ptr(), lengths.as_ptr(), 2);
+        let results = elements::<WhisperResult>(&array);
+        assert_eq!(results[0].error_code, WhisperError::InvalidParams as i32);
+        assert_eq!(results[1].error_code, WhisperError::LoadFailed as i32);
+        whisper_free_array(array);
+        
+        // Only raised here, since the setting is shared with concurrent tests
+        assert!(whisper_set_max_audio_len(DEFAULT_MAX_AUDIO_LEN * 2));
+        assert!(!exceeds_audio_len_ceiling(DEFAULT_MAX_AUDIO_LEN + 1));
+        assert!(whisper_set_max_audio_len(0));
+        assert!(exceeds_audio_len_ceiling(DEFAULT_MAX_AUDIO_LEN + 1));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_audio_level_of_buffers() {
+        let samples = [0.0f32, -1.0, 0.0, 0.0];
+        assert_eq!(whisper_audio_peak(samples.as_ptr(), samples.len()), 1.0);
+        assert_eq!(whisper_audio_rms(samples.as_ptr(), samples.len()), 0.5);
+        assert_eq!(whisper_audio_rms(samples.as_ptr(), 0), 0.0);
+        assert_eq!(whisper_audio_rms(std::ptr::null(), 4), 0.0);
+        assert_eq!(whisper_audio_peak(std::ptr::null(), 4), 0.0);
+    }
+    
+    #[test]
+    fn test_core_preference_sets_thread_count() {
+        let hybrid = CoreCounts { performance: 6, efficiency: 2 };
+        assert_eq!(CorePreference::Performance.thread_count(8, hybrid), 6);
+        assert_eq!(CorePreference::Efficiency.thread_count(8, hybrid), 2);
+        assert_eq!(CorePreference::Auto.thread_count(8, hybrid), 6);
+        assert_eq!(CorePreference::Auto.for_batch().thread_count(8, hybrid), 2);
+        assert_eq!(CorePreference::Performance.for_batch(), CorePreference::Performance);
+        assert_eq!(CorePreference::Performance.thread_count(4, hybrid), 4);
+        
+        // Without efficiency cores, half the cores stand in for them
+        let uniform = CoreCounts { performance: 8, efficiency: 0 };
+        assert_eq!(CorePreference::Efficiency.thread_count(8, uniform), 4);
+        assert_eq!(CorePreference::Efficiency.thread_count(8, CoreCounts { performance: 1, efficiency: 0 }), 1);
+        
+        let path = CString::new("/nonexistent/cores-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert!(!whisper_set_core_preference(handle, 3));
+        assert!(whisper_set_core_preference(handle, WHISPER_CORES_EFFICIENCY));
+        let settings = WHISPER_MANAGER.with_model(&model_id, |model| model.settings.clone()).unwrap();
+        assert_eq!(settings.core_preference, CorePreference::Efficiency);
+        let info = model_info_for_path("/nonexistent/cores-tiny.bin");
+        let expected = CorePreference::Efficiency.thread_count(Tuning::for_model(&info).n_threads, *CORE_COUNTS);
+        assert_eq!(settings.thread_count(&info), expected);
+        assert_eq!(TranscribeSettings { n_threads: Some(3), ..settings }.thread_count(&info), 3);
+        assert!(!whisper_set_core_preference(std::ptr::null_mut(), WHISPER_CORES_AUTO));
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_audio_check_flags_integer_samples() {
+        assert_eq!(TranscribeSettings::default().audio_check, AudioCheck::Warn);
+        assert!(!whisper_set_audio_check(std::ptr::null_mut(), WHISPER_AUDIO_CHECK_REJECT));
+        
+        let path = CString::new("/nonexistent/range-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(!whisper_set_audio_check(handle, 3));
+        
+        // i16 samples converted to f32 without scaling
+        let audio: Vec<f32> = (0..1600).map(|i| ((i * 37) % 20000) as f32 - 10000.0).collect();
+        let code = |mode| {
+            assert!(whisper_set_audio_check(handle, mode));
+            let result = whisper_transcribe(handle, audio.as_ptr(), audio.len());
+            whisper_free_string(result.error);
+            result.error_code
+        };
+        assert_eq!(code(WHISPER_AUDIO_CHECK_REJECT), WhisperError::InvalidAudio as i32);
+        // Warnings and disabled checks go on to load the missing model
+        assert_eq!(code(WHISPER_AUDIO_CHECK_WARN), WhisperError::LoadFailed as i32);
+        assert_eq!(code(WHISPER_AUDIO_CHECK_OFF), WhisperError::LoadFailed as i32);
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_non_finite_samples_are_reported() {
+        // Every tenth sample is NaN or infinite
+        let audio: Vec<f32> = (0..16000)
+            .map(|i| match i % 20 {
+                0 => f32::NAN,
+                10 => f32::INFINITY,
+                _ => 0.01,
+            })
+            .collect();
+        
+        let transcript = |non_finite| Transcript { text: String::new(), segment_count: 0, language: None, non_finite, fallbacks: Fallbacks::default(), no_speech: true };
+        let result = transcription_result(Ok(transcript(1600)), Duration::ZERO, audio.len());
+        assert_eq!(result.non_finite_samples, 1600);
+        let warning = unsafe { CStr::from_ptr(result.warning) }.to_str().unwrap().to_string();
+        assert!(warning.starts_with("1600 of 16000 samples"), "{}", warning);
+        whisper_free_string(result.warning);
+        let result = transcription_result(Ok(transcript(100)), Duration::ZERO, audio.len());
+        assert_eq!(result.non_finite_samples, 100);
+        assert!(result.warning.is_null());
+        
+        // The transcribe path counts them and decodes the rest as usual
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-nan-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            let manager = WhisperManager::new();
+            let info = model_info_for_path(path.to_str().unwrap());
+            manager.register_model("nan_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+            let transcript = manager.transcribe("nan_id", &audio).unwrap();
+            assert_eq!((transcript.non_finite, transcript.text.as_str()), (1600, "Second 1."));
+            let clean = manager.transcribe("nan_id", &[0.01; 16000]).unwrap();
+            assert_eq!(clean.non_finite, 0);
+            manager.unregister("nan_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_reload_restores_a_loadable_model() {
+        assert!(!whisper_reload(std::ptr::null_mut()));
+        let missing = CString::new("/nonexistent/reload-tiny.bin").unwrap();
+        let handle = whisper_init(missing.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert!(!whisper_reload(handle));
+        assert_eq!(WHISPER_MANAGER.reload("unregistered_model").unwrap_err().code, WhisperError::ModelNotFound);
+        // The failed reload kept the registration
+        assert!(WHISPER_MANAGER.with_model(&model_id, |model| model.ctx.is_none()).unwrap());
+        whisper_unregister(handle);
+        whisper_free(handle);
+        
+        if cfg!(feature = "mock") {
+            let path = std::env::temp_dir().join("whisper-mock-reload-tiny.bin");
+            std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+            let manager = WhisperManager::new();
+            let info = model_info_for_path(path.to_str().unwrap());
+            let bytes = info.memory_usage;
+            manager.register_model("reload_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+            let loaded = || manager.with_model("reload_id", |model| model.ctx.is_some()).unwrap();
+            
+            // A forced unload is recovered from, holding the model's memory once
+            manager.preload("reload_id").unwrap();
+            manager.with_model("reload_id", |model| manager.unload_model(model)).unwrap();
+            assert!(!loaded());
+            manager.reload("reload_id").unwrap();
+            assert!(loaded());
+            manager.reload("reload_id").unwrap();
+            assert_eq!(manager.committed_memory.load(Ordering::SeqCst), bytes);
+            assert_eq!(manager.transcribe("reload_id", &[0.1; 16000]).unwrap().text, "Second 1.");
+            manager.unregister("reload_id").unwrap();
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_init_named_ids() {
+        let path = CString::new("/nonexistent/named-tiny.bin").unwrap();
+        let fast = whisper_init_named(path.as_ptr(), c"named_fast".as_ptr());
+        let accurate = whisper_init_named(path.as_ptr(), c"named_accurate".as_ptr());
+        assert!(!fast.is_null() && !accurate.is_null());
+        assert_eq!(unsafe { &*fast }.model_id, "named_fast");
+        
+        // Each id gets its own settings despite sharing the file
+        assert!(whisper_set_translate(fast, true));
+        let translate = |id: &str| WHISPER_MANAGER.with_model(id, |model| model.settings.translate).unwrap();
+        assert!(translate("named_fast") && !translate("named_accurate"));
+        
+        assert!(whisper_init_named(path.as_ptr(), c"named_fast".as_ptr()).is_null());
+        assert!(whisper_init_named(path.as_ptr(), c"".as_ptr()).is_null());
+        assert!(whisper_init_named(path.as_ptr(), c"named#1".as_ptr()).is_null());
+        assert!(whisper_init_named(std::ptr::null(), c"named_other".as_ptr()).is_null());
+        
+        // A freed id can be registered again, e.g. for a different file
+        whisper_unregister(fast);
+        whisper_free(fast);
+        let other = CString::new("/nonexistent/named-small.bin").unwrap();
+        let swapped = whisper_init_named(other.as_ptr(), c"named_fast".as_ptr());
+        assert!(!swapped.is_null());
+        
+        for handle in [swapped, accurate] {
+            whisper_unregister(handle);
+            whisper_free(handle);
+        }
+    }
+    
+    #[test]
+    fn test_model_memory_during_inference() {
+        let manager = Arc::new(WhisperManager::new());
+        let model_info = ModelInfo {
+            name: "tiny".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("memory_id".to_string(), "/nonexistent/memory-tiny.bin".to_string(), model_info).unwrap();
+        assert_eq!(manager.model_memory("memory_id").unwrap(), (0, 0));
+        assert_eq!(manager.model_memory("missing").unwrap_err().code, WhisperError::ModelNotFound);
+        
+        // Stand in for a load, then hold the model on another thread as inference would
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let holder = {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || {
+                let models = manager.models.read().unwrap();
+                let model = models["memory_id"].lock().unwrap();
+                model.memory.set(39 * 1024 * 1024, 41 * 1024 * 1024);
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                drop(model);
+            })
+        };
+        locked_rx.recv().unwrap();
+        assert_eq!(manager.model_memory("memory_id").unwrap(), (39 * 1024 * 1024, 41 * 1024 * 1024));
+        
+        // A writer queued behind the transcription holds off new readers of the map
+        let writer = {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || drop(manager.models.write().unwrap()))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        let (query_tx, query_rx) = std::sync::mpsc::channel();
+        {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || {
+                query_tx.send((manager.model_memory("memory_id").unwrap(), manager.current_memory_usage())).unwrap();
+            });
+        }
+        let queried = query_rx.recv_timeout(Duration::from_secs(5)).expect("Memory query waited on the map lock");
+        assert_eq!(queried, ((39 * 1024 * 1024, 41 * 1024 * 1024), 0));
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+        writer.join().unwrap();
+        
+        // The figures stand until the next unload
+        assert_eq!(manager.model_memory("memory_id").unwrap(), (39 * 1024 * 1024, 41 * 1024 * 1024));
+        manager.models.read().unwrap()["memory_id"].lock().unwrap().unload();
+        assert_eq!(manager.model_memory("memory_id").unwrap(), (0, 0));
+        manager.unregister("memory_id").unwrap();
+        assert!(manager.memory_figures.lock().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_concurrent_transcribe_and_memory_queries() {
+        let manager = Arc::new(WhisperManager::new());
+        let model_info = ModelInfo {
+            name: "tiny".to_string(),
+            size: ModelSize::Tiny,
+            memory_usage: 39 * 1024 * 1024,
+            cpu_factor: 1.0,
+        };
+        manager.register_model("stress_id".to_string(), "/nonexistent/stress-tiny.bin".to_string(), model_info).unwrap();
+        
+        // Transcribers, memory queries and a pool resizer all contend for the same locks
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        for worker in 0..6 {
+            let manager = Arc::clone(&manager);
+            let done_tx = done_tx.clone();
+            std::thread::spawn(move || {
+                for _ in 0..200 {
+                    match worker {
+                        0..=2 => assert_eq!(manager.transcribe("stress_id", &[0.0; 160]).unwrap_err().code, WhisperError::LoadFailed),
+                        // Failing loads reserve memory briefly, so only the calls are checked
+                        3 => {
+                            manager.manage_memory().unwrap();
+                            assert!(manager.current_memory_usage() <= 2 * 39 * 1024 * 1024);
+                        }
+                        4 => {
+                            manager.model_memory("stress_id").unwrap();
+                            manager.stats().unwrap();
+                        }
+                        _ => manager.resize_pool("stress_id", 1 + manager.next_slot.load(Ordering::Relaxed) % 2).unwrap(),
+                    }
+                }
+                done_tx.send(()).unwrap();
+            });
+        }
+        drop(done_tx);
+        for _ in 0..6 {
+            done_rx.recv_timeout(Duration::from_secs(30)).expect("Workers deadlocked");
+        }
+        manager.unregister("stress_id").unwrap();
+    }
+    
+    #[test]
+    fn test_transcribe_buffer_sees_latest_window() {
+        assert!(whisper_buffer_create(0).is_null());
+        assert!(whisper_buffer_create(MAX_BUFFER_SAMPLES + 1).is_null());
+        
+        let buffer = whisper_buffer_create(1600);
+        let path = CString::new("/nonexistent/buffer-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let empty = whisper_transcribe_buffer(handle, buffer);
+        assert_eq!(empty.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(empty.error);
+        
+        // 150ms of one level, then 100ms of another: the buffer keeps the last 100ms
+        let older = vec![0.1f32; 2400];
+        let newer = vec![0.2f32; 1600];
+        assert!(whisper_buffer_push(buffer, older.as_ptr(), older.len()));
+        assert!(whisper_buffer_push(buffer, newer.as_ptr(), newer.len()));
+        assert_eq!(unsafe { &*buffer }.ring.lock().unwrap().to_vec(), newer);
+        assert!(!whisper_buffer_push(std::ptr::null_mut(), newer.as_ptr(), newer.len()));
+        
+        let result = whisper_transcribe_buffer(handle, buffer);
+        assert_eq!(result.error_code, WhisperError::LoadFailed as i32);
+        whisper_free_string(result.error);
+        let result = whisper_transcribe_buffer(handle, std::ptr::null_mut());
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        
+        whisper_buffer_free(buffer);
+        whisper_buffer_free(std::ptr::null_mut());
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    fn test_system_info() {
+        let raw = whisper_system_info();
+        assert!(!raw.is_null());
+        let info = unsafe { CStr::from_ptr(raw) }.to_str().unwrap().to_string();
+        whisper_free_string(raw);
+        assert!(!info.is_empty());
+    }
+    
+    #[test]
+    fn test_abort_check_deadline() {
+        let flag = AtomicBool::new(false);
+        let unbounded = AbortCheck { flag: &flag, deadline: None };
+        let expired = AbortCheck { flag: &flag, deadline: Some(Instant::now()) };
+        let pending = AbortCheck { flag: &flag, deadline: Some(Instant::now() + Duration::from_secs(60)) };
+        assert!(!unbounded.should_abort());
+        assert!(expired.timed_out() && expired.should_abort());
+        assert!(!pending.should_abort());
+        
+        flag.store(true, Ordering::Relaxed);
+        assert!(unbounded.should_abort() && !unbounded.timed_out());
+    }
+    
+    #[test]
+    fn test_transcribe_with_timeout() {
+        let audio = vec![0.0f32; 1600];
+        let result = whisper_transcribe_with_timeout(std::ptr::null_mut(), audio.as_ptr(), audio.len(), 100);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        
+        let path = CString::new("/nonexistent/timeout-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let result = whisper_transcribe_with_timeout(handle, audio.as_ptr(), audio.len(), 0);
+        assert_eq!(result.error_code, WhisperError::InvalidParams as i32);
+        whisper_free_string(result.error);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)] // needs a real model without the mock backend
+    fn test_passed_deadline_times_out() {
+        let path = std::env::temp_dir().join("whisper-mock-timeout-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("timeout_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        let audio = vec![0.1f32; 30 * WHISPER_SAMPLE_RATE];
+        let transcribe = |timeout: Duration| {
+            manager.transcribe_overriding("timeout_id", &audio, |settings| settings.timeout = Some(timeout), |_| {})
+        };
+        
+        // A deadline already passed when inference starts stops the first window
+        let error = transcribe(Duration::ZERO).unwrap_err();
+        assert_eq!(error.code, WhisperError::TimedOut);
+        assert_eq!(manager.recent_history(1).len(), 0);
+        
+        // The overrun only failed that call
+        let decoded = transcribe(Duration::from_secs(60)).unwrap();
+        assert_eq!(decoded.segments.len(), 30);
+        manager.unregister("timeout_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_temperature_settings_are_bounded() {
+        let defaults = TranscribeSettings::default();
+        assert_eq!(defaults.temperature, 0.0);
+        assert_eq!(defaults.temperature_inc, None);
+        
+        let path = CString::new("/nonexistent/temperature_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let stored = || WHISPER_MANAGER
+            .with_model(&model_id, |model| (model.settings.temperature, model.settings.temperature_inc))
+            .unwrap();
+        
+        assert!(whisper_set_temperature(handle, 1.7));
+        assert!(whisper_set_temperature_fallback(handle, -0.3));
+        assert_eq!(stored(), (1.0, Some(0.0)));
+        
+        assert!(whisper_set_temperature(handle, 0.4));
+        assert!(whisper_set_temperature_fallback(handle, 0.2));
+        assert_eq!(stored(), (0.4, Some(0.2)));
+        
+        assert!(!whisper_set_temperature(handle, f32::NAN));
+        assert!(!whisper_set_temperature_fallback(handle, f32::NAN));
+        assert!(!whisper_set_temperature(std::ptr::null_mut(), 0.5));
+        assert_eq!(stored(), (0.4, Some(0.2)));
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)] // needs a real model without the mock backend
+    fn test_sampled_decoding_is_reproducible() {
+        let path = std::env::temp_dir().join("whisper-mock-sampled-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("sampled_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        let audio = vec![0.1f32; 8 * WHISPER_SAMPLE_RATE];
+        let transcribe = || manager.transcribe("sampled_id", &audio).unwrap().text;
+        let greedy = transcribe();
+        
+        manager.with_model("sampled_id", |model| model.settings.temperature = 0.8).unwrap();
+        let sampled = transcribe();
+        #[cfg(feature = "mock")]
+        assert_eq!(mock::last_params(|params| params.temperature), Some(0.8));
+        
+        // The sampler picked other words, and picks the same ones every run
+        assert_ne!(sampled, greedy);
+        assert_eq!(transcribe(), sampled);
+        assert_eq!(transcribe(), sampled);
+        manager.unregister("sampled_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
     
-    let path_str = unsafe {
-        match CStr::from_ptr(model_path).to_str() {
-            Ok(s) => s,
-            Err(_) => return std::ptr::null_mut(),
-        }
-    };
-    
-    // Generate unique model ID based on path
-    let model_id = format!("model_{}", path_str.replace(['/', '\\', '.'], "_"));
-    
-    // Create model info based on path
-    let model_size = ModelSize::from_name(path_str);
-    let model_info = ModelInfo {
-        name: path_str.to_string(),
-        size: model_size,
-        memory_usage: match model_size {
-            ModelSize::Tiny => 39 * 1024 * 1024,   // ~39MB
-            ModelSize::Small => 244 * 1024 * 1024,  // ~244MB
-            ModelSize::Medium => 769 * 1024 * 1024, // ~769MB
-        },
-        cpu_factor: match model_size {
-            ModelSize::Tiny => 1.0,
-            ModelSize::Small => 2.5,
-            ModelSize::Medium => 4.0,
-        },
-    };
+    #[test]
+    fn test_strip_non_speech() {
+        assert_eq!(strip_non_speech(" Hello [BLANK_AUDIO] world."), " Hello world.");
+        assert_eq!(strip_non_speech(" [BLANK_AUDIO]"), "");
+        assert_eq!(strip_non_speech(" (wind blowing) So anyway."), " So anyway.");
+        // Parentheses are always annotations in whisper output
+        assert_eq!(strip_non_speech(" That's funny (laughing)"), " That's funny");
+        assert_eq!(strip_non_speech(" an array[0 element"), " an array[0 element");
+        
+        let segments = vec![segment(" [BLANK_AUDIO]", 0.9, -0.1), segment(" Hi [MUSIC] there", 0.9, -0.1)];
+        let kept = suppress_non_speech(segments);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(join_segments(&kept, Some("en")), "Hi there");
+        
+        assert!(!TranscribeSettings::default().suppress_non_speech);
+        assert!(!whisper_set_suppress_non_speech(std::ptr::null_mut(), true));
+    }
     
-    // Register model with manager
-    match WHISPER_MANAGER.register_model(model_id.clone(), path_str.to_string(), model_info) {
-        Ok(_) => {
-            let handle = Box::new(WhisperHandle { model_id });
-            Box::into_raw(handle)
-        }
-        Err(_) => std::ptr::null_mut(),
+    #[test]
+    fn test_decoder_suppression_settings() {
+        let defaults = TranscribeSettings::default();
+        assert_eq!((defaults.suppress_blank, defaults.suppress_nst), (None, false));
+        assert!(!whisper_set_suppress_blank(std::ptr::null_mut(), false));
+        assert!(!whisper_set_suppress_nst(std::ptr::null_mut(), true));
+        
+        let path = CString::new("/nonexistent/suppress-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert!(whisper_set_suppress_blank(handle, false));
+        assert!(whisper_set_suppress_nst(handle, true));
+        let stored = WHISPER_MANAGER.with_model(&model_id, |model| model.settings.clone()).unwrap();
+        assert_eq!((stored.suppress_blank, stored.suppress_nst), (Some(false), true));
+        whisper_unregister(handle);
+        whisper_free(handle);
     }
-}
-
-/// Transcribe audio data using the whisper model manager
-/// 
-/// Implements full whisper.cpp integration with lazy loading, memory management,
-/// and CPU monitoring for automatic model downgrade.
-/// 
-/// # Safety
-/// - handle must be a valid pointer returned by whisper_init
-/// - audio_data must point to valid f32 audio samples  
-/// - audio_len must accurately represent the length of audio_data
-#[no_mangle]
-pub extern "C" fn whisper_transcribe(
-    handle: *mut WhisperHandle,
-    audio_data: *const c_float,
-    audio_len: size_t,
-) -> WhisperResult {
-    if handle.is_null() || audio_data.is_null() || audio_len <= 0 {
-        return WhisperResult {
-            success: false,
-            text: std::ptr::null_mut(),
-            error: create_error_string("Invalid parameters"),
+    
+    #[test]
+    #[cfg(feature = "mock")] // reads back the mock's parameters
+    fn test_suppression_reaches_the_decoder() {
+        let path = std::env::temp_dir().join("whisper-mock-suppress-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("suppress_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        let audio = vec![0.1f32; WHISPER_SAMPLE_RATE];
+        let transcribe = || {
+            manager.transcribe("suppress_id", &audio).unwrap();
+            mock::last_params(|params| (params.suppress_blank, params.suppress_nst)).unwrap()
         };
+        
+        // Unset keeps whisper's defaults; both settings are applied on every run
+        assert_eq!(transcribe(), (true, false));
+        manager.with_model("suppress_id", |model| {
+            model.settings.suppress_blank = Some(false);
+            model.settings.suppress_nst = true;
+        }).unwrap();
+        assert_eq!(transcribe(), (false, true));
+        manager.unregister("suppress_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
     
-    let handle_ref = unsafe { &*handle };
-    
-    // Convert audio data to slice
-    let audio_slice = unsafe {
-        std::slice::from_raw_parts(audio_data, audio_len)
-    };
+    #[test]
+    fn test_max_segment_len() {
+        assert_eq!(TranscribeSettings::default().max_segment_len, None);
+        assert!(!whisper_set_max_segment_len(std::ptr::null_mut(), 40));
+        
+        let path = CString::new("/nonexistent/maxlen-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let stored = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.max_segment_len).unwrap();
+        assert!(whisper_set_max_segment_len(handle, 40));
+        assert_eq!(stored(), Some(40));
+        assert!(!whisper_set_max_segment_len(handle, u32::MAX));
+        assert_eq!(stored(), Some(40));
+        assert!(whisper_set_max_segment_len(handle, 0));
+        assert_eq!(stored(), None);
+        whisper_unregister(handle);
+        whisper_free(handle);
+    }
     
-    // Perform transcription using the manager
-    match WHISPER_MANAGER.transcribe(&handle_ref.model_id, audio_slice) {
-        Ok(text) => {
-            // Check if model downgrade is suggested
-            if let Some(suggested_model) = WHISPER_MANAGER.suggest_model_downgrade(&handle_ref.model_id) {
-                eprintln!("Whisper: High CPU usage detected, consider switching to {} model", suggested_model);
-            }
-            
-            match CString::new(text) {
-                Ok(c_string) => WhisperResult {
-                    success: true,
-                    text: c_string.into_raw(),
-                    error: std::ptr::null_mut(),
-                },
-                Err(_) => WhisperResult {
-                    success: false,
-                    text: std::ptr::null_mut(),
-                    error: create_error_string("Failed to convert transcription result"),
-                },
-            }
-        }
-        Err(error_msg) => WhisperResult {
-            success: false,
-            text: std::ptr::null_mut(),
-            error: create_error_string(&error_msg),
-        },
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)] // needs a real model without the mock backend
+    fn test_segments_respect_the_length_cap() {
+        let path = std::env::temp_dir().join("whisper-mock-maxlen-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("maxlen_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        let audio = vec![0.1f32; 3 * WHISPER_SAMPLE_RATE];
+        let segments_capped_at = |max_len: Option<i32>| {
+            manager.with_model("maxlen_id", |model| model.settings.max_segment_len = max_len).unwrap();
+            manager.transcribe_segments_with("maxlen_id", &audio, |_| {}).unwrap().segments
+        };
+        
+        // Uncapped, each second is one " Second N." segment
+        assert_eq!(segments_capped_at(None).len(), 3);
+        
+        // " Second 1." is over 8 characters, so it splits between its words
+        let capped = segments_capped_at(Some(8));
+        let texts: Vec<&str> = capped.iter().map(|segment| segment.text.as_str()).collect();
+        assert_eq!(texts, [" Second", " 1.", " Second", " 2.", " Second", " 3."]);
+        assert!(capped.windows(2).all(|pair| pair[0].end_ms <= pair[1].start_ms));
+        #[cfg(feature = "mock")]
+        assert_eq!(
+            mock::last_params(|params| (params.token_timestamps, params.max_len, params.split_on_word)),
+            Some((true, 8, true))
+        );
+        
+        // A cap under one word's length still splits only between words
+        assert!(segments_capped_at(Some(1)).iter().all(|segment| segment.text.split_whitespace().count() == 1));
+        manager.unregister("maxlen_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
-}
-
-/// Free whisper context
-#[no_mangle]
-pub extern "C" fn whisper_free(handle: *mut WhisperHandle) {
-    if !handle.is_null() {
-        unsafe {
-            drop(Box::from_raw(handle));
-        }
+    
+    #[test]
+    fn test_collapse_repetitions() {
+        assert_eq!(collapse_repetitions(" Thanks for watching. you you you you you", 3), " Thanks for watching. you");
+        assert_eq!(collapse_repetitions("See you. Thank you. Thank you. Thank you. Thank you.", 3), "See you. Thank you.");
+        assert_eq!(collapse_repetitions("Okay. Bye now, bye now, BYE NOW, bye now!", 3), "Okay. bye now!");
+        
+        // Punctuation-only words never repeat, and don't break a run of words
+        assert_eq!(collapse_repetitions("So - ... - ... - ... - ... -", 3), "So - ... - ... - ... - ... -");
+        assert_eq!(collapse_repetitions("A - B - C - D - E", 3), "A - B - C - D - E");
+        assert_eq!(collapse_repetitions("Fine. you - you - you - you ...", 3), "Fine. you ...");
+        assert_eq!(collapse_repetitions("no no no", 3), "no no no");
+        assert!(!whisper_set_collapse_repeats(std::ptr::null_mut(), true));
     }
-}
-
-/// Free result strings
-#[no_mangle]
-pub extern "C" fn whisper_free_string(ptr: *mut c_char) {
-    if !ptr.is_null() {
-        unsafe {
-            drop(CString::from_raw(ptr));
+    
+    #[test]
+    fn test_collapse_repetitions_keeps_short_runs() {
+        for text in [" No no no, not like that.", " I said no no no", "very very very good", "", " Hello."] {
+            assert_eq!(collapse_repetitions(text, 3), text);
         }
+        // Repetition only matters at the end
+        let inner = "you you you you and then it stopped";
+        assert_eq!(collapse_repetitions(inner, 3), inner);
+        assert!(!TranscribeSettings::default().collapse_repeats);
     }
-}
-
-/// Get current memory usage of all loaded models
-#[no_mangle]
-pub extern "C" fn whisper_get_memory_usage() -> u64 {
-    WHISPER_MANAGER.current_memory_usage()
-}
-
-/// Force memory cleanup by unloading idle models
-#[no_mangle]
-pub extern "C" fn whisper_cleanup_memory() -> bool {
-    WHISPER_MANAGER.manage_memory().is_ok()
-}
-
-/// Get average CPU usage for performance monitoring
-#[no_mangle]
-pub extern "C" fn whisper_get_avg_cpu_usage() -> c_float {
-    if let Ok(monitor) = WHISPER_MANAGER.cpu_monitor.lock() {
-        monitor.average_cpu_usage()
-    } else {
-        0.0
-    }
-}
-
-/// Check if model downgrade is recommended for given model
-#[no_mangle]
-pub extern "C" fn whisper_check_downgrade_needed(handle: *mut WhisperHandle) -> bool {
-    if handle.is_null() {
-        return false;
+    
+    #[test]
+    fn test_text_format_flags() {
+        let format = |text: &str, flags| format_text(text.to_string(), flags);
+        assert_eq!(format("Let me see.", 0), "Let me see.");
+        
+        assert_eq!(format("Let me see the API.", WHISPER_TEXT_LOWERCASE), "let me see the api.");
+        assert_eq!(format("Let me see...", WHISPER_TEXT_STRIP_TRAILING_PUNCT), "Let me see");
+        assert_eq!(format("Call it (soon).", WHISPER_TEXT_STRIP_TRAILING_PUNCT), "Call it (soon)");
+        assert_eq!(format("See you? ", WHISPER_TEXT_STRIP_TRAILING_PUNCT), "See you");
+        assert_eq!(format("Let me see.", WHISPER_TEXT_STRIP_LEADING_CAPITAL), "let me see.");
+        assert_eq!(format("Élan vital.", WHISPER_TEXT_STRIP_LEADING_CAPITAL), "élan vital.");
+        
+        // The pronoun and acronyms keep their capitals
+        assert_eq!(format("I think so.", WHISPER_TEXT_STRIP_LEADING_CAPITAL), "I think so.");
+        assert_eq!(format("I'm done.", WHISPER_TEXT_STRIP_LEADING_CAPITAL), "I'm done.");
+        assert_eq!(format("API keys rotate.", WHISPER_TEXT_STRIP_LEADING_CAPITAL), "API keys rotate.");
+        assert_eq!(format("", WHISPER_TEXT_ALL), "");
+        
+        assert_eq!(format("Rename the Variable.", WHISPER_TEXT_STRIP_TRAILING_PUNCT | WHISPER_TEXT_STRIP_LEADING_CAPITAL), "rename the Variable");
+        assert_eq!(format("I Said So!", WHISPER_TEXT_ALL), "i said so");
+        
+        // Applied to the joined text only
+        let decoded = Decoded {
+            segments: vec![segment(" Open the file.", 0.9, -0.1), segment(" Save it.", 0.9, -0.1)],
+            language: Some("en"),
+            non_finite: 0,
+            text_format: WHISPER_TEXT_STRIP_TRAILING_PUNCT | WHISPER_TEXT_STRIP_LEADING_CAPITAL,
+            trim: TrimPolicy::Both,
+            fallbacks: Fallbacks::default(),
+        };
+        assert_eq!(decoded.text(), "open the file. Save it");
+        assert_eq!(decoded.segments[1].text, " Save it.");
+        
+        assert_eq!(TranscribeSettings::default().text_format, 0);
+        assert!(!whisper_set_text_format(std::ptr::null_mut(), WHISPER_TEXT_LOWERCASE));
+        let path = CString::new("/nonexistent/format-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(whisper_set_text_format(handle, WHISPER_TEXT_LOWERCASE | WHISPER_TEXT_STRIP_TRAILING_PUNCT));
+        assert!(!whisper_set_text_format(handle, 8));
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert_eq!(WHISPER_MANAGER.with_model(&model_id, |model| model.settings.text_format).unwrap(), 3);
+        whisper_unregister(handle);
+        whisper_free(handle);
     }
     
-    let handle_ref = unsafe { &*handle };
-    WHISPER_MANAGER.suggest_model_downgrade(&handle_ref.model_id).is_some()
-}
-
-/// Get suggested downgrade model name (caller must free result)
-#[no_mangle]
-pub extern "C" fn whisper_get_suggested_model(handle: *mut WhisperHandle) -> *mut c_char {
-    if handle.is_null() {
-        return std::ptr::null_mut();
+    #[test]
+    fn test_trim_policies_over_padded_text() {
+        let decoded = |trim| Decoded {
+            segments: vec![segment(" Hello", 0.9, -0.1), segment(" world.  ", 0.9, -0.1)],
+            language: Some("en"),
+            non_finite: 0,
+            text_format: 0,
+            trim,
+            fallbacks: Fallbacks::default(),
+        };
+        assert_eq!(decoded(TrimPolicy::Both).text(), "Hello world.");
+        assert_eq!(decoded(TrimPolicy::None).text(), " Hello world.  ");
+        assert_eq!(decoded(TrimPolicy::LeadingOnly).text(), "Hello world.  ");
+        assert_eq!(decoded(TrimPolicy::TrailingOnly).text(), " Hello world.");
+        
+        // Segment ranges move past the kept leading space
+        let (text, ranges) = decoded(TrimPolicy::TrailingOnly).text_with_ranges();
+        assert_eq!(&text[ranges[0].clone()], "Hello");
+        assert_eq!(&text[ranges[1].clone()], "world.");
+        
+        let silent = Decoded { segments: vec![segment("  ", 0.9, -0.1)], ..decoded(TrimPolicy::None) };
+        assert_eq!(silent.text(), "");
+        
+        let path = CString::new("/nonexistent/trim-tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let trim = || WHISPER_MANAGER.with_model(&model_id, |model| model.settings.trim).unwrap();
+        assert_eq!(trim(), TrimPolicy::Both);
+        assert!(whisper_set_trim(handle, WHISPER_TRIM_TRAILING));
+        assert_eq!(trim(), TrimPolicy::TrailingOnly);
+        assert!(!whisper_set_trim(handle, 4));
+        assert!(!whisper_set_trim(std::ptr::null_mut(), WHISPER_TRIM_NONE));
+        assert_eq!(trim(), TrimPolicy::TrailingOnly);
+        whisper_unregister(handle);
+        whisper_free(handle);
     }
     
-    let handle_ref = unsafe { &*handle };
-    if let Some(suggested) = WHISPER_MANAGER.suggest_model_downgrade(&handle_ref.model_id) {
-        match CString::new(suggested) {
-            Ok(c_string) => c_string.into_raw(),
-            Err(_) => std::ptr::null_mut(),
-        }
-    } else {
-        std::ptr::null_mut()
+    #[test]
+    fn test_join_segments() {
+        let segments = vec![segment(" Hello", 0.9, -0.1), segment("world.", 0.9, -0.1)];
+        assert_eq!(join_segments(&segments, Some("en")), "Hello world.");
+        assert_eq!(join_segments(&[], Some("en")), "");
     }
-}
-
-/// Helper function to create error C strings
-fn create_error_string(msg: &str) -> *mut c_char {
-    match CString::new(msg) {
-        Ok(c_string) => c_string.into_raw(),
-        Err(_) => std::ptr::null_mut(),
+    
+    #[test]
+    fn test_join_segments_keeps_single_spaces() {
+        // Whisper's own leading spaces must not double up with the separator
+        let segments = vec![
+            segment(" Deploy the service.", 0.9, -0.1),
+            segment(" Then  restart it. ", 0.9, -0.1),
+            segment(" ", 0.9, -0.1),
+            segment(" Done.", 0.9, -0.1),
+        ];
+        assert_eq!(join_segments(&segments, Some("en")), "Deploy the service. Then restart it. Done.");
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
     
     #[test]
-    fn test_ffi_safety() {
-        // Test null pointer handling
-        let result = whisper_transcribe(
-            std::ptr::null_mut(),
-            std::ptr::null(),
-            0
-        );
-        assert!(!result.success);
+    fn test_join_segments_spaceless_scripts() {
+        let segments = vec![
+            segment("今日は晴れです。", 0.9, -0.1),
+            segment(" 明日は雨でしょう。", 0.9, -0.1),
+            segment("Slackで連絡します。", 0.9, -0.1),
+        ];
+        assert_eq!(join_segments(&segments, Some("ja")), "今日は晴れです。明日は雨でしょう。Slackで連絡します。");
         
-        // Test context creation with invalid path
-        let invalid_path = CString::new("invalid_path").unwrap();
-        let handle = whisper_init(invalid_path.as_ptr());
-        assert!(handle.is_null());
+        // Without a language, only boundaries between space-less characters stay joined
+        assert_eq!(join_segments(&segments, None), "今日は晴れです。明日は雨でしょう。 Slackで連絡します。");
+        let latin = vec![segment(" Hello", 0.9, -0.1), segment(" world.", 0.9, -0.1)];
+        assert_eq!(join_segments(&latin, None), "Hello world.");
     }
     
     #[test]
-    fn test_model_info_creation() {
-        let tiny_info = ModelInfo {
-            name: "tiny.en".to_string(),
-            size: ModelSize::Tiny,
-            memory_usage: 39 * 1024 * 1024,
-            cpu_factor: 1.0,
+    fn test_segment_byte_ranges_index_joined_text() {
+        let decoded = |text_format| Decoded {
+            segments: vec![
+                segment(" İstanbul'da  kar var.", 0.9, -0.1),
+                segment("今日は晴れです。", 0.9, -0.1),
+                segment(" ", 0.9, -0.1),
+                segment(" Ça va?", 0.9, -0.1),
+            ],
+            language: None,
+            non_finite: 0,
+            text_format,
+            trim: TrimPolicy::Both,
+            fallbacks: Fallbacks::default(),
+        };
+        let slices = |decoded: Decoded| {
+            let array = segment_array(decoded);
+            assert!(array.success);
+            let text = unsafe { CStr::from_ptr(array.text) }.to_str().unwrap().to_string();
+            let segments = elements::<WhisperSegment>(&array);
+            let slices: Vec<String> = segments.iter()
+                .map(|segment| text[segment.byte_start as usize..segment.byte_end as usize].to_string())
+                .collect();
+            whisper_free_array(array);
+            (text, slices)
         };
         
-        assert_eq!(tiny_info.size, ModelSize::Tiny);
-        assert_eq!(tiny_info.size.memory_limit(), 100 * 1024 * 1024);
-        assert_eq!(tiny_info.size.cpu_threshold(), 80.0);
+        let (text, pieces) = slices(decoded(0));
+        assert_eq!(text, "İstanbul'da kar var. 今日は晴れです。 Ça va?");
+        assert_eq!(pieces, ["İstanbul'da kar var.", "今日は晴れです。", "", "Ça va?"]);
+        
+        // Offsets past the header's uint32_t are refused rather than wrapped
+        let max = u32::MAX as usize;
+        assert_eq!(byte_range_u32(&(max - 1..max)), Some((u32::MAX - 1, u32::MAX)));
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(byte_range_u32(&(max..max + 1)), None);
+        
+        // Lowercasing "İ" takes a byte more, and the stripped tail shortens the last range
+        let (text, pieces) = slices(decoded(WHISPER_TEXT_ALL));
+        assert_eq!(text, "i̇stanbul'da kar var. 今日は晴れです。 ça va");
+        assert_eq!(pieces, ["i̇stanbul'da kar var.", "今日は晴れです。", "", "ça va"]);
+        let (text, pieces) = slices(decoded(WHISPER_TEXT_STRIP_LEADING_CAPITAL));
+        assert!(text.starts_with("i̇stanbul"));
+        assert_eq!(pieces[3], "Ça va?");
     }
     
+    /// Held by tests that load into the global manager or expect nothing loaded there
+    static GLOBAL_MODELS: Mutex<()> = Mutex::new(());
+    
     #[test]
-    fn test_model_size_from_name() {
-        assert_eq!(ModelSize::from_name("tiny.en"), ModelSize::Tiny);
-        assert_eq!(ModelSize::from_name("small.en"), ModelSize::Small);
-        assert_eq!(ModelSize::from_name("medium.en"), ModelSize::Medium);
-        assert_eq!(ModelSize::from_name("unknown"), ModelSize::Medium); // Default
+    fn test_unregister_releases_model() {
+        let _global = GLOBAL_MODELS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let path = CString::new("/nonexistent/unregister_tiny.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert!(!handle.is_null());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        
+        let audio = vec![0.0f32; 1600];
+        let result = whisper_transcribe(handle, audio.as_ptr(), audio.len());
+        assert!(!result.success);
+        whisper_free_string(result.error);
+        
+        assert!(whisper_unregister(handle));
+        assert!(WHISPER_MANAGER.with_model(&model_id, |_| ()).is_err());
+        assert!(!whisper_unregister(handle));
+        assert_eq!(whisper_get_memory_usage(), 0);
+        whisper_free(handle);
     }
     
     #[test]
-    fn test_cpu_monitor() {
-        let mut monitor = CpuMonitor::new();
-        
-        // Test initial state
-        assert_eq!(monitor.average_cpu_usage(), 0.0);
-        assert!(!monitor.should_downgrade(80.0));
+    fn test_manager_reset() {
+        let manager = WhisperManager::new();
+        manager.set_default_idle_timeout(Duration::from_secs(5));
+        for id in ["a", "b"] {
+            let model_info = ModelInfo {
+                name: "test_model".to_string(),
+                size: ModelSize::Tiny,
+                memory_usage: 39 * 1024 * 1024,
+                cpu_factor: 1.0,
+            };
+            manager.register_model(id.to_string(), format!("/tmp/{}.bin", id), model_info).unwrap();
+        }
         
-        // Add some CPU usage samples
-        monitor.record_cpu_usage(50.0);
-        monitor.record_cpu_usage(60.0);
-        monitor.record_cpu_usage(70.0);
+        manager.reset();
+        assert!(manager.models.read().unwrap().is_empty());
+        assert_eq!(manager.default_idle_timeout_secs.load(Ordering::Relaxed), DEFAULT_IDLE_TIMEOUT_SECS);
+        assert!(manager.unregister("a").is_err());
+    }
+    
+    #[test]
+    fn test_second_medium_load_is_refused() {
+        let manager = WhisperManager::new();
+        let medium = 769 * 1024 * 1024;
+        for id in ["medium_a", "medium_b"] {
+            let model_info = ModelInfo {
+                name: format!("{}.en", id),
+                size: ModelSize::Medium,
+                memory_usage: medium,
+                cpu_factor: 4.0,
+            };
+            manager.register_model(id.to_string(), format!("/nonexistent/{}.bin", id), model_info).unwrap();
+        }
         
-        assert_eq!(monitor.average_cpu_usage(), 60.0);
-        assert!(!monitor.should_downgrade(80.0));
+        // The first medium model is mid-load, so it has reserved its memory
+        assert!(manager.try_reserve_memory(medium));
+        assert_eq!(manager.current_memory_usage(), medium);
         
-        // Add high CPU usage
-        monitor.record_cpu_usage(90.0);
-        monitor.record_cpu_usage(95.0);
+        let error = manager.preload("medium_b").unwrap_err();
+        assert_eq!(error.code, WhisperError::LoadFailed);
+        assert!(error.message.contains("exceed"), "{}", error);
+        assert_eq!(manager.current_memory_usage(), medium);
         
-        assert!(monitor.average_cpu_usage() > 70.0);
-        assert!(monitor.should_downgrade(80.0));
+        // Once the first finishes unloading, the second may try; a failed load releases its reservation
+        manager.release_memory(medium);
+        let error = manager.preload("medium_b").unwrap_err();
+        assert!(error.message.starts_with("Failed to load model"), "{}", error);
+        assert_eq!(manager.current_memory_usage(), 0);
     }
     
     #[test]
-    fn test_whisper_model_lifecycle() {
+    fn test_mislabeled_tiny_model_is_flagged() {
+        let manager = WhisperManager::new();
         let model_info = ModelInfo {
-            name: "test.en".to_string(),
+            name: "ggml-tiny.bin".to_string(),
             size: ModelSize::Tiny,
-            memory_usage: 50 * 1024 * 1024,
+            memory_usage: 244 * 1024 * 1024, // really a small model
             cpu_factor: 1.0,
         };
+        assert!(model_info.exceeds_size_limit(model_info.memory_usage));
+        manager.register_model("mislabeled".to_string(), "/nonexistent/ggml-tiny.bin".to_string(), model_info).unwrap();
         
-        let mut model = WhisperModel::new("/tmp/test".to_string(), model_info);
+        let error = manager.preload("mislabeled").unwrap_err();
+        assert_eq!(error.code, WhisperError::LoadFailed);
+        assert!(error.message.contains("size class"), "{}", error);
+        assert_eq!(manager.current_memory_usage(), 0);
         
-        // Initially unloaded
-        assert_eq!(model.memory_usage(), 0);
-        assert!(model.ctx.is_none());
+        // The built-in estimates stay within tolerance of their classes
+        for (size, estimate) in [(ModelSize::Tiny, 39), (ModelSize::Small, 244), (ModelSize::Medium, 769), (ModelSize::Large, 3072)] {
+            let info = ModelInfo { name: String::new(), size, memory_usage: estimate * 1024 * 1024, cpu_factor: 1.0 };
+            assert!(!info.exceeds_size_limit(info.memory_usage));
+        }
+    }
+    
+    #[test]
+    fn test_model_limit_ffi() {
+        assert_eq!(whisper_get_model_limit(std::ptr::null_mut()), 0);
         
-        // Should unload after timeout (simulated)
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        model.idle_timeout = std::time::Duration::from_millis(5);
-        assert!(model.should_unload());
+        let path = CString::new("/nonexistent/limit-small.en.bin").unwrap();
+        let handle = whisper_init(path.as_ptr());
+        assert_eq!(whisper_get_model_limit(handle), 400 * 1024 * 1024);
+        whisper_unregister(handle);
+        whisper_free(handle);
     }
     
     #[test]
@@ -615,24 +9741,205 @@ mod tests {
         assert_eq!(ModelSize::Tiny.memory_limit(), 100 * 1024 * 1024);
         assert_eq!(ModelSize::Small.memory_limit(), 400 * 1024 * 1024);
         assert_eq!(ModelSize::Medium.memory_limit(), 700 * 1024 * 1024);
+        assert_eq!(ModelSize::Large.memory_limit(), 4 * 1024 * 1024 * 1024);
     }
     
     #[test]
-    #[ignore] // Enable when real transcription is implemented
+    #[cfg_attr(not(feature = "mock"), ignore)] // needs a real model without the mock backend
     fn test_successful_transcription() {
-        // This test should be enabled once whisper_init works with valid models
-        // and whisper_transcribe performs actual transcription
+        let _global = GLOBAL_MODELS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        // The mock backend only checks the header, so any ggml magic will do
+        let path = std::env::temp_dir().join("whisper-mock-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let model_path = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = whisper_init(model_path.as_ptr());
+        assert!(!handle.is_null());
+        
+        let test_audio = vec![0.1f32; 16000]; // 1 second of audio at 16kHz
+        let result = whisper_transcribe(handle, test_audio.as_ptr(), test_audio.len());
+        assert!(result.success);
+        assert!(!result.text.is_null());
+        assert!(whisper_is_loaded(handle));
+        
+        #[cfg(feature = "mock")]
+        {
+            let text = unsafe { CStr::from_ptr(result.text) }.to_str().unwrap();
+            assert_eq!(text, "Second 1.");
+            assert_eq!((result.segment_count, result.audio_duration_ms), (1, 1000));
+            
+            let long = vec![0.1f32; 16000 * 5 / 2];
+            let segments = WHISPER_MANAGER.transcribe_segments_with(&unsafe { &*handle }.model_id, &long, |_| {}).unwrap();
+            let spans: Vec<(i64, i64)> = segments.segments.iter().map(|segment| (segment.start_ms, segment.end_ms)).collect();
+            assert_eq!(spans, [(0, 1000), (1000, 2000), (2000, 2500)]);
+            assert_eq!(segments.text(), "Second 1. Second 2. Second 3.");
+            assert!(!result.no_speech);
+            
+            let silence = vec![0.0f32; 16000];
+            let quiet = whisper_transcribe(handle, silence.as_ptr(), silence.len());
+            assert!(quiet.success && quiet.no_speech && !quiet.has_text);
+            whisper_free_string(quiet.text);
+            whisper_free_string(quiet.detected_lang);
+        }
+        
+        whisper_free_string(result.text);
+        whisper_free_string(result.detected_lang);
+        whisper_unregister(handle);
+        whisper_free(handle);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)]
+    fn test_auto_language_labels_each_segment() {
+        let _global = GLOBAL_MODELS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let path = std::env::temp_dir().join("whisper-mock-auto-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let model_path = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = whisper_init(model_path.as_ptr());
+        let model_id = unsafe { &*handle }.model_id.clone();
+        let languages = |array: WhisperArray| {
+            assert!(array.success);
+            let segments = elements::<WhisperSegment>(&array);
+            let languages: Vec<String> = segments.iter()
+                .map(|segment| unsafe { CStr::from_ptr(segment.language) }.to_str().unwrap().to_string())
+                .collect();
+            whisper_free_array(array);
+            languages
+        };
+        
+        // The mock detects English in every run
+        WHISPER_MANAGER.with_model(&model_id, |model| model.settings.language = Some("auto")).unwrap();
+        let audio = vec![0.1f32; 16000 * 3];
+        assert_eq!(languages(whisper_transcribe_segments(handle, audio.as_ptr(), audio.len())), ["en", "en", "en"]);
+        let long = languages(whisper_transcribe_long(handle, audio.as_ptr(), audio.len(), 2, 1));
+        assert!(!long.is_empty());
+        assert!(long.iter().all(|language| language == "en"));
+        
+        // A fixed language labels every segment without detection
+        WHISPER_MANAGER.with_model(&model_id, |model| model.settings.language = Some("de")).unwrap();
+        assert_eq!(languages(whisper_transcribe_segments(handle, audio.as_ptr(), audio.len())), ["de", "de", "de"]);
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)]
+    fn test_build_default_threads_apply_without_a_setter() {
+        let _global = GLOBAL_MODELS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let path = std::env::temp_dir().join("whisper-mock-threads-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let model_path = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = whisper_init(model_path.as_ptr());
+        
+        // Tiny models get 4 threads unless the build baked in another default
+        let cores = std::thread::available_parallelism().map_or(i32::MAX, |cores| cores.get() as i32);
+        let tuned = Tuning::for_model(&model_info_for_path(path.to_str().unwrap())).n_threads;
+        assert_eq!(tuned, DEFAULT_THREADS.unwrap_or(4).min(cores));
+        
+        let audio = vec![0.1f32; 16000];
+        let result = whisper_transcribe(handle, audio.as_ptr(), audio.len());
+        assert!(result.success);
+        whisper_free_string(result.text);
+        whisper_free_string(result.detected_lang);
+        
+        let raw = whisper_get_effective_params(handle);
+        let json = parse_json(unsafe { CStr::from_ptr(raw) }.to_str().unwrap()).unwrap();
+        whisper_free_string(raw);
+        let expected = CorePreference::Auto.thread_count(tuned, *CORE_COUNTS);
+        assert_eq!(json.get("n_threads"), Some(&Json::Number(expected as f64)));
+        
+        assert_eq!(parse_thread_count("12"), 12);
+        whisper_unregister(handle);
+        whisper_free(handle);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)]
+    fn test_dtw_reloads_the_context_and_fills_word_times() {
+        let _global = GLOBAL_MODELS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let path = std::env::temp_dir().join("whisper-mock-dtw-tiny.en.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let model_path = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = whisper_init(model_path.as_ptr());
+        let audio = vec![0.1f32; 16000 * 2];
+        let dtw_times = || {
+            let array = whisper_transcribe_words(handle, audio.as_ptr(), audio.len());
+            assert!(array.success);
+            let words = elements::<WhisperWord>(&array);
+            let times: Vec<(i64, i64)> = words.iter().map(|word| (word.start_ms, word.t_dtw)).collect();
+            whisper_free_array(array);
+            times
+        };
+        
+        assert!(dtw_times().iter().all(|&(_, t_dtw)| t_dtw == -1));
+        assert!(whisper_is_loaded(handle));
+        
+        // The loaded context has no heads, so the next transcription reloads it
+        assert!(whisper_set_dtw(handle, WHISPER_DTW_AUTO));
+        let times = dtw_times();
+        assert_eq!(times.len(), 4);
+        assert!(times.iter().all(|&(start_ms, t_dtw)| t_dtw == start_ms));
+        let model_id = unsafe { &*handle }.model_id.clone();
+        assert_eq!(WHISPER_MANAGER.with_model(&model_id, |model| model.loaded_dtw).unwrap(), Some(DtwPreset::TinyEn));
+        
+        whisper_unregister(handle);
+        whisper_free(handle);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)]
+    fn test_failed_dtw_reload_releases_its_reservation() {
+        let path = std::env::temp_dir().join("whisper-mock-dtw-reserve-tiny.en.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        let bytes = info.memory_usage;
+        manager.register_model("dtw_reserve_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
+        manager.preload("dtw_reserve_id").unwrap();
+        assert_eq!(manager.committed_memory.load(Ordering::SeqCst), bytes);
         
-        // let model_path = CString::new("path/to/test/model").unwrap();
-        // let handle = whisper_init(model_path.as_ptr());
-        // assert!(!handle.is_null());
+        // The new heads need a reload, which fails once the file is gone
+        manager.with_model("dtw_reserve_id", |model| model.settings.dtw = Some(DtwPreset::TinyEn)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let error = manager.transcribe("dtw_reserve_id", &[0.1; 16000]).unwrap_err();
+        assert_eq!(error.code, WhisperError::LoadFailed);
+        assert!(!manager.with_model("dtw_reserve_id", |model| model.ctx.is_some()).unwrap());
+        assert_eq!(manager.committed_memory.load(Ordering::SeqCst), 0);
         
-        // let test_audio = vec![0.1f32; 16000]; // 1 second of audio at 16kHz
-        // let result = whisper_transcribe(handle, test_audio.as_ptr(), test_audio.len());
-        // assert!(result.success);
-        // assert!(!result.text.is_null());
+        // A later load reserves the model once, not on top of a leaked reservation
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        manager.preload("dtw_reserve_id").unwrap();
+        assert_eq!(manager.committed_memory.load(Ordering::SeqCst), bytes);
+        assert_eq!(manager.with_model("dtw_reserve_id", |model| model.loaded_dtw).unwrap(), Some(DtwPreset::TinyEn));
+        manager.unregister("dtw_reserve_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    #[cfg_attr(not(feature = "mock"), ignore)]
+    fn test_long_prompt_with_carryover() {
+        let path = std::env::temp_dir().join("whisper-mock-prompt-tiny.bin");
+        std::fs::write(&path, 0x6767_6d6cu32.to_le_bytes()).unwrap();
+        let manager = WhisperManager::new();
+        let info = model_info_for_path(path.to_str().unwrap());
+        manager.register_model("long_prompt_id".to_string(), path.to_str().unwrap().to_string(), info).unwrap();
         
-        // whisper_free_string(result.text);
-        // whisper_free(handle);
+        // Well over the carried tail's 64 tokens; the mock tokenizes one per word
+        let prompt = vec!["Kubernetes"; 300].join(" ");
+        manager.with_model("long_prompt_id", |model| {
+            model.settings.context_carryover = true;
+            model.settings.initial_prompt = Some(prompt);
+        }).unwrap();
+        for _ in 0..2 {
+            let result = manager.transcribe("long_prompt_id", &[0.1; 16000]).unwrap();
+            assert_eq!(result.text, "Second 1.");
+        }
+        assert!(!manager.with_model("long_prompt_id", |model| model.carryover.is_empty()).unwrap());
+        manager.unregister("long_prompt_id").unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
 }
\ No newline at end of file
@@ -0,0 +1,122 @@
+//! Canonical lock order for the model manager
+//!
+//! The `models` map lock is taken first, then at most one model lock with a
+//! blocking `lock`. Any further model lock may only be tried, since blocking
+//! on a second model could deadlock against a thread holding it. Each thread
+//! counts the model locks it holds, and debug builds panic on an inversion
+//! so it fails a test instead of deadlocking in the field.
+
+use std::cell::Cell;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+thread_local! {
+    static MODEL_LOCKS_HELD: Cell<usize> = const { Cell::new(0) };
+}
+
+fn model_locks_held() -> usize {
+    MODEL_LOCKS_HELD.with(Cell::get)
+}
+
+fn assert_no_model_locks(what: &str) {
+    debug_assert_eq!(model_locks_held(), 0, "{} while holding a model lock", what);
+}
+
+/// Read-lock the models map; never called with a model lock held
+pub fn read_map<T>(map: &RwLock<T>) -> Result<RwLockReadGuard<'_, T>, PoisonError<()>> {
+    assert_no_model_locks("Models map read");
+    map.read().map_err(|_| PoisonError::new(()))
+}
+
+/// Write-lock the models map; never called with a model lock held
+pub fn write_map<T>(map: &RwLock<T>) -> Result<RwLockWriteGuard<'_, T>, PoisonError<()>> {
+    assert_no_model_locks("Models map write");
+    map.write().map_err(|_| PoisonError::new(()))
+}
+
+/// A held model lock, counted until dropped
+pub struct ModelGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+}
+
+impl<'a, T> ModelGuard<'a, T> {
+    fn new(guard: MutexGuard<'a, T>) -> Self {
+        MODEL_LOCKS_HELD.with(|held| held.set(held.get() + 1));
+        Self { guard }
+    }
+}
+
+impl<T> Deref for ModelGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for ModelGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for ModelGuard<'_, T> {
+    fn drop(&mut self) {
+        MODEL_LOCKS_HELD.with(|held| held.set(held.get() - 1));
+    }
+}
+
+/// Lock a model, waiting for it; only the first model lock a thread takes may block
+pub fn lock_model<T>(model: &Mutex<T>) -> Result<ModelGuard<'_, T>, PoisonError<()>> {
+    assert_no_model_locks("Blocking model lock");
+    model.lock().map(ModelGuard::new).map_err(|_| PoisonError::new(()))
+}
+
+/// Lock a model if it's free, which is allowed while holding another
+pub fn try_lock_model<T>(model: &Mutex<T>) -> Result<ModelGuard<'_, T>, TryLockError<()>> {
+    match model.try_lock() {
+        Ok(guard) => Ok(ModelGuard::new(guard)),
+        Err(TryLockError::WouldBlock) => Err(TryLockError::WouldBlock),
+        Err(TryLockError::Poisoned(_)) => Err(TryLockError::Poisoned(PoisonError::new(()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guards_are_counted_until_dropped() {
+        let map = RwLock::new(());
+        let (first, second) = (Mutex::new(1), Mutex::new(2));
+        {
+            let _map = read_map(&map).unwrap();
+            let first_guard = lock_model(&first).unwrap();
+            let second_guard = try_lock_model(&second).unwrap();
+            assert_eq!((*first_guard, *second_guard), (1, 2));
+            assert_eq!(model_locks_held(), 2);
+            assert!(matches!(try_lock_model(&second), Err(TryLockError::WouldBlock)));
+        }
+        assert_eq!(model_locks_held(), 0);
+        assert!(write_map(&map).is_ok());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Models map read while holding a model lock")]
+    fn test_map_lock_after_model_lock_panics() {
+        let map = RwLock::new(());
+        let model = Mutex::new(());
+        let _model = lock_model(&model).unwrap();
+        let _map = read_map(&map);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Blocking model lock while holding a model lock")]
+    fn test_second_blocking_model_lock_panics() {
+        let (first, second) = (Mutex::new(()), Mutex::new(()));
+        let _first = lock_model(&first).unwrap();
+        let _second = lock_model(&second);
+    }
+}
@@ -0,0 +1,126 @@
+//! Routing of library and whisper.cpp log messages to a host callback
+//!
+//! Messages go to stderr until a callback is installed, which keeps them
+//! visible in command-line use while letting a GUI host forward them to its
+//! own logging system.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::RwLock;
+
+use whisper_rs::GGMLLogLevel;
+
+/// Callback receiving a log level and a message valid only for the call
+pub type LogCallback = extern "C" fn(level: i32, msg: *const c_char, user_data: *mut c_void);
+
+pub const LOG_LEVEL_DEBUG: i32 = 0;
+pub const LOG_LEVEL_INFO: i32 = 1;
+pub const LOG_LEVEL_WARN: i32 = 2;
+pub const LOG_LEVEL_ERROR: i32 = 3;
+
+/// Installed callback and its user data, carried as an address so the static is `Sync`
+static SINK: RwLock<Option<(LogCallback, usize)>> = RwLock::new(None);
+
+/// Install or clear the log callback
+///
+/// whisper.cpp's own logging is routed through the same callback while one
+/// is installed, and restored to its stderr default when cleared.
+pub fn set_callback(callback: Option<LogCallback>, user_data: *mut c_void) {
+    if let Ok(mut sink) = SINK.write() {
+        *sink = callback.map(|callback| (callback, user_data as usize));
+    }
+
+    let native: whisper_rs::WhisperLogCallback = callback.map(|_| forward_native as _);
+    unsafe {
+        whisper_rs::set_log_callback(native, std::ptr::null_mut());
+    }
+}
+
+pub fn info(msg: &str) {
+    log(LOG_LEVEL_INFO, msg);
+}
+
+pub fn warn(msg: &str) {
+    log(LOG_LEVEL_WARN, msg);
+}
+
+/// Send a message to the installed callback, or stderr if there is none
+pub fn log(level: i32, msg: &str) {
+    // Copied out so a callback that replaces itself doesn't deadlock
+    let sink = SINK.read().ok().and_then(|sink| *sink);
+    let Some((callback, user_data)) = sink else {
+        match level {
+            LOG_LEVEL_WARN => eprintln!("Warning: {}", msg),
+            LOG_LEVEL_ERROR => eprintln!("Error: {}", msg),
+            _ => eprintln!("Whisper: {}", msg),
+        }
+        return;
+    };
+
+    let Ok(msg) = CString::new(msg) else {
+        return;
+    };
+    // Never let a panic unwind into the host, or into whisper.cpp when forwarding its logs
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        callback(level, msg.as_ptr(), user_data as *mut c_void);
+    }));
+    if outcome.is_err() {
+        eprintln!("Warning: Log callback panicked, ignoring");
+    }
+}
+
+/// whisper.cpp log hook; messages arrive with trailing newlines, sometimes in pieces
+unsafe extern "C" fn forward_native(level: c_uint, text: *const c_char, _: *mut c_void) {
+    if text.is_null() {
+        return;
+    }
+    let text = CStr::from_ptr(text).to_string_lossy();
+    let text = text.trim_end();
+    if text.is_empty() {
+        return;
+    }
+
+    let level = match GGMLLogLevel::from(level) {
+        GGMLLogLevel::Error => LOG_LEVEL_ERROR,
+        GGMLLogLevel::Warn => LOG_LEVEL_WARN,
+        GGMLLogLevel::Info => LOG_LEVEL_INFO,
+        _ => LOG_LEVEL_DEBUG,
+    };
+    log(level, text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    static CAPTURED: Mutex<Vec<(i32, String)>> = Mutex::new(Vec::new());
+
+    extern "C" fn capture(level: i32, msg: *const c_char, user_data: *mut c_void) {
+        assert_eq!(user_data as usize, 0x1234);
+        let msg = unsafe { CStr::from_ptr(msg) }.to_string_lossy().into_owned();
+        CAPTURED.lock().unwrap().push((level, msg));
+    }
+
+    #[test]
+    fn test_messages_reach_callback() {
+        set_callback(Some(capture), 0x1234 as *mut c_void);
+        warn("logging test warning");
+        let ggml_info = 2;
+        unsafe { forward_native(ggml_info, c"logging test native\n".as_ptr(), std::ptr::null_mut()) };
+        set_callback(None, std::ptr::null_mut());
+        info("logging test to stderr");
+
+        // Other tests may log concurrently, so only look at this test's messages
+        let captured: Vec<_> = CAPTURED.lock().unwrap()
+            .iter()
+            .filter(|(_, msg)| msg.starts_with("logging test"))
+            .cloned()
+            .collect();
+        assert_eq!(captured, [
+            (LOG_LEVEL_WARN, "logging test warning".to_string()),
+            (LOG_LEVEL_INFO, "logging test native".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,504 @@
+//! Deterministic stand-in for whisper.cpp inference, behind the `mock` feature
+//!
+//! Swapped in for `WhisperContext` and `WhisperState` so the manager, memory
+//! accounting, CPU monitoring and FFI layers can be tested end to end
+//! without a model. Loading only checks that the file has a model header,
+//! and every second of audio with any sound in it decodes as one canned
+//! segment. Tokens get DTW times when the context was loaded with DTW heads.
+//!
+//! `MockParams` likewise stands in for `FullParams`, whose fields whisper-rs
+//! keeps private, so tests can read back what a run was given with
+//! `last_params`.
+
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::os::raw::c_int;
+
+use whisper_rs::{
+    DtwMode, SamplingStrategy, SegmentCallbackData, WhisperAbortCallback, WhisperContextParameters, WhisperError,
+    WhisperLogitsFilterCallback, WhisperStartEncoderCallback, WhisperToken, WhisperTokenData,
+};
+
+use crate::{check_model_file, SegmentSource, WHISPER_SAMPLE_RATE};
+
+/// Samples whisper.cpp encodes at a time; callbacks run once per window
+const WINDOW_SAMPLES: usize = 30 * WHISPER_SAMPLE_RATE;
+
+/// Readings of " Second" a sampled decode picks from
+const SAMPLED_WORDS: [&str; 4] = [" Second", " Seconds", " Secant", " Sekund"];
+
+/// Fixed seed of every run's sampler, as whisper.cpp reseeds its RNG for each `full` call
+const SAMPLER_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+thread_local! {
+    static LAST_PARAMS: RefCell<Option<MockParams>> = const { RefCell::new(None) };
+}
+
+/// Read the parameters of the last `full` run on this thread
+#[cfg(test)]
+pub fn last_params<R>(read: impl FnOnce(&MockParams) -> R) -> Option<R> {
+    LAST_PARAMS.with(|last| last.borrow().as_ref().map(read))
+}
+
+/// Decoding parameters as each `FullParams` setter left them
+#[cfg_attr(not(test), allow(dead_code))] // read back by tests
+pub struct MockParams {
+    pub strategy: SamplingStrategy,
+    pub n_threads: c_int,
+    pub language: Option<String>,
+    pub translate: bool,
+    pub temperature: f32,
+    pub temperature_inc: f32,
+    pub token_timestamps: bool,
+    pub max_len: c_int,
+    pub split_on_word: bool,
+    pub suppress_blank: bool,
+    pub suppress_nst: bool,
+    pub tokens: Vec<WhisperToken>,
+    pub initial_prompt: Option<String>,
+    pub offset_ms: c_int,
+    pub duration_ms: c_int,
+    abort: (WhisperAbortCallback, *mut c_void),
+    encoder_begin: (WhisperStartEncoderCallback, *mut c_void),
+    filter_logits: (WhisperLogitsFilterCallback, *mut c_void),
+    on_progress: Option<Box<dyn FnMut(i32)>>,
+    on_segment: Option<Box<dyn FnMut(SegmentCallbackData)>>,
+}
+
+impl MockParams {
+    /// whisper.cpp's defaults for `strategy`
+    pub fn new(strategy: SamplingStrategy) -> Self {
+        MockParams {
+            strategy,
+            n_threads: 4,
+            language: Some("en".to_string()),
+            translate: false,
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            token_timestamps: false,
+            max_len: 0,
+            split_on_word: false,
+            suppress_blank: true,
+            suppress_nst: false,
+            tokens: Vec::new(),
+            initial_prompt: None,
+            offset_ms: 0,
+            duration_ms: 0,
+            abort: (None, std::ptr::null_mut()),
+            encoder_begin: (None, std::ptr::null_mut()),
+            filter_logits: (None, std::ptr::null_mut()),
+            on_progress: None,
+            on_segment: None,
+        }
+    }
+
+    pub fn set_n_threads(&mut self, n_threads: c_int) {
+        self.n_threads = n_threads;
+    }
+
+    pub fn set_language(&mut self, language: Option<&str>) {
+        self.language = language.map(str::to_string);
+    }
+
+    pub fn set_translate(&mut self, translate: bool) {
+        self.translate = translate;
+    }
+
+    pub fn set_temperature(&mut self, temperature: f32) {
+        self.temperature = temperature;
+    }
+
+    pub fn set_temperature_inc(&mut self, temperature_inc: f32) {
+        self.temperature_inc = temperature_inc;
+    }
+
+    pub fn set_token_timestamps(&mut self, token_timestamps: bool) {
+        self.token_timestamps = token_timestamps;
+    }
+
+    pub fn set_max_len(&mut self, max_len: c_int) {
+        self.max_len = max_len;
+    }
+
+    pub fn set_split_on_word(&mut self, split_on_word: bool) {
+        self.split_on_word = split_on_word;
+    }
+
+    pub fn set_suppress_blank(&mut self, suppress_blank: bool) {
+        self.suppress_blank = suppress_blank;
+    }
+
+    pub fn set_suppress_nst(&mut self, suppress_nst: bool) {
+        self.suppress_nst = suppress_nst;
+    }
+
+    pub fn set_print_special(&mut self, _print_special: bool) {}
+
+    pub fn set_print_progress(&mut self, _print_progress: bool) {}
+
+    pub fn set_print_realtime(&mut self, _print_realtime: bool) {}
+
+    pub fn set_print_timestamps(&mut self, _print_timestamps: bool) {}
+
+    pub fn set_tokens(&mut self, tokens: &[WhisperToken]) {
+        self.tokens = tokens.to_vec();
+    }
+
+    pub fn set_initial_prompt(&mut self, initial_prompt: &str) {
+        self.initial_prompt = Some(initial_prompt.to_string());
+    }
+
+    pub fn set_offset_ms(&mut self, offset_ms: c_int) {
+        self.offset_ms = offset_ms;
+    }
+
+    pub fn set_duration_ms(&mut self, duration_ms: c_int) {
+        self.duration_ms = duration_ms;
+    }
+
+    /// # Safety
+    /// `abort_callback` must be safe to call with the user data set next
+    pub unsafe fn set_abort_callback(&mut self, abort_callback: WhisperAbortCallback) {
+        self.abort.0 = abort_callback;
+    }
+
+    /// # Safety
+    /// `user_data` must stay valid for the duration of `full`
+    pub unsafe fn set_abort_callback_user_data(&mut self, user_data: *mut c_void) {
+        self.abort.1 = user_data;
+    }
+
+    /// # Safety
+    /// As for `set_abort_callback`
+    pub unsafe fn set_start_encoder_callback(&mut self, start_encoder_callback: WhisperStartEncoderCallback) {
+        self.encoder_begin.0 = start_encoder_callback;
+    }
+
+    /// # Safety
+    /// As for `set_abort_callback_user_data`
+    pub unsafe fn set_start_encoder_callback_user_data(&mut self, user_data: *mut c_void) {
+        self.encoder_begin.1 = user_data;
+    }
+
+    /// # Safety
+    /// As for `set_abort_callback`
+    pub unsafe fn set_filter_logits_callback(&mut self, logits_filter_callback: WhisperLogitsFilterCallback) {
+        self.filter_logits.0 = logits_filter_callback;
+    }
+
+    /// # Safety
+    /// As for `set_abort_callback_user_data`
+    pub unsafe fn set_filter_logits_callback_user_data(&mut self, user_data: *mut c_void) {
+        self.filter_logits.1 = user_data;
+    }
+
+    pub fn set_progress_callback_safe<O, F>(&mut self, closure: O)
+    where
+        F: FnMut(i32) + 'static,
+        O: Into<Option<F>>,
+    {
+        self.on_progress = closure.into().map(|closure| Box::new(closure) as Box<dyn FnMut(i32)>);
+    }
+
+    pub fn set_segment_callback_safe_lossy<O, F>(&mut self, closure: O)
+    where
+        F: FnMut(SegmentCallbackData) + 'static,
+        O: Into<Option<F>>,
+    {
+        self.on_segment = closure.into().map(|closure| Box::new(closure) as Box<dyn FnMut(SegmentCallbackData)>);
+    }
+
+    fn aborted(&self) -> bool {
+        match self.abort {
+            (Some(callback), user_data) => unsafe { callback(user_data) },
+            (None, _) => false,
+        }
+    }
+
+    /// Run the encoder and first logits callbacks of a window, as whisper.cpp does
+    ///
+    /// Returns false when the encoder callback asks to stop.
+    fn begin_window(&self) -> bool {
+        if let (Some(callback), user_data) = self.encoder_begin {
+            if !unsafe { callback(std::ptr::null_mut(), std::ptr::null_mut(), user_data) } {
+                return false;
+            }
+        }
+        if let (Some(callback), user_data) = self.filter_logits {
+            let mut logits = vec![0.0f32; TOKEN_EOT as usize + 1];
+            unsafe {
+                callback(std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null(), 0, logits.as_mut_ptr(), user_data);
+            }
+        }
+        true
+    }
+}
+
+/// Milliseconds of audio as a sample count
+fn ms_to_samples(ms: c_int) -> usize {
+    ms.max(0) as usize * WHISPER_SAMPLE_RATE / 1000
+}
+
+/// End of text in the English vocabulary; canned text tokens sort before it
+const TOKEN_EOT: WhisperToken = 50256;
+
+/// Probability of every canned text token
+const TOKEN_P: f32 = 0.9;
+
+/// A "loaded" model that decodes without any weights
+#[derive(Debug)]
+pub struct MockContext {
+    dtw: bool, // loaded with DTW alignment heads
+}
+
+impl MockContext {
+    pub fn new_with_params(path: &str, parameters: WhisperContextParameters) -> Result<Self, WhisperError> {
+        check_model_file(path).map_err(|_| WhisperError::InitError)?;
+        Ok(MockContext { dtw: !matches!(parameters.dtw_parameters.mode, DtwMode::None) })
+    }
+
+    /// One token per whitespace-separated word, failing like whisper.cpp past `max_tokens`
+    pub fn tokenize(&self, text: &str, max_tokens: usize) -> Result<Vec<WhisperToken>, WhisperError> {
+        let count = text.split_whitespace().count();
+        if count > max_tokens {
+            return Err(WhisperError::InvalidText);
+        }
+        Ok((0..count as WhisperToken).collect())
+    }
+
+    pub fn n_vocab(&self) -> c_int {
+        TOKEN_EOT + 1
+    }
+
+    pub fn token_eot(&self) -> WhisperToken {
+        TOKEN_EOT
+    }
+
+    pub fn create_state(&self) -> Result<MockState, WhisperError> {
+        Ok(MockState { segments: Vec::new(), dtw: self.dtw })
+    }
+
+    pub fn is_multilingual(&self) -> bool {
+        true
+    }
+
+    pub fn model_type_readable(&self) -> Result<String, WhisperError> {
+        Ok("mock".to_string())
+    }
+
+    pub fn model_n_vocab(&self) -> c_int {
+        self.n_vocab()
+    }
+
+    pub fn model_n_audio_ctx(&self) -> c_int {
+        1500
+    }
+
+    pub fn model_n_text_ctx(&self) -> c_int {
+        448
+    }
+
+    pub fn model_n_mels(&self) -> c_int {
+        80
+    }
+}
+
+/// One canned segment, in whisper's 10ms units
+#[derive(Debug)]
+struct MockSegment {
+    words: Vec<String>,
+    t0: i64,
+    t1: i64,
+}
+
+impl MockSegment {
+    /// Split into segments of at most `max_len` characters, as whisper.cpp wraps them
+    ///
+    /// A word longer than `max_len` gets a segment to itself. With
+    /// `split_on_word` a split only falls before a word starting with a space.
+    fn wrap(self, max_len: usize, split_on_word: bool) -> Vec<MockSegment> {
+        let count = self.words.len();
+        let step = (self.t1 - self.t0) / count as i64;
+        let mut wrapped: Vec<MockSegment> = Vec::new();
+        for (index, word) in self.words.into_iter().enumerate() {
+            let t0 = self.t0 + step * index as i64;
+            let t1 = if index + 1 == count { self.t1 } else { t0 + step };
+            match wrapped.last_mut() {
+                Some(last) if last.words.concat().len() + word.len() <= max_len || (split_on_word && !word.starts_with(' ')) => {
+                    last.words.push(word);
+                    last.t1 = t1;
+                }
+                _ => wrapped.push(MockSegment { words: vec![word], t0, t1 }),
+            }
+        }
+        wrapped
+    }
+}
+
+/// Inference state holding the segments of the last `full` run
+#[derive(Debug)]
+pub struct MockState {
+    segments: Vec<MockSegment>,
+    dtw: bool,
+}
+
+impl MockState {
+    /// Decode `data` as " Second N." for each second N with a non-zero sample, counting from 1
+    ///
+    /// A trailing partial second gets a segment of its own, cut short at the
+    /// end of the audio. Silent seconds, including any padding, decode as
+    /// nothing. Like whisper.cpp, only the window set by `offset_ms` and
+    /// `duration_ms` is decoded, and nothing at all when that's under a
+    /// second. The abort and encoder callbacks can stop the run at each 30s
+    /// window, and `max_len` splits segments when token timestamps are on. Above temperature zero the first word is sampled
+    /// from `SAMPLED_WORDS`, with the same seed in every run.
+    pub fn full(&mut self, mut params: MockParams, data: &[f32]) -> Result<c_int, WhisperError> {
+        if data.is_empty() {
+            return Err(WhisperError::NoSamples);
+        }
+        let result = self.decode(&mut params, data);
+        LAST_PARAMS.with(|last| *last.borrow_mut() = Some(params));
+        result
+    }
+
+    fn decode(&mut self, params: &mut MockParams, data: &[f32]) -> Result<c_int, WhisperError> {
+        let start = ms_to_samples(params.offset_ms).min(data.len());
+        let end = match params.duration_ms {
+            0 => data.len(),
+            duration_ms => (start + ms_to_samples(duration_ms)).min(data.len()),
+        };
+        let mut rng = SAMPLER_SEED;
+        self.segments.clear();
+        if end - start < WHISPER_SAMPLE_RATE {
+            return Ok(0);
+        }
+        for chunk_start in (start..end).step_by(WHISPER_SAMPLE_RATE) {
+            if (chunk_start - start).is_multiple_of(WINDOW_SAMPLES) {
+                if params.aborted() {
+                    return Err(WhisperError::GenericError(-6));
+                }
+                if !params.begin_window() {
+                    break;
+                }
+            }
+            let chunk_end = (chunk_start + WHISPER_SAMPLE_RATE).min(end);
+            if data[chunk_start..chunk_end].iter().all(|&sample| sample == 0.0) {
+                continue;
+            }
+            let first = if params.temperature > 0.0 {
+                // xorshift64, standing in for whisper.cpp's seeded mt19937
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+                SAMPLED_WORDS[(rng % SAMPLED_WORDS.len() as u64) as usize]
+            } else {
+                SAMPLED_WORDS[0]
+            };
+            let segment = MockSegment {
+                words: vec![first.to_string(), format!(" {}", chunk_start / WHISPER_SAMPLE_RATE + 1), ".".to_string()],
+                t0: (chunk_start * 100 / WHISPER_SAMPLE_RATE) as i64,
+                t1: (chunk_end * 100 / WHISPER_SAMPLE_RATE) as i64,
+            };
+            let split = if params.token_timestamps && params.max_len > 0 {
+                segment.wrap(params.max_len as usize, params.split_on_word)
+            } else {
+                vec![segment]
+            };
+            for segment in split {
+                if let Some(on_segment) = &mut params.on_segment {
+                    on_segment(SegmentCallbackData {
+                        segment: self.segments.len() as i32,
+                        start_timestamp: segment.t0,
+                        end_timestamp: segment.t1,
+                        text: segment.words.concat(),
+                    });
+                }
+                self.segments.push(segment);
+            }
+            if let Some(on_progress) = &mut params.on_progress {
+                on_progress(((chunk_end - start) * 100 / (end - start)) as i32);
+            }
+        }
+        Ok(0)
+    }
+
+    pub fn full_lang_id_from_state(&self) -> Result<c_int, WhisperError> {
+        Ok(0) // English
+    }
+
+    pub fn pcm_to_mel(&mut self, pcm: &[f32], threads: usize) -> Result<(), WhisperError> {
+        if threads < 1 {
+            return Err(WhisperError::InvalidThreadCount);
+        }
+        if pcm.is_empty() {
+            return Err(WhisperError::NoSamples);
+        }
+        Ok(())
+    }
+
+    /// Always detects English with full confidence
+    pub fn lang_detect(&self, _offset_ms: usize, _threads: usize) -> Result<(c_int, Vec<f32>), WhisperError> {
+        let mut probabilities = vec![0.0; whisper_rs::get_lang_max_id() as usize + 1];
+        probabilities[0] = 1.0;
+        Ok((0, probabilities))
+    }
+
+    fn segment(&self, segment: c_int) -> Result<&MockSegment, WhisperError> {
+        usize::try_from(segment).ok()
+            .and_then(|index| self.segments.get(index))
+            .ok_or(WhisperError::GenericError(segment))
+    }
+
+    fn word(&self, segment: c_int, token: c_int) -> Result<&str, WhisperError> {
+        usize::try_from(token).ok()
+            .and_then(|index| self.segment(segment).ok()?.words.get(index))
+            .map(String::as_str)
+            .ok_or(WhisperError::GenericError(token))
+    }
+}
+
+impl SegmentSource for MockState {
+    fn full_n_segments(&self) -> Result<i32, WhisperError> {
+        Ok(self.segments.len() as i32)
+    }
+
+    fn full_get_segment_text(&self, segment: i32) -> Result<String, WhisperError> {
+        Ok(self.segment(segment)?.words.concat())
+    }
+
+    fn full_get_segment_t0(&self, segment: i32) -> Result<i64, WhisperError> {
+        Ok(self.segment(segment)?.t0)
+    }
+
+    fn full_get_segment_t1(&self, segment: i32) -> Result<i64, WhisperError> {
+        Ok(self.segment(segment)?.t1)
+    }
+
+    fn full_n_tokens(&self, segment: i32) -> Result<i32, WhisperError> {
+        Ok(self.segment(segment)?.words.len() as i32)
+    }
+
+    /// Tokens split their segment's span evenly; DTW places each at its start
+    fn full_get_token_data(&self, segment: i32, token: i32) -> Result<WhisperTokenData, WhisperError> {
+        self.word(segment, token)?;
+        let canned = self.segment(segment)?;
+        let step = (canned.t1 - canned.t0) / canned.words.len() as i64;
+        let t0 = canned.t0 + step * token as i64;
+        Ok(WhisperTokenData {
+            id: token,
+            tid: TOKEN_EOT + 1,
+            p: TOKEN_P,
+            plog: TOKEN_P.ln(),
+            pt: 0.0,
+            ptsum: 0.0,
+            t0,
+            t1: t0 + step,
+            t_dtw: if self.dtw { t0 } else { -1 },
+            vlen: 0.0,
+        })
+    }
+
+    fn full_get_token_bytes(&self, segment: i32, token: i32) -> Result<Vec<u8>, WhisperError> {
+        Ok(self.word(segment, token)?.as_bytes().to_vec())
+    }
+}
@@ -0,0 +1,139 @@
+//! Sample rate conversion to the 16kHz input whisper expects
+//!
+//! Uses windowed-sinc interpolation. When downsampling, the kernel's cutoff
+//! is lowered to the target Nyquist frequency so content above 8kHz is
+//! filtered out rather than aliased into the speech band.
+
+use std::borrow::Cow;
+use std::f32::consts::PI;
+
+/// Rate whisper expects
+pub const TARGET_RATE: u32 = 16000;
+
+/// Zero crossings of the sinc kernel on each side of the output sample
+const HALF_TAPS: f32 = 16.0;
+
+/// Convert `samples` recorded at `src_rate` to 16kHz
+///
+/// Audio already at 16kHz is returned as-is without allocating. A zero
+/// `src_rate` yields an empty buffer.
+pub fn resample_to_16k(samples: &[f32], src_rate: u32) -> Cow<'_, [f32]> {
+    if src_rate == TARGET_RATE || samples.is_empty() {
+        return Cow::Borrowed(samples);
+    }
+    if src_rate == 0 {
+        return Cow::Owned(Vec::new());
+    }
+
+    let step = src_rate as f64 / TARGET_RATE as f64; // source samples per output sample
+    let cutoff = (TARGET_RATE as f32 / src_rate as f32).min(1.0);
+    let half_width = (HALF_TAPS / cutoff) as f64;
+    let out_len = (samples.len() as f64 / step).ceil() as usize;
+
+    let output = (0..out_len)
+        .map(|i| {
+            // Positions stay in f64: past 2^24 samples an f32 can't tell neighbours apart
+            let center = i as f64 * step;
+            let first = (center - half_width).ceil().max(0.0) as usize;
+            let last = ((center + half_width).floor() as usize).min(samples.len() - 1);
+
+            let (mut sum, mut weight_sum) = (0.0f32, 0.0f32);
+            for (offset, sample) in samples[first..=last].iter().enumerate() {
+                let x = center - (first + offset) as f64;
+                let weight = cutoff * sinc(cutoff * x as f32) * hann((x / half_width) as f32);
+                sum += sample * weight;
+                weight_sum += weight;
+            }
+
+            // Normalizing keeps unity gain at the buffer edges where the kernel is truncated
+            if weight_sum.abs() > f32::EPSILON {
+                sum / weight_sum
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    Cow::Owned(output)
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Hann window over [-1, 1]
+fn hann(x: f32) -> f32 {
+    if x.abs() >= 1.0 {
+        0.0
+    } else {
+        0.5 + 0.5 * (PI * x).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: u32, seconds: f32) -> Vec<f32> {
+        (0..(rate as f32 * seconds) as usize)
+            .map(|i| (2.0 * PI * freq * i as f32 / rate as f32).sin() * 0.5)
+            .collect()
+    }
+
+    /// Frequency estimated from rising zero crossings
+    fn frequency(samples: &[f32], rate: u32) -> f32 {
+        let crossings = samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+        crossings as f32 * rate as f32 / samples.len() as f32
+    }
+
+    #[test]
+    fn test_48k_sine_keeps_frequency() {
+        let samples = sine(1000.0, 48000, 1.0);
+        let resampled = resample_to_16k(&samples, 48000);
+        assert_eq!(resampled.len(), 16000);
+        assert!((frequency(&resampled, TARGET_RATE) - 1000.0).abs() < 5.0);
+
+        // Amplitude survives away from the truncated edges
+        let peak = resampled[1000..15000].iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.5).abs() < 0.02, "{}", peak);
+    }
+
+    #[test]
+    fn test_44100_sine_keeps_frequency() {
+        let samples = sine(440.0, 44100, 1.0);
+        let resampled = resample_to_16k(&samples, 44100);
+        assert_eq!(resampled.len(), 16000);
+        assert!((frequency(&resampled, TARGET_RATE) - 440.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_content_above_target_nyquist_is_filtered() {
+        // A 12kHz tone can't be represented at 16kHz and must not alias to 4kHz
+        let samples = sine(12000.0, 48000, 0.5);
+        let resampled = resample_to_16k(&samples, 48000);
+        let rms = (resampled[500..7500].iter().map(|s| s * s).sum::<f32>() / 7000.0).sqrt();
+        assert!(rms < 0.01, "{}", rms);
+    }
+
+    #[test]
+    fn test_long_buffer_keeps_phase() {
+        // Past 2^23 samples an f32 position is off by up to half a sample
+        let len = (1 << 23) + 44100;
+        let tone = |i: usize, rate: u32| ((2.0 * std::f64::consts::PI * 3000.0 * i as f64 / rate as f64).sin() * 0.5) as f32;
+        let samples: Vec<f32> = (0..len).map(|i| tone(i, 44100)).collect();
+        let resampled = resample_to_16k(&samples, 44100);
+        let tail = resampled.len() - 16000..resampled.len() - 1000;
+        let error = tail.map(|i| (resampled[i] - tone(i, TARGET_RATE)).abs()).fold(0.0f32, f32::max);
+        assert!(error < 0.01, "{}", error);
+    }
+
+    #[test]
+    fn test_16k_input_is_borrowed() {
+        let samples = sine(1000.0, 16000, 0.1);
+        assert!(matches!(resample_to_16k(&samples, 16000), Cow::Borrowed(_)));
+        assert!(resample_to_16k(&samples, 0).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+//! Fixed-capacity sample buffer for continuous capture
+//!
+//! Pushing past capacity overwrites the oldest samples, so the buffer
+//! always holds the most recent window of audio.
+
+/// Ring of the last `capacity` samples pushed
+#[derive(Debug)]
+pub struct RingBuffer {
+    samples: Vec<f32>,
+    next: usize, // index the next sample is written to
+    len: usize,
+}
+
+impl RingBuffer {
+    /// An empty buffer; `capacity` must be non-zero
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Ring buffer capacity must be non-zero");
+        Self { samples: vec![0.0; capacity], next: 0, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append `samples`, overwriting the oldest once full
+    ///
+    /// Only the last `capacity` samples of a push larger than the buffer
+    /// are kept.
+    pub fn push(&mut self, samples: &[f32]) {
+        let capacity = self.capacity();
+        let samples = &samples[samples.len().saturating_sub(capacity)..];
+        let first = samples.len().min(capacity - self.next);
+        self.samples[self.next..self.next + first].copy_from_slice(&samples[..first]);
+        self.samples[..samples.len() - first].copy_from_slice(&samples[first..]);
+        self.next = (self.next + samples.len()) % capacity;
+        self.len = (self.len + samples.len()).min(capacity);
+    }
+
+    /// The buffered samples, oldest first
+    pub fn to_vec(&self) -> Vec<f32> {
+        let start = (self.next + self.capacity() - self.len) % self.capacity();
+        let mut window = Vec::with_capacity(self.len);
+        let first = self.len.min(self.capacity() - start);
+        window.extend_from_slice(&self.samples[start..start + first]);
+        window.extend_from_slice(&self.samples[..self.len - first]);
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(range: std::ops::Range<u16>) -> Vec<f32> {
+        range.map(f32::from).collect()
+    }
+
+    #[test]
+    fn test_push_wraps_around() {
+        let mut ring = RingBuffer::new(5);
+        assert!(ring.is_empty());
+        ring.push(&ramp(0..3));
+        assert_eq!(ring.to_vec(), ramp(0..3));
+
+        // Overwrites the two oldest samples, across the end of the storage
+        ring.push(&ramp(3..7));
+        assert_eq!(ring.to_vec(), ramp(2..7));
+
+        ring.push(&ramp(7..8));
+        assert_eq!(ring.to_vec(), ramp(3..8));
+        ring.push(&[]);
+        assert_eq!(ring.to_vec(), ramp(3..8));
+    }
+
+    #[test]
+    fn test_oversized_push_keeps_the_tail() {
+        let mut ring = RingBuffer::new(4);
+        ring.push(&ramp(0..1));
+        ring.push(&ramp(1..11));
+        assert_eq!(ring.to_vec(), ramp(7..11));
+        ring.push(&ramp(11..15));
+        assert_eq!(ring.to_vec(), ramp(11..15));
+    }
+}
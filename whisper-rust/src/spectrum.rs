@@ -0,0 +1,190 @@
+//! Best-effort spectral checks on input audio
+//!
+//! Audio that was recorded at a lower rate and upsampled (or mislabeled)
+//! carries no energy above the original Nyquist frequency. These helpers
+//! look for that sharp spectral cliff to flag buffers whose content doesn't
+//! match the declared sample rate. They are heuristics: heavily filtered or
+//! very quiet audio can trigger false positives, so the check is opt-in.
+
+use std::f32::consts::PI;
+
+/// FFT frame length used for the averaged power spectrum
+const FRAME_LEN: usize = 512;
+
+/// Upper limit on analysed frames so long buffers stay cheap
+const MAX_FRAMES: usize = 64;
+
+/// Lower rates whose band limit we look for, highest first
+const CANDIDATE_RATES: [u32; 4] = [32000, 22050, 11025, 8000];
+
+/// Energy density above the cutoff must be this far below the density just under it
+const CLIFF_RATIO: f32 = 0.01;
+
+/// The band under the cutoff must hold at least this share of the average density
+const MIN_BAND_SHARE: f32 = 0.01;
+
+/// Frames quieter than this mean power are ignored
+const MIN_FRAME_POWER: f32 = 1e-8;
+
+/// Estimate the rate the content was actually captured at, if it looks band-limited
+///
+/// Returns `Some(rate)` when the spectrum of `samples` (declared as
+/// `declared_rate`) shows a cliff at the Nyquist frequency of a lower common
+/// sample rate, or `None` when the content looks consistent with the
+/// declared rate or there isn't enough signal to tell.
+pub fn detect_band_limited_rate(samples: &[f32], declared_rate: u32) -> Option<u32> {
+    let spectrum = average_power_spectrum(samples)?;
+    let bin_hz = declared_rate as f32 / FRAME_LEN as f32;
+    let declared_nyquist = declared_rate as f32 / 2.0;
+
+    let overall = spectrum.iter().sum::<f32>() / spectrum.len() as f32;
+
+    // Prefer the highest rate that explains the cliff
+    CANDIDATE_RATES
+        .into_iter()
+        .filter(|&rate| rate < declared_rate)
+        .find(|&rate| {
+            let cutoff = rate as f32 / 2.0;
+            let below = band_density(&spectrum, bin_hz, cutoff * 0.7, cutoff * 0.95);
+            let above = band_density(&spectrum, bin_hz, cutoff * 1.05, (cutoff * 1.9).min(declared_nyquist * 0.95));
+            below > overall * MIN_BAND_SHARE && above < below * CLIFF_RATIO
+        })
+}
+
+/// Mean power per bin between `low_hz` and `high_hz`
+fn band_density(spectrum: &[f32], bin_hz: f32, low_hz: f32, high_hz: f32) -> f32 {
+    let low = (low_hz / bin_hz).ceil() as usize;
+    let high = ((high_hz / bin_hz).floor() as usize).min(spectrum.len().saturating_sub(1));
+    if low > high {
+        return 0.0;
+    }
+    spectrum[low..=high].iter().sum::<f32>() / (high - low + 1) as f32
+}
+
+/// Hann-windowed power spectrum averaged over non-silent frames
+fn average_power_spectrum(samples: &[f32]) -> Option<Vec<f32>> {
+    let window: Vec<f32> = (0..FRAME_LEN)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_LEN as f32).cos())
+        .collect();
+
+    let frame_count = samples.len() / FRAME_LEN;
+    let step = (frame_count / MAX_FRAMES).max(1);
+
+    let mut spectrum = vec![0.0f32; FRAME_LEN / 2 + 1];
+    let mut used = 0usize;
+    for frame in samples.chunks_exact(FRAME_LEN).step_by(step).take(MAX_FRAMES) {
+        let power = frame.iter().map(|sample| sample * sample).sum::<f32>() / FRAME_LEN as f32;
+        if power < MIN_FRAME_POWER {
+            continue;
+        }
+
+        let mut re: Vec<f32> = frame.iter().zip(&window).map(|(sample, w)| sample * w).collect();
+        let mut im = vec![0.0f32; FRAME_LEN];
+        fft(&mut re, &mut im);
+        for (bin, value) in spectrum.iter_mut().enumerate() {
+            *value += re[bin] * re[bin] + im[bin] * im[bin];
+        }
+        used += 1;
+    }
+
+    if used == 0 {
+        return None;
+    }
+    for value in &mut spectrum {
+        *value /= used as f32;
+    }
+    Some(spectrum)
+}
+
+/// In-place iterative radix-2 FFT; the length must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise in [-0.5, 0.5)
+    fn noise(len: usize) -> Vec<f32> {
+        let mut seed: u32 = 0x1234_5678;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_single_tone() {
+        let mut re: Vec<f32> = (0..64).map(|i| (2.0 * PI * 4.0 * i as f32 / 64.0).cos()).collect();
+        let mut im = vec![0.0; 64];
+        fft(&mut re, &mut im);
+
+        let peak = (0..32)
+            .max_by(|&a, &b| re[a].hypot(im[a]).total_cmp(&re[b].hypot(im[b])))
+            .unwrap();
+        assert_eq!(peak, 4);
+    }
+
+    #[test]
+    fn test_detects_upsampled_8k_audio() {
+        // Content band-limited to below 4kHz but declared as 16kHz
+        let rate = 16000.0;
+        let tones = [310.0, 870.0, 1450.0, 2230.0, 3100.0, 3650.0];
+        let samples: Vec<f32> = (0..16000)
+            .map(|i| {
+                let t = i as f32 / rate;
+                tones.iter().map(|f| (2.0 * PI * f * t).sin()).sum::<f32>() * 0.1
+            })
+            .collect();
+
+        assert_eq!(detect_band_limited_rate(&samples, 16000), Some(8000));
+    }
+
+    #[test]
+    fn test_full_band_audio_is_consistent() {
+        assert_eq!(detect_band_limited_rate(&noise(16000), 16000), None);
+    }
+
+    #[test]
+    fn test_silence_is_inconclusive() {
+        assert_eq!(detect_band_limited_rate(&[0.0; 16000], 16000), None);
+        assert_eq!(detect_band_limited_rate(&[0.1; 10], 16000), None);
+    }
+}
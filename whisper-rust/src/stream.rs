@@ -0,0 +1,170 @@
+//! Word stabilization for live transcription of a growing buffer
+//!
+//! Each pass re-transcribes everything heard so far, so whisper may revise
+//! its latest words from one pass to the next. A word is committed once it
+//! has read the same in the last `passes_required` passes; committed words
+//! are never retracted, and everything after them stays provisional until
+//! a final pass finishes the session.
+
+use std::collections::VecDeque;
+
+/// Audio and commit state of one live transcription
+#[derive(Debug)]
+pub struct StreamSession {
+    audio: Vec<f32>,
+    passes_required: usize,
+    committed: Vec<String>,
+    recent: VecDeque<Vec<String>>, // last hypotheses, newest at the back
+    finished: bool,
+}
+
+/// Words compare equal when they differ only in case or surrounding punctuation
+fn same_word(a: &str, b: &str) -> bool {
+    let normalize = |word: &str| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    normalize(a) == normalize(b)
+}
+
+impl StreamSession {
+    /// A session committing words that read the same in `passes_required` passes in a row
+    pub fn new(passes_required: usize) -> Self {
+        let passes_required = passes_required.max(1);
+        Self {
+            audio: Vec::new(),
+            passes_required,
+            committed: Vec::new(),
+            recent: VecDeque::with_capacity(passes_required),
+            finished: false,
+        }
+    }
+
+    pub fn push_audio(&mut self, samples: &[f32]) {
+        self.audio.extend_from_slice(samples);
+    }
+
+    pub fn audio(&self) -> &[f32] {
+        &self.audio
+    }
+
+    /// Record a pass's full text, returning the words it newly commits
+    ///
+    /// Only words after the committed prefix are compared. They commit as
+    /// far as the last `passes_required` hypotheses agree, in the spelling
+    /// of the newest one.
+    pub fn update(&mut self, text: &str) -> Vec<String> {
+        if self.recent.len() == self.passes_required {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(text.split_whitespace().map(str::to_string).collect());
+        if self.recent.len() < self.passes_required {
+            return Vec::new();
+        }
+
+        let newest = self.recent.back().expect("just pushed");
+        let start = self.committed.len();
+        // A pass that revised or dropped words up to the committed prefix has nothing new
+        if newest.len() <= start {
+            return Vec::new();
+        }
+        let agreed = (start..newest.len())
+            .take_while(|&i| {
+                self.recent.iter().all(|words| words.get(i).is_some_and(|word| same_word(word, &newest[i])))
+            })
+            .count();
+        let newly: Vec<String> = newest[start..start + agreed].to_vec();
+        self.committed.extend(newly.iter().cloned());
+        newly
+    }
+
+    pub fn committed(&self) -> &[String] {
+        &self.committed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// End the session with the text of a final pass over all of its audio
+    ///
+    /// The committed words stand and the final pass supplies everything
+    /// after them, without waiting for agreement. Returns those newly
+    /// committed words. The audio and pending hypotheses are released.
+    pub fn finish(&mut self, text: &str) -> Vec<String> {
+        let rest: Vec<String> = text.split_whitespace().skip(self.committed.len()).map(str::to_string).collect();
+        self.committed.extend(rest.iter().cloned());
+        self.audio = Vec::new();
+        self.recent = VecDeque::new();
+        self.finished = true;
+        rest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_words_commit_after_k_passes() {
+        let mut session = StreamSession::new(3);
+        assert!(session.update("Hello").is_empty());
+        assert!(session.update("Hello world").is_empty());
+        // Third pass agreeing on "Hello": it commits, the changing tail doesn't
+        assert_eq!(session.update("Hello, word and"), ["Hello,"]);
+        assert!(session.update("Hello, world and more").is_empty());
+        assert!(session.update("Hello world and more.").is_empty());
+        assert_eq!(session.update("Hello world and more. Then"), ["world", "and", "more."]);
+        assert_eq!(session.committed(), ["Hello,", "world", "and", "more."]);
+    }
+
+    #[test]
+    fn test_unstable_tail_stays_provisional() {
+        let mut session = StreamSession::new(2);
+        assert!(session.update("Turn left").is_empty());
+        assert_eq!(session.update("Turn right"), ["Turn"]);
+        assert!(session.update("Turn left").is_empty());
+        assert!(session.update("Turn right").is_empty());
+        assert_eq!(session.committed(), ["Turn"]);
+
+        // A revision of committed words doesn't retract them
+        assert_eq!(session.update("Learn right"), ["right"]);
+        assert!(session.update("Learn right now").is_empty());
+        assert_eq!(session.committed(), ["Turn", "right"]);
+    }
+
+    #[test]
+    fn test_shorter_pass_commits_nothing() {
+        let mut session = StreamSession::new(2);
+        assert!(session.update("one two three four five").is_empty());
+        assert_eq!(session.update("one two three four five six"), ["one", "two", "three", "four", "five"]);
+
+        // A pass shorter than the committed prefix, or empty, leaves it alone
+        assert!(session.update("one two").is_empty());
+        assert!(session.update("").is_empty());
+        assert!(session.update("one two three four five six").is_empty());
+        assert_eq!(session.update("one two three four five six seven"), ["six"]);
+        assert_eq!(session.committed(), ["one", "two", "three", "four", "five", "six"]);
+    }
+
+    #[test]
+    fn test_finish_commits_the_final_pass() {
+        let mut session = StreamSession::new(2);
+        session.push_audio(&[0.5; 16000]);
+        assert!(session.update("Meet me").is_empty());
+        assert_eq!(session.update("Meet me at"), ["Meet", "me"]);
+
+        // The final pass revises the provisional tail but not committed words
+        assert_eq!(session.finish("Meat me at noon."), ["at", "noon."]);
+        assert_eq!(session.committed(), ["Meet", "me", "at", "noon."]);
+        assert!(session.is_finished());
+        assert!(session.audio().is_empty());
+        assert_eq!(session.audio.capacity(), 0);
+    }
+
+    #[test]
+    fn test_single_pass_commits_immediately() {
+        let mut session = StreamSession::new(0);
+        assert_eq!(session.update("One two"), ["One", "two"]);
+        session.push_audio(&[0.5; 4]);
+        session.push_audio(&[0.25; 2]);
+        assert_eq!(session.audio().len(), 6);
+    }
+}
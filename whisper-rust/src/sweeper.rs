@@ -0,0 +1,76 @@
+//! Background thread that periodically runs an idle-model sweep
+//!
+//! Without it, idle models are only unloaded as a side effect of a later
+//! load or transcription, so a model used once stays resident indefinitely.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Handle to a running sweeper thread
+pub struct IdleSweeper {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl IdleSweeper {
+    /// Spawn a thread that calls `sweep` every `interval` until stopped
+    ///
+    /// The thread only waits on a channel between sweeps, so it holds no
+    /// locks while sleeping and wakes immediately when stopped.
+    pub fn start(interval: Duration, sweep: impl Fn() + Send + 'static) -> std::io::Result<Self> {
+        let (stop, stopped) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("whisper-idle-sweeper".to_string())
+            .spawn(move || {
+                // A stop message or a dropped sender both end the loop
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    sweep();
+                }
+            })?;
+        Ok(Self { stop, thread })
+    }
+
+    /// Signal the thread to exit and wait for it, including any sweep in progress
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.thread.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[test]
+    fn test_sweeps_until_stopped() {
+        let sweeps = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&sweeps);
+        let sweeper = IdleSweeper::start(Duration::from_millis(5), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sweeps.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        sweeper.stop();
+
+        let after_stop = sweeps.load(Ordering::SeqCst);
+        assert!(after_stop >= 2);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(sweeps.load(Ordering::SeqCst), after_stop);
+    }
+
+    #[test]
+    fn test_stop_interrupts_long_interval() {
+        let sweeper = IdleSweeper::start(Duration::from_secs(3600), || {}).unwrap();
+        let start = Instant::now();
+        sweeper.stop();
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
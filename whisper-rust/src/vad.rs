@@ -0,0 +1,77 @@
+//! Energy-based voice activity detection
+//!
+//! A deliberately simple detector used to trim leading and trailing silence
+//! before inference. Silent tails waste decode time and are where whisper
+//! tends to hallucinate text, so trimming them helps both latency and output.
+
+/// Sample rate the detector assumes for input audio
+const SAMPLE_RATE: usize = 16000;
+
+/// Analysis frame length
+const FRAME_MS: usize = 20;
+
+/// Audio kept before the first speech frame so quiet onsets aren't clipped
+pub const DEFAULT_PRE_ROLL_MS: u32 = 100;
+
+/// Frame RMS above which a frame counts as speech
+pub const DEFAULT_THRESHOLD: f32 = 0.01;
+
+/// Find the speech region of `samples`
+///
+/// Returns the half-open sample range `(start, end)` from the first to the
+/// last frame whose RMS exceeds `threshold`. A buffer without speech yields
+/// an empty range. Apply [`with_pre_roll`] to the start before decoding.
+pub fn trim_silence(samples: &[f32], threshold: f32) -> (usize, usize) {
+    let frame_len = FRAME_MS * SAMPLE_RATE / 1000;
+    let speech: Vec<bool> = samples
+        .chunks(frame_len)
+        .map(|frame| crate::audio::rms(frame) > threshold)
+        .collect();
+
+    let (Some(first), Some(last)) = (speech.iter().position(|&s| s), speech.iter().rposition(|&s| s)) else {
+        return (0, 0);
+    };
+    (first * frame_len, ((last + 1) * frame_len).min(samples.len()))
+}
+
+/// Move a detected speech start back by `pre_roll_ms`, clamped to the buffer start
+pub fn with_pre_roll(start: usize, pre_roll_ms: u32) -> usize {
+    start.saturating_sub(pre_roll_ms as usize * SAMPLE_RATE / 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_trims_silence_speech_silence() {
+        // 1s silence, 0.5s speech, 1s silence
+        let mut samples = vec![0.0f32; 16000];
+        samples.extend(tone(8000, 0.3));
+        samples.extend(vec![0.0f32; 16000]);
+
+        let (start, end) = trim_silence(&samples, DEFAULT_THRESHOLD);
+        assert_eq!((start, end), (16000, 24000));
+        assert_eq!(with_pre_roll(start, DEFAULT_PRE_ROLL_MS), 16000 - 1600);
+    }
+
+    #[test]
+    fn test_pre_roll_clamps_at_buffer_start() {
+        let mut samples = tone(3200, 0.3);
+        samples.extend(vec![0.0f32; 3200]);
+        let (start, end) = trim_silence(&samples, DEFAULT_THRESHOLD);
+        assert_eq!((with_pre_roll(start, DEFAULT_PRE_ROLL_MS), end), (0, 3200));
+    }
+
+    #[test]
+    fn test_silence_only_is_empty() {
+        assert_eq!(trim_silence(&[0.0; 16000], DEFAULT_THRESHOLD), (0, 0));
+        assert_eq!(trim_silence(&[], DEFAULT_THRESHOLD), (0, 0));
+    }
+}
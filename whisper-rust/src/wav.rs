@@ -0,0 +1,152 @@
+//! WAV file decoding into whisper's 16kHz mono input format
+//!
+//! Supports 16-bit integer and 32-bit float PCM, including the
+//! WAVE_FORMAT_EXTENSIBLE wrapper, at any sample rate and channel count.
+
+use crate::{audio, resample};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Sample layout from a WAV file's `fmt ` chunk
+#[derive(Debug, Clone, Copy)]
+struct WavFormat {
+    format: u16, // FORMAT_PCM or FORMAT_IEEE_FLOAT, unwrapped from extensible
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+/// Read a PCM WAV file as 16kHz mono f32 samples
+pub fn load_wav_16k_mono(path: &str) -> Result<Vec<f32>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    decode_wav_16k_mono(&bytes)
+}
+
+/// Decode an in-memory WAV file as 16kHz mono f32 samples
+pub fn decode_wav_16k_mono(bytes: &[u8]) -> Result<Vec<f32>, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body_start = offset + 8;
+        // Writers streaming to a pipe leave the data size unset, so clamp to the file
+        let body = &bytes[body_start..body_start.saturating_add(size).min(bytes.len())];
+        match id {
+            b"fmt " => format = Some(parse_format(body)?),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        offset = body_start.saturating_add(size).saturating_add(size & 1);
+    }
+
+    let format = format.ok_or("WAV file has no fmt chunk")?;
+    let data = data.ok_or("WAV file has no data chunk")?;
+
+    let interleaved: Vec<f32> = match (format.format, format.bits_per_sample) {
+        (FORMAT_PCM, 16) => data
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as f32 / 32768.0)
+            .collect(),
+        (FORMAT_IEEE_FLOAT, 32) => data
+            .chunks_exact(4)
+            .map(|sample| f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]))
+            .collect(),
+        (FORMAT_PCM, bits) => return Err(format!("Unsupported WAV format: {}-bit integer PCM", bits)),
+        (FORMAT_IEEE_FLOAT, bits) => return Err(format!("Unsupported WAV format: {}-bit float", bits)),
+        (tag, _) => return Err(format!("Unsupported WAV format tag 0x{:04x}", tag)),
+    };
+
+    let mono = audio::downmix_to_mono(&interleaved, format.channels);
+    Ok(resample::resample_to_16k(&mono, format.sample_rate).into_owned())
+}
+
+fn parse_format(body: &[u8]) -> Result<WavFormat, String> {
+    if body.len() < 16 {
+        return Err("WAV fmt chunk is truncated".to_string());
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+
+    let mut format = u16_at(0);
+    if format == FORMAT_EXTENSIBLE {
+        // The real format is the first two bytes of the subformat GUID
+        if body.len() < 26 {
+            return Err("WAV extensible fmt chunk is truncated".to_string());
+        }
+        format = u16_at(24);
+    }
+
+    let channels = u16_at(2);
+    let sample_rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+    if channels == 0 || sample_rate == 0 {
+        return Err("WAV file declares no channels or a zero sample rate".to_string());
+    }
+
+    Ok(WavFormat { format, channels, sample_rate, bits_per_sample: u16_at(14) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a WAV file with the given format tag, holding `samples` as raw bytes
+    fn wav(format: u16, channels: u16, sample_rate: u32, bits: u16, samples: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&format.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&bits.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        out.extend_from_slice(samples);
+        out
+    }
+
+    #[test]
+    fn test_int16_stereo_file_is_downmixed() {
+        // Left at half scale, right silent: every mono sample is a quarter scale
+        let frames: Vec<u8> = (0..1600)
+            .flat_map(|_| [16384i16.to_le_bytes(), 0i16.to_le_bytes()].concat())
+            .collect();
+        let path = std::env::temp_dir().join("whisper_wav_test_int16.wav");
+        std::fs::write(&path, wav(FORMAT_PCM, 2, 16000, 16, &frames)).unwrap();
+
+        let samples = load_wav_16k_mono(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|&s| (s - 0.25).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_float_file_is_resampled() {
+        let samples: Vec<u8> = (0..4800).flat_map(|_| 0.5f32.to_le_bytes()).collect();
+        let decoded = decode_wav_16k_mono(&wav(FORMAT_IEEE_FLOAT, 1, 48000, 32, &samples)).unwrap();
+        assert_eq!(decoded.len(), 1600);
+        assert!((decoded[800] - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_unsupported_formats_are_errors() {
+        let err = decode_wav_16k_mono(&wav(FORMAT_PCM, 1, 16000, 24, &[0; 30])).unwrap_err();
+        assert!(err.contains("24-bit"), "{}", err);
+        assert!(decode_wav_16k_mono(&wav(0x0055, 1, 16000, 16, &[0; 32])).is_err()); // MP3
+        assert!(decode_wav_16k_mono(b"RIFF\0\0\0\0WAVE").is_err());
+        assert!(decode_wav_16k_mono(b"not a wav file").is_err());
+        assert!(load_wav_16k_mono("/nonexistent/clip.wav").is_err());
+    }
+}